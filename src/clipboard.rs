@@ -0,0 +1,252 @@
+//! Portable clipboard blobs for copy / paste within and between documents.
+//! Little-endian binary: magic `RSCB`, a version, the lines, the curves of their
+//! strokes, then the fills with their paint. Fill colors are stored resolved,
+//! since swatch ids only mean something in the palette they came from; stroke
+//! ids are stored relative to the blob (1 = its first stroke) for the same reason.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::curves::{CubicBez, Curve};
+use crate::paint::{GradientStop, Paint};
+use crate::pattern::PatternKind;
+use crate::{Line, Polygon};
+
+const MAGIC: &[u8; 4] = b"RSCB";
+const VERSION: u32 = 2; // 2: stroke per line and curves (1 is still read)
+
+/// Why a blob could not be pasted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipError {
+    BadMagic,
+    BadVersion,
+    /// Ends before the counts say it should
+    Truncated,
+    /// Unknown paint or pattern kind, or a non-finite coordinate
+    BadData,
+}
+
+impl ClipError {
+    /// Stable code for the FFI boundary (0 is reserved for "no error")
+    pub fn code(self) -> u32 {
+        match self {
+            ClipError::BadMagic => 1,
+            ClipError::BadVersion => 2,
+            ClipError::Truncated => 3,
+            ClipError::BadData => 4,
+        }
+    }
+}
+
+/// Decoded clipboard contents; ids are unassigned (0) and strokes (on lines and
+/// curves) are blob-relative, ascending in `curves`
+pub struct Clip {
+    pub lines: Vec<Line>,
+    pub curves: Vec<Curve>,
+    pub fills: Vec<Polygon>,
+}
+
+/// Serialize lines, curves and fills. Line strokes must already be blob-relative
+/// (0 = plain line); `curves` pairs each curve with its blob-relative stroke, in
+/// ascending order, and `fills` each polygon with its resolved color.
+pub fn encode(lines: &[Line], curves: &[(u32, &Curve)], fills: &[(&Polygon, u32)]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(MAGIC);
+    put_u32(&mut out, VERSION);
+    put_u32(&mut out, lines.len() as u32);
+    for line in lines.iter() {
+        for v in [line.x1, line.y1, line.x2, line.y2] {
+            put_f32(&mut out, v);
+        }
+        put_u32(&mut out, line.stroke);
+    }
+    put_u32(&mut out, curves.len() as u32);
+    for &(stroke, curve) in curves.iter() {
+        put_u32(&mut out, stroke);
+        put_u32(&mut out, curve.point_count as u32);
+        put_u32(&mut out, curve.segments.len() as u32);
+        let (x0, y0) = curve.segments[0].p0;
+        put_f32(&mut out, x0);
+        put_f32(&mut out, y0);
+        for seg in curve.segments.iter() {
+            for v in [seg.p1.0, seg.p1.1, seg.p2.0, seg.p2.1, seg.p3.0, seg.p3.1] {
+                put_f32(&mut out, v);
+            }
+        }
+    }
+    put_u32(&mut out, fills.len() as u32);
+    for &(fill, color) in fills.iter() {
+        put_u32(&mut out, color);
+        put_u32(&mut out, fill.points.len() as u32);
+        for &(x, y) in fill.points.iter() {
+            put_f32(&mut out, x);
+            put_f32(&mut out, y);
+        }
+        put_paint(&mut out, &fill.paint);
+    }
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Clip, ClipError> {
+    if bytes.len() < 4 || &bytes[..4] != MAGIC {
+        return Err(ClipError::BadMagic);
+    }
+    let mut r = Reader { bytes, pos: 4 };
+    let version = r.u32()?;
+    if version == 0 || version > VERSION {
+        return Err(ClipError::BadVersion);
+    }
+    let with_strokes = version >= 2;
+
+    let line_count = r.count(if with_strokes { 20 } else { 16 })?;
+    let mut lines: Vec<Line> = Vec::with_capacity(line_count);
+    for _ in 0..line_count {
+        let mut line = Line::new(r.f32()?, r.f32()?, r.f32()?, r.f32()?);
+        if with_strokes {
+            // A blob has at most one stroke per line
+            line.stroke = r.u32()?;
+            if line.stroke as usize > line_count {
+                return Err(ClipError::BadData);
+            }
+        }
+        lines.push(line);
+    }
+
+    let max_stroke = lines.iter().map(|l| l.stroke).max().unwrap_or(0);
+    let mut curves: Vec<Curve> = Vec::new();
+    if with_strokes {
+        let curve_count = r.count(20)?;
+        for _ in 0..curve_count {
+            let stroke = r.u32()?;
+            let point_count = r.u32()? as usize;
+            let segment_count = r.count(24)?;
+            let after = curves.last().map(|c: &Curve| c.stroke).unwrap_or(0);
+            if stroke <= after || stroke > max_stroke || segment_count == 0 {
+                return Err(ClipError::BadData);
+            }
+            let mut segments: Vec<CubicBez> = Vec::with_capacity(segment_count);
+            let mut p0 = (r.f32()?, r.f32()?);
+            for _ in 0..segment_count {
+                let (p1, p2, p3) = ((r.f32()?, r.f32()?), (r.f32()?, r.f32()?), (r.f32()?, r.f32()?));
+                segments.push(CubicBez { p0, p1, p2, p3 });
+                p0 = p3;
+            }
+            curves.push(Curve { stroke, segments, point_count });
+        }
+    }
+
+    let fill_count = r.count(12)?;
+    let mut fills: Vec<Polygon> = Vec::with_capacity(fill_count);
+    for _ in 0..fill_count {
+        let mut fill = Polygon::new().with_color(r.u32()?);
+        let point_count = r.count(8)?;
+        for _ in 0..point_count {
+            fill.push(r.f32()?, r.f32()?);
+        }
+        fill.paint = read_paint(&mut r)?;
+        fills.push(fill);
+    }
+    Ok(Clip { lines, curves, fills })
+}
+
+// Paint: kind (Paint::kind), then linear [x1 y1 x2 y2 stops], radial [cx cy r stops]
+// or pattern [pattern_kind angle spacing]; stops are [count, (offset, color) * count]
+fn put_paint(out: &mut Vec<u8>, paint: &Paint) {
+    put_u32(out, paint.kind());
+    match paint {
+        Paint::Solid => {}
+        Paint::Linear { start, end, stops } => {
+            for v in [start.0, start.1, end.0, end.1] {
+                put_f32(out, v);
+            }
+            put_stops(out, stops);
+        }
+        Paint::Radial { center, radius, stops } => {
+            for v in [center.0, center.1, *radius] {
+                put_f32(out, v);
+            }
+            put_stops(out, stops);
+        }
+        Paint::Pattern { kind, angle, spacing } => {
+            put_u32(out, kind.code());
+            put_f32(out, *angle);
+            put_f32(out, *spacing);
+        }
+    }
+}
+
+fn put_stops(out: &mut Vec<u8>, stops: &[GradientStop]) {
+    put_u32(out, stops.len() as u32);
+    for stop in stops.iter() {
+        put_f32(out, stop.offset);
+        put_u32(out, stop.color);
+    }
+}
+
+fn read_paint(r: &mut Reader) -> Result<Paint, ClipError> {
+    Ok(match r.u32()? {
+        0 => Paint::Solid,
+        1 => Paint::Linear {
+            start: (r.f32()?, r.f32()?),
+            end: (r.f32()?, r.f32()?),
+            stops: read_stops(r)?,
+        },
+        2 => Paint::Radial {
+            center: (r.f32()?, r.f32()?),
+            radius: r.f32()?,
+            stops: read_stops(r)?,
+        },
+        3 => Paint::Pattern {
+            kind: PatternKind::from_code(r.u32()?).ok_or(ClipError::BadData)?,
+            angle: r.f32()?,
+            spacing: r.f32()?,
+        },
+        _ => return Err(ClipError::BadData),
+    })
+}
+
+fn read_stops(r: &mut Reader) -> Result<Vec<GradientStop>, ClipError> {
+    let count = r.count(8)?;
+    let mut stops: Vec<GradientStop> = Vec::with_capacity(count);
+    for _ in 0..count {
+        stops.push(GradientStop { offset: r.f32()?, color: r.u32()? });
+    }
+    Ok(stops)
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_f32(out: &mut Vec<u8>, v: f32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u32(&mut self) -> Result<u32, ClipError> {
+        let end = self.pos + 4;
+        let chunk = self.bytes.get(self.pos..end).ok_or(ClipError::Truncated)?;
+        self.pos = end;
+        Ok(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, ClipError> {
+        let v = f32::from_bits(self.u32()?);
+        if v.is_finite() { Ok(v) } else { Err(ClipError::BadData) }
+    }
+
+    // Element count, checked against the bytes left so a corrupt count cannot
+    // trigger a huge allocation
+    fn count(&mut self, min_item_size: usize) -> Result<usize, ClipError> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(min_item_size) > self.bytes.len() - self.pos {
+            return Err(ClipError::Truncated);
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,231 @@
+//! Color parsing and the document palette.
+//! Colors are packed as 0xRRGGBBAA. Fills may reference a palette swatch by id,
+//! in which case the swatch color is resolved whenever fills are exported.
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Why a color string was rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorError {
+    Empty,
+    NotUtf8,
+    /// `#` followed by something other than 3, 4, 6 or 8 hex digits
+    BadHex,
+    /// `rgb(`/`hsl(` without a closing paren or with the wrong number of components
+    BadSyntax,
+    /// A component that is not a number or lies outside its range
+    BadComponent,
+    UnknownFormat,
+}
+
+impl ColorError {
+    /// Stable code for the FFI boundary (0 is reserved for "no error")
+    pub fn code(self) -> u32 {
+        match self {
+            ColorError::Empty => 1,
+            ColorError::NotUtf8 => 2,
+            ColorError::BadHex => 3,
+            ColorError::BadSyntax => 4,
+            ColorError::BadComponent => 5,
+            ColorError::UnknownFormat => 6,
+        }
+    }
+}
+
+/// Parse `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`, `rgba()`, `hsl()` or `hsla()`.
+/// Functional forms accept comma or space separated components with an optional
+/// `/ alpha`; rgb channels may be 0-255 or percentages, alpha 0-1 or a percentage.
+pub fn parse_color(input: &[u8]) -> Result<u32, ColorError> {
+    let text = core::str::from_utf8(input).map_err(|_| ColorError::NotUtf8)?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ColorError::Empty);
+    }
+    if let Some(hex) = text.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+
+    let lower = text.to_ascii_lowercase();
+    let (name, args) = match lower.find('(') {
+        Some(open) if lower.ends_with(')') => (lower[..open].trim(), &lower[open + 1..lower.len() - 1]),
+        Some(_) => return Err(ColorError::BadSyntax),
+        None => return Err(ColorError::UnknownFormat),
+    };
+    let (parts, alpha) = split_components(args)?;
+    let a = match alpha {
+        Some(token) => parse_alpha(token)?,
+        None => 255,
+    };
+    match name {
+        "rgb" | "rgba" => {
+            let r = parse_channel(parts[0])?;
+            let g = parse_channel(parts[1])?;
+            let b = parse_channel(parts[2])?;
+            Ok(pack(r, g, b, a))
+        }
+        "hsl" | "hsla" => {
+            let h = parse_number(parts[0].trim_end_matches("deg"))?;
+            let s = parse_percent(parts[1])?;
+            let l = parse_percent(parts[2])?;
+            let (r, g, b) = hsl_to_rgb(h, s, l);
+            Ok(pack(r, g, b, a))
+        }
+        _ => Err(ColorError::UnknownFormat),
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<u32, ColorError> {
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ColorError::BadHex);
+    }
+    let value = u32::from_str_radix(hex, 16).map_err(|_| ColorError::BadHex)?;
+    // Short forms repeat each nibble: #abc == #aabbcc
+    let nibble = |shift: u32| ((value >> shift) & 0xF) * 0x11;
+    match hex.len() {
+        3 => Ok(pack(nibble(8), nibble(4), nibble(0), 0xFF)),
+        4 => Ok(pack(nibble(12), nibble(8), nibble(4), nibble(0))),
+        6 => Ok((value << 8) | 0xFF),
+        8 => Ok(value),
+        _ => Err(ColorError::BadHex),
+    }
+}
+
+// Returns the three color components and the optional alpha token
+fn split_components(args: &str) -> Result<([&str; 3], Option<&str>), ColorError> {
+    let (body, slash_alpha) = match args.split_once('/') {
+        Some((body, alpha)) => (body, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let mut tokens = body
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|t| !t.is_empty());
+    let mut parts = [""; 3];
+    for part in parts.iter_mut() {
+        *part = tokens.next().ok_or(ColorError::BadSyntax)?;
+    }
+    let comma_alpha = tokens.next();
+    if tokens.next().is_some() || (comma_alpha.is_some() && slash_alpha.is_some()) {
+        return Err(ColorError::BadSyntax);
+    }
+    Ok((parts, slash_alpha.or(comma_alpha)))
+}
+
+fn parse_number(token: &str) -> Result<f32, ColorError> {
+    let value: f32 = token.parse().map_err(|_| ColorError::BadComponent)?;
+    if value.is_finite() { Ok(value) } else { Err(ColorError::BadComponent) }
+}
+
+// Percentage as a 0-1 fraction
+fn parse_percent(token: &str) -> Result<f32, ColorError> {
+    let value = parse_number(token.strip_suffix('%').ok_or(ColorError::BadComponent)?)?;
+    if (0.0..=100.0).contains(&value) { Ok(value / 100.0) } else { Err(ColorError::BadComponent) }
+}
+
+fn parse_channel(token: &str) -> Result<u32, ColorError> {
+    if token.ends_with('%') {
+        return Ok(unit_to_byte(parse_percent(token)?));
+    }
+    let value = parse_number(token)?;
+    if (0.0..=255.0).contains(&value) { Ok((value + 0.5) as u32) } else { Err(ColorError::BadComponent) }
+}
+
+fn parse_alpha(token: &str) -> Result<u32, ColorError> {
+    if token.ends_with('%') {
+        return Ok(unit_to_byte(parse_percent(token)?));
+    }
+    let value = parse_number(token)?;
+    if (0.0..=1.0).contains(&value) { Ok(unit_to_byte(value)) } else { Err(ColorError::BadComponent) }
+}
+
+fn unit_to_byte(v: f32) -> u32 {
+    (v * 255.0 + 0.5) as u32
+}
+
+fn pack(r: u32, g: u32, b: u32, a: u32) -> u32 {
+    (r << 24) | (g << 16) | (b << 8) | a
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u32, u32, u32) {
+    let mut h = h % 360.0;
+    if h < 0.0 {
+        h += 360.0;
+    }
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c * 0.5;
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (unit_to_byte(r + m), unit_to_byte(g + m), unit_to_byte(b + m))
+}
+
+/// Named document color that fills can reference
+#[derive(Debug, Clone)]
+pub struct Swatch {
+    pub id: u32,
+    pub name: String,
+    pub color: u32,
+}
+
+/// Document-level palette. Ids start at 1 and are never reused, so a fill's
+/// reference stays valid (or simply unresolved) across undo.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub swatches: Vec<Swatch>,
+    next_id: u32,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            swatches: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn add(&mut self, name: &str, color: u32) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.swatches.push(Swatch {
+            id,
+            name: String::from(name),
+            color,
+        });
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Swatch> {
+        self.swatches.iter().find(|s| s.id == id)
+    }
+
+    pub fn position(&self, id: u32) -> Option<usize> {
+        self.swatches.iter().position(|s| s.id == id)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<u32> {
+        self.swatches.iter().find(|s| s.name == name).map(|s| s.id)
+    }
+
+    /// Set a swatch color, returning the previous one
+    pub fn set_color(&mut self, id: u32, color: u32) -> Option<u32> {
+        let swatch = self.swatches.iter_mut().find(|s| s.id == id)?;
+        let previous = swatch.color;
+        swatch.color = color;
+        Some(previous)
+    }
+
+    /// Color for a fill: its swatch when the reference resolves, else its own color
+    pub fn resolve(&self, swatch: Option<u32>, fallback: u32) -> u32 {
+        swatch
+            .and_then(|id| self.get(id))
+            .map(|s| s.color)
+            .unwrap_or(fallback)
+    }
+}
@@ -0,0 +1,326 @@
+//! Smooth strokes from freehand input: Chaikin corner cutting, centripetal
+//! Catmull-Rom interpolation and least-squares cubic Bézier fitting (Schneider,
+//! "An Algorithm for Automatically Fitting Digitized Curves", Graphics Gems 1990).
+//! Curves are kept as cubic segments and flattened adaptively into the points of
+//! ordinary stroke lines, so fills and the graph only ever see lines.
+
+extern crate alloc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{point_segment_nearest, sqrt_precise};
+
+type Point = (f32, f32);
+
+/// Subdivision depth limit of the flattening (at most 2^16 pieces per segment)
+const MAX_FLATTEN_DEPTH: u32 = 16;
+
+/// Newton-Raphson rounds before a fit gives up and splits
+const MAX_REPARAMETERIZE: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveMethod {
+    /// Corner cutting; the result stays a polyline
+    Chaikin,
+    /// Centripetal Catmull-Rom through the points, as cubic segments
+    CatmullRom,
+    /// Least-squares cubic segments within the tolerance (Schneider)
+    Fit,
+}
+
+impl CurveMethod {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(CurveMethod::Chaikin),
+            1 => Some(CurveMethod::CatmullRom),
+            2 => Some(CurveMethod::Fit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicBez {
+    pub p0: Point,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+}
+
+impl CubicBez {
+    pub fn map(&self, f: impl Fn(f32, f32) -> Point) -> Self {
+        Self { p0: f(self.p0.0, self.p0.1), p1: f(self.p1.0, self.p1.1), p2: f(self.p2.0, self.p2.1), p3: f(self.p3.0, self.p3.1) }
+    }
+
+    pub fn eval(&self, t: f32) -> Point {
+        let s = 1.0 - t;
+        let (b0, b1, b2, b3) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+        (
+            b0 * self.p0.0 + b1 * self.p1.0 + b2 * self.p2.0 + b3 * self.p3.0,
+            b0 * self.p0.1 + b1 * self.p1.1 + b2 * self.p2.1 + b3 * self.p3.1,
+        )
+    }
+
+    fn derivative(&self, t: f32) -> Point {
+        let s = 1.0 - t;
+        let (d0, d1, d2) = (sub(self.p1, self.p0), sub(self.p2, self.p1), sub(self.p3, self.p2));
+        let (b0, b1, b2) = (3.0 * s * s, 6.0 * s * t, 3.0 * t * t);
+        (b0 * d0.0 + b1 * d1.0 + b2 * d2.0, b0 * d0.1 + b1 * d1.1 + b2 * d2.1)
+    }
+
+    fn second_derivative(&self, t: f32) -> Point {
+        let a = add(sub(self.p2, scale(self.p1, 2.0)), self.p0);
+        let b = add(sub(self.p3, scale(self.p2, 2.0)), self.p1);
+        (6.0 * ((1.0 - t) * a.0 + t * b.0), 6.0 * ((1.0 - t) * a.1 + t * b.1))
+    }
+
+    // de Casteljau halves
+    fn split(&self) -> (Self, Self) {
+        let mid = |a: Point, b: Point| ((a.0 + b.0) * 0.5, (a.1 + b.1) * 0.5);
+        let (ab, bc, cd) = (mid(self.p0, self.p1), mid(self.p1, self.p2), mid(self.p2, self.p3));
+        let (abc, bcd) = (mid(ab, bc), mid(bc, cd));
+        let m = mid(abc, bcd);
+        (Self { p0: self.p0, p1: ab, p2: abc, p3: m }, Self { p0: m, p1: bcd, p2: cd, p3: self.p3 })
+    }
+
+    // Control points within `tolerance` of the chord keep the whole curve
+    // (their convex hull) within it too
+    fn is_flat(&self, tolerance: f32) -> bool {
+        let tol2 = tolerance * tolerance;
+        let (a, b) = (self.p0, self.p3);
+        [self.p1, self.p2].iter().all(|p| point_segment_nearest(p.0, p.1, a.0, a.1, b.0, b.1).3 <= tol2)
+    }
+}
+
+/// Cubic segments of one stroke; the stroke's lines are their flattening
+#[derive(Clone, Debug)]
+pub struct Curve {
+    pub stroke: u32,
+    pub segments: Vec<CubicBez>,
+    /// Points of the flattened path, to tell whether the lines still match it
+    pub point_count: usize,
+}
+
+/// Points along `segments` (first segment start included) with every piece
+/// within `tolerance` of the curve; segment ends are kept exactly
+pub fn flatten(segments: &[CubicBez], tolerance: f32) -> Vec<Point> {
+    let mut out: Vec<Point> = Vec::new();
+    let Some(first) = segments.first() else {
+        return out;
+    };
+    out.push(first.p0);
+    let mut stack: Vec<(CubicBez, u32)> = Vec::new();
+    for &segment in segments.iter() {
+        stack.push((segment, 0));
+        while let Some((bez, depth)) = stack.pop() {
+            if depth >= MAX_FLATTEN_DEPTH || bez.is_flat(tolerance) {
+                out.push(bez.p3);
+                continue;
+            }
+            let (left, right) = bez.split();
+            stack.push((right, depth + 1));
+            stack.push((left, depth + 1));
+        }
+    }
+    out
+}
+
+/// Chaikin corner cutting, `iterations` rounds; the end points stay in place
+pub fn chaikin(points: &[Point], iterations: u32) -> Vec<Point> {
+    let mut current = points.to_vec();
+    for _ in 0..iterations {
+        if current.len() < 3 {
+            break;
+        }
+        let mut next: Vec<Point> = Vec::with_capacity(current.len() * 2);
+        next.push(current[0]);
+        for w in current.windows(2) {
+            next.push(lerp(w[0], w[1], 0.25));
+            next.push(lerp(w[0], w[1], 0.75));
+        }
+        next.push(current[current.len() - 1]);
+        current = next;
+    }
+    current
+}
+
+/// Centripetal Catmull-Rom through `points` (consecutive points must differ),
+/// one cubic per step. The ends get a mirrored phantom point.
+pub fn catmull_rom(points: &[Point]) -> Vec<CubicBez> {
+    let n = points.len();
+    let mut out: Vec<CubicBez> = Vec::with_capacity(n.saturating_sub(1));
+    for i in 0..n.saturating_sub(1) {
+        let (p1, p2) = (points[i], points[i + 1]);
+        let p0 = if i > 0 { points[i - 1] } else { sub(scale(p1, 2.0), p2) };
+        let p3 = if i + 2 < n { points[i + 2] } else { sub(scale(p2, 2.0), p1) };
+        // Centripetal knot spacing: square root of the step length
+        let knot = |a: Point, b: Point| sqrt_precise(sqrt_precise(dist(a, b)));
+        let (d1, d2, d3) = (knot(p0, p1), knot(p1, p2), knot(p2, p3));
+        let c1 = if d1 > 0.0 {
+            let w = 2.0 * d1 * d1 + 3.0 * d1 * d2 + d2 * d2;
+            scale(add(sub(scale(p2, d1 * d1), scale(p0, d2 * d2)), scale(p1, w)), 1.0 / (3.0 * d1 * (d1 + d2)))
+        } else {
+            p1
+        };
+        let c2 = if d3 > 0.0 {
+            let w = 2.0 * d3 * d3 + 3.0 * d3 * d2 + d2 * d2;
+            scale(add(sub(scale(p1, d3 * d3), scale(p3, d2 * d2)), scale(p2, w)), 1.0 / (3.0 * d3 * (d3 + d2)))
+        } else {
+            p2
+        };
+        out.push(CubicBez { p0: p1, p1: c1, p2: c2, p3: p2 });
+    }
+    out
+}
+
+/// Fewest cubics (by recursive splitting) keeping every point within `error`.
+/// Consecutive points must differ.
+pub fn fit_cubics(points: &[Point], error: f32) -> Vec<CubicBez> {
+    let n = points.len();
+    let mut out: Vec<CubicBez> = Vec::new();
+    if n < 2 {
+        return out;
+    }
+    let error2 = error * error;
+    let start_tangent = unit(sub(points[1], points[0]));
+    let end_tangent = unit(sub(points[n - 2], points[n - 1]));
+    // Explicit stack, right half pushed first so segments come out in order
+    let mut pending: Vec<(usize, usize, Point, Point)> = vec![(0, n - 1, start_tangent, end_tangent)];
+    while let Some((first, last, t1, t2)) = pending.pop() {
+        match fit_span(&points[first..=last], t1, t2, error2) {
+            Ok(bez) => out.push(bez),
+            Err(split) => {
+                let split = first + split;
+                let center = unit(sub(points[split - 1], points[split + 1]));
+                pending.push((split, last, scale(center, -1.0), t2));
+                pending.push((first, split, t1, center));
+            }
+        }
+    }
+    out
+}
+
+// One cubic for the span, or the index of the worst point to split at
+fn fit_span(points: &[Point], t1: Point, t2: Point, error2: f32) -> Result<CubicBez, usize> {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if points.len() == 2 {
+        let d = sqrt_precise(dist(first, last)) / 3.0;
+        return Ok(CubicBez { p0: first, p1: add(first, scale(t1, d)), p2: add(last, scale(t2, d)), p3: last });
+    }
+    let mut u = chord_lengths(points);
+    let mut bez = generate_bezier(points, &u, t1, t2);
+    let (mut max_err, mut split) = max_error(points, &bez, &u);
+    if max_err < error2 {
+        return Ok(bez);
+    }
+    // Close enough that better parameters may do
+    if max_err < error2 * 4.0 {
+        for _ in 0..MAX_REPARAMETERIZE {
+            reparameterize(points, &mut u, &bez);
+            bez = generate_bezier(points, &u, t1, t2);
+            (max_err, split) = max_error(points, &bez, &u);
+            if max_err < error2 {
+                return Ok(bez);
+            }
+        }
+    }
+    Err(split)
+}
+
+// Normalized cumulative chord length per point
+fn chord_lengths(points: &[Point]) -> Vec<f32> {
+    let mut u: Vec<f32> = Vec::with_capacity(points.len());
+    u.push(0.0);
+    for w in points.windows(2) {
+        let last = u[u.len() - 1];
+        u.push(last + sqrt_precise(dist(w[0], w[1])));
+    }
+    let total = u[u.len() - 1];
+    let n = (u.len() - 1) as f32;
+    for (i, v) in u.iter_mut().enumerate() {
+        *v = if total > 0.0 { *v / total } else { i as f32 / n };
+    }
+    u
+}
+
+// Least-squares inner control point distances along the end tangents
+fn generate_bezier(points: &[Point], u: &[f32], t1: Point, t2: Point) -> CubicBez {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let (mut c00, mut c01, mut c11, mut x0, mut x1) = (0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    for (&p, &t) in points.iter().zip(u.iter()) {
+        let s = 1.0 - t;
+        let (b0, b1, b2, b3) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+        let (a0, a1) = (scale(t1, b1), scale(t2, b2));
+        c00 += dot(a0, a0);
+        c01 += dot(a0, a1);
+        c11 += dot(a1, a1);
+        let rest = sub(p, add(scale(first, b0 + b1), scale(last, b2 + b3)));
+        x0 += dot(a0, rest);
+        x1 += dot(a1, rest);
+    }
+    let det = c00 * c11 - c01 * c01;
+    let (mut alpha1, mut alpha2) = if det != 0.0 { ((x0 * c11 - x1 * c01) / det, (c00 * x1 - c01 * x0) / det) } else { (0.0, 0.0) };
+    // Degenerate or backwards handles: fall back to a third of the chord
+    let chord = sqrt_precise(dist(first, last));
+    if alpha1 < chord * 1e-6 || alpha2 < chord * 1e-6 || !alpha1.is_finite() || !alpha2.is_finite() {
+        alpha1 = chord / 3.0;
+        alpha2 = chord / 3.0;
+    }
+    CubicBez { p0: first, p1: add(first, scale(t1, alpha1)), p2: add(last, scale(t2, alpha2)), p3: last }
+}
+
+// Largest squared distance of an interior point from its parameter on the
+// curve, and that point's index
+fn max_error(points: &[Point], bez: &CubicBez, u: &[f32]) -> (f32, usize) {
+    let mut worst = (0.0f32, points.len() / 2);
+    for i in 1..points.len() - 1 {
+        let d = dist(bez.eval(u[i]), points[i]);
+        if d > worst.0 {
+            worst = (d, i);
+        }
+    }
+    worst
+}
+
+// One Newton-Raphson step per parameter towards the closest curve point
+fn reparameterize(points: &[Point], u: &mut [f32], bez: &CubicBez) {
+    for (&p, t) in points.iter().zip(u.iter_mut()) {
+        let diff = sub(bez.eval(*t), p);
+        let (d1, d2) = (bez.derivative(*t), bez.second_derivative(*t));
+        let denominator = dot(d1, d1) + dot(diff, d2);
+        if denominator != 0.0 {
+            *t = (*t - dot(diff, d1) / denominator).clamp(0.0, 1.0);
+        }
+    }
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: Point, s: f32) -> Point {
+    (a.0 * s, a.1 * s)
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+// Squared distance
+fn dist(a: Point, b: Point) -> f32 {
+    let d = sub(a, b);
+    dot(d, d)
+}
+
+fn unit(a: Point) -> Point {
+    let len = sqrt_precise(dot(a, a));
+    if len > 0.0 { scale(a, 1.0 / len) } else { a }
+}
@@ -40,7 +40,7 @@ pub struct Edge {
     pub ay: f32,
     pub bx: f32,
     pub by: f32,
-    pub line_idx: usize,
+    pub line_id: u32, // Stable id of the source line
 }
 
 /// Disjoint Set Union (Union-Find) with path compression
@@ -166,7 +166,7 @@ impl GraphStore {
         id
     }
 
-    pub fn add_segment(&mut self, ax: f32, ay: f32, bx: f32, by: f32, line_idx: usize) -> EdgeId {
+    pub fn add_segment(&mut self, ax: f32, ay: f32, bx: f32, by: f32, line_id: u32) -> EdgeId {
         let node_a = self.get_or_create_node(ax, ay);
         let node_b = self.get_or_create_node(bx, by);
 
@@ -197,7 +197,7 @@ impl GraphStore {
             ay,
             bx,
             by,
-            line_idx,
+            line_id,
         });
 
         edge_id
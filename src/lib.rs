@@ -10,8 +10,18 @@ extern crate alloc;
 
 mod graph;
 mod debug_checks;
-
+mod color;
+mod paint;
+mod pattern;
+mod transform;
+mod clipboard;
+mod spatial;
+mod simplify;
+mod curves;
+
+use alloc::collections::BinaryHeap;
 use alloc::vec::Vec;
+use core::cmp::Reverse;
 use core::alloc::{GlobalAlloc, Layout};
 use core::cell::UnsafeCell;
 use core::f32::consts::PI;
@@ -19,6 +29,13 @@ use core::ptr;
 use core::sync::atomic::{AtomicUsize, Ordering};
 use core::hint::spin_loop;
 use graph::GraphStore;
+use color::{parse_color, ColorError, Palette};
+use paint::{parse_stops, Paint};
+use pattern::{expand_pattern, PatternKind, PatternScratch};
+use transform::Affine;
+use spatial::SpatialGrid;
+use simplify::{simplify_points, SimplifyMethod};
+use curves::{catmull_rom, chaikin, fit_cubics, flatten, Curve, CurveMethod};
 use debug_checks::*;
 
 const HEAP_SIZE: usize = 1024 * 1024;
@@ -85,9 +102,34 @@ const SNAP_EPS: f32 = 0.25; // Grid snap for intersection deduplication
 #[allow(dead_code)]
 const MAX_TRACE_STEPS: u32 = 2048;
 const GAP_RADIUS: f32 = 12.0;
+const LEAK_RADIUS: f32 = 40.0; // Default search radius for near-miss gaps
+const LEAK_DETOUR: f32 = 3.0;  // Walk/gap ratio below which a target counts as the same stroke
 const MIN_AREA: f32 = 50.0;
+const TRIM_PICK_RADIUS: f32 = 10.0; // Max cursor distance for the scissors and extend tools
+const EXTEND_MAX_DIST: f32 = 100.0;  // Default reach of the extend tool
+const SNAP_RADIUS: f32 = 8.0;        // Max pointer distance for endpoint, intersection and midpoint snaps
+const SNAP_ANGLE_STEP: f32 = 15.0;   // Degrees between angle snap directions
+const SNAP_GRID_SIZE: f32 = 20.0;    // Default grid spacing
+const WELD_RADIUS: f32 = 4.0;        // Default reach of auto-weld when a line is added
+const SIMPLIFY_TOLERANCE: f32 = 1.0; // Default max deviation when simplifying polyline input
+const CURVE_FLATNESS: f32 = 0.25;    // Max distance of flattened curve segments from the curve
+const CHAIKIN_ITERATIONS: u32 = 3;
 const FRAC_3_PI_4: f32 = PI * 0.75;
 
+// Snap kinds; also the flag bits selecting them in editor_snap
+const SNAP_ENDPOINT: u32 = 1;
+const SNAP_INTERSECTION: u32 = 2;
+const SNAP_MIDPOINT: u32 = 4;
+const SNAP_GRID: u32 = 8;
+const SNAP_ANGLE: u32 = 16;
+
+// Weld kinds in weld_buf
+const WELD_ENDPOINT: u32 = 1;
+const WELD_SEGMENT: u32 = 2; // Onto a segment, which is split there
+
+// Weld destination (x, y) and the segment to split there (line index, t)
+type WeldTarget = (f32, f32, Option<(usize, f32)>);
+
 fn floor_f32(x: f32) -> f32 {
     let truncated = x as i32 as f32;
     if truncated <= x {
@@ -120,20 +162,20 @@ fn find_node_index(nodes: &[Point], p: Point) -> Option<u32> {
     None
 }
 
-fn parse_hex_color(hex: &[u8]) -> u32 {
-    let hex_str = core::str::from_utf8(hex).unwrap_or("747474");
-    let start = if hex_str.starts_with('#') { 1 } else { 0 };
-    let hex_trimmed = &hex_str[start..];
-    let color = u32::from_str_radix(hex_trimmed, 16).unwrap_or(0x747474);
-    (color << 8) | 0xFF
-}
-
 #[derive(Clone, Copy)]
 struct Line {
     x1: f32,
     y1: f32,
     x2: f32,
     y2: f32,
+    id: u32, // Stable id, assigned by the editor (0 until then)
+    stroke: u32, // Polyline stroke the segment belongs to (0 = a plain line)
+}
+
+impl Line {
+    fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self { x1, y1, x2, y2, id: 0, stroke: 0 }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -153,6 +195,9 @@ impl Point {
 struct Seg {
     a: u32,
     b: u32,
+    line: u32, // Source line index
+    t0: f32,   // Parameter span of the source line covered by this segment
+    t1: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -194,7 +239,6 @@ impl IntersectionRegistry {
     }
 }
 
-#[allow(dead_code)]
 fn other_end(seg: &Seg, node: u32) -> u32 {
     if seg.a == node { seg.b } else { seg.a }
 }
@@ -221,7 +265,6 @@ struct StateKey {
     incoming: u32, // edge identifier (segment ID or special marker)
 }
 
-#[allow(dead_code)]
 #[derive(Clone)]
 struct TraceEntry {
     step: u32,
@@ -232,19 +275,34 @@ struct TraceEntry {
     score: f32, // abs_turn or similar
 }
 
-#[allow(dead_code)]
 struct FillRunStats {
     steps: u32,
     unique_states: u32,
     cand_max: u32,
-    abort_reason: &'static str,
+    abort_reason: Option<FailReason>,
     trace: Vec<TraceEntry>,
 }
 
+impl FillRunStats {
+    fn empty() -> Self {
+        Self {
+            steps: 0,
+            unique_states: 0,
+            cand_max: 0,
+            abort_reason: None,
+            trace: Vec::new(),
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Polygon {
     points: Vec<(f32, f32)>,
-    color: u32,
+    color: u32,           // Own color; fallback when the swatch no longer resolves
+    swatch: Option<u32>,  // Palette swatch id, resolved at export time
+    paint: Paint,         // Solid uses color/swatch; gradients carry their own stops
+    stale: bool,          // Outline no longer matches a closed face (e.g. erased boundary)
+    id: u32,              // Stable id, assigned by the editor (0 until then)
 }
 
 impl Polygon {
@@ -252,6 +310,10 @@ impl Polygon {
         Self {
             points: Vec::new(),
             color: 0x747474FF,
+            swatch: None,
+            paint: Paint::Solid,
+            stale: false,
+            id: 0,
         }
     }
 
@@ -267,14 +329,37 @@ impl Polygon {
         self.color = color;
         self
     }
+
+    fn with_swatch(mut self, swatch: Option<u32>) -> Self {
+        self.swatch = swatch;
+        self
+    }
 }
 
 enum Command {
     Add,
+    AddWelded(Vec<(usize, Line, usize)>), // Added line and the lines its ends split: index, original, pieces
     AddFill,
     AddFrame, // Grouped undo for 4 frame lines
-    Clear(Vec<Line>, Vec<Polygon>),
-    CleanOverhangs(Vec<Line>), // Save previous lines before cleanup
+    AddPolyline(usize, Vec<(usize, Line, usize)>), // Segment count of a polyline stroke and the lines its ends split
+    AddCurve(usize, Vec<(usize, Line, usize)>),    // Same for a curve stroke; its Curve is the last one
+    Clear(Vec<Line>, Vec<Polygon>, Vec<Curve>),
+    // The commands below that remove lines also carry the curves whose stroke
+    // lost its last line (see take_dead_curves)
+    CleanOverhangs(Vec<Line>, Vec<Curve>), // Save previous lines before cleanup
+    TrimFills(Vec<Line>, Vec<Curve>),      // Lines before trimming strokes inside fills
+    TrimAt(usize, Line, usize, Vec<Curve>), // Scissors: line index, original line, pieces left in its place
+    ExtendLine(usize, Line),   // Line index and its geometry before extending
+    Erase(Vec<Line>, Vec<(usize, Polygon)>, Vec<Curve>), // Lines before the erase stroke and fills it re-resolved
+    Simplify(Vec<Line>, Vec<(usize, Polygon)>, Vec<Curve>), // Lines before simplifying the selection and fills it re-resolved
+    Transform(Vec<(usize, Line)>, Vec<(usize, Polygon)>, Vec<(usize, Curve)>), // Selected lines, fills and curves before the transform
+    Paste(usize, usize, usize), // Line, fill and curve counts appended by a paste
+    EditFill(usize, Polygon),  // Fill edited in place (color, swatch, paint) and its previous state
+    DeleteFill(usize, Polygon), // Index the fill lived at and the removed fill
+    SwapFills(usize, usize),   // Z-order swap, undone by swapping back
+    AddSwatch,                 // Swatch appended to the palette
+    SetSwatchColor(u32, u32),  // Swatch id and its previous color
+    RemoveSwatch(usize, color::Swatch), // Palette index of the removed swatch and the swatch
 }
 
 fn distance_sq(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
@@ -363,26 +448,29 @@ fn line_intersection_params(
     None
 }
 
-fn find_nearest_line(lines: &[Line], x: f32, y: f32, max_dist_sq: f32) -> Option<usize> {
-    let mut best_idx = None;
-    let mut best_dist_sq = max_dist_sq;
-
-    for (i, line) in lines.iter().enumerate() {
-        let dist_sq_start = distance_sq(x, y, line.x1, line.y1);
-        let dist_sq_end = distance_sq(x, y, line.x2, line.y2);
-
-        if dist_sq_start < best_dist_sq {
-            best_dist_sq = dist_sq_start;
-            best_idx = Some(i);
-        }
+// Squared distance from the point to the closer endpoint of the line
+fn line_endpoint_dist_sq(line: &Line, x: f32, y: f32) -> f32 {
+    distance_sq(x, y, line.x1, line.y1).min(distance_sq(x, y, line.x2, line.y2))
+}
 
-        if dist_sq_end < best_dist_sq {
-            best_dist_sq = dist_sq_end;
-            best_idx = Some(i);
+// Freehand input cleaned for a stroke: points closer than 1 px to the previous
+// one are dropped; empty when any coordinate is not finite
+fn stroke_points(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    if points.iter().any(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Vec::new();
+    }
+    let mut chain: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+    for &(x, y) in points.iter() {
+        if chain.last().map(|&(lx, ly)| distance_sq(lx, ly, x, y) >= 1.0).unwrap_or(true) {
+            chain.push((x, y));
         }
     }
+    chain
+}
 
-    best_idx
+// Squared distance from the point to the closest point on the line
+fn line_segment_dist_sq(line: &Line, x: f32, y: f32) -> f32 {
+    point_segment_nearest(x, y, line.x1, line.y1, line.x2, line.y2).3
 }
 
 fn find_connected_lines(lines: &[Line], start_idx: usize) -> Vec<usize> {
@@ -493,6 +581,42 @@ struct Editor {
     fill_graph: FillGraph,
     fill_trace_buf: Vec<f32>,
     fills_export_buf: Vec<f32>,
+    swatches_export_buf: Vec<f32>,  // [count, (id, r, g, b, a) * count]
+    pattern_lines_buf: Vec<f32>,    // [count, (fill_idx, x1, y1, x2, y2) * count] expanded pattern fills
+    pattern_cache: Vec<CachedPattern>, // Expansion per pattern fill, redone only when its inputs change
+    pattern_scratch: PatternScratch,
+    pattern_holes: Vec<(f32, f32)>, // Holes of the fill being expanded, packed back to back
+    pattern_hole_ends: Vec<usize>,
+    graph_rev: u32,                 // Bumped on every fill graph rebuild
+    islands_rev: u32,               // graph_rev the islands below were found for
+    node_component: Vec<u32>,       // Island per graph node (u32::MAX: on no cycle)
+    island_points: Vec<(f32, f32)>, // Outer contour per island, packed back to back
+    island_ends: Vec<usize>,
+    overhang_preview_buf: Vec<f32>, // [count, (x1, y1, x2, y2) * count] segments Clean would remove
+    stale_fills_buf: Vec<f32>,      // [count, fill_idx * count] fills whose outline lost its boundary
+    line_ids_buf: Vec<u32>,         // Line ids in export_buf order
+    fill_ids_buf: Vec<u32>,         // Fill ids in fills_export_buf order
+    next_line_id: u32,              // Ids start at 1 and are never reused
+    next_fill_id: u32,
+    next_stroke_id: u32,
+    stroke_simplify: SimplifyMethod, // Applied to polyline input before it becomes lines
+    stroke_tolerance: f32,
+    line_strokes_buf: Vec<u32>,     // Stroke id per line in export_buf order (0 = plain line)
+    strokes_buf: Vec<f32>,          // [count, (stroke_id, point_count, (x, y) * point_count) * count] polylines as paths
+    curves: Vec<Curve>,             // Cubic source of curve strokes, by ascending stroke id
+    curves_buf: Vec<f32>,           // [count, (stroke_id, segment_count, x0, y0, (c1x, c1y, c2x, c2y, x, y) * segment_count) * count]
+    selected_lines: Vec<u32>,       // Selection by stable id; ids that no longer exist are ignored
+    selected_fills: Vec<u32>,
+    selection_buf: Vec<u32>,        // [line_count, line_id * line_count, fill_count, fill_id * fill_count]
+    query_buf: Vec<u32>,            // Last region query, same layout as selection_buf
+    clipboard_buf: Vec<u8>,         // Last copied blob (see clipboard.rs), also the paste input area
+    line_grid: SpatialGrid,         // Lines by grid cell, rebuilt with export_buf
+    fill_grid: SpatialGrid,         // Fill bounding boxes by grid cell, rebuilt with fills_export_buf
+    spatial_buf: Vec<u32>,          // [count, line_id * count] last k-nearest / radius query, closest first
+    snap_grid: f32,                 // Grid spacing for SNAP_GRID
+    snap_buf: Vec<f32>,             // [kind, x, y] last snapped point (kind 0 = unchanged)
+    weld_radius: f32,               // Auto-weld reach for add_line, 0 disables
+    weld_buf: Vec<f32>,             // [count, (end, kind, from_x, from_y, to_x, to_y) * count] welds of the last add_line
     fill_candidates_buf: Vec<f32>,  // Logs candidate segments at each junction
     adjacency_debug_buf: Vec<f32>,  // Dumps all outgoing edges at junction for debugging
     node_outgoing_buf: Vec<f32>,    // Outgoing half-edges for current node
@@ -503,8 +627,20 @@ struct Editor {
     effective_degree: Vec<u32>,     // Effective degree after pruning
     fill_walk_debug_buf: Vec<f32>,  // Walk step debugging
     fill_color: u32,                // Current fill color (RGBA)
+    background_color: u32,          // Canvas color behind all fills (RGBA)
+    fill_swatch: Option<u32>,       // Swatch new fills reference, overrides fill_color
+    palette: Palette,               // Document swatches
+    color_error: Option<ColorError>, // Last rejected color string
+    paste_error: Option<clipboard::ClipError>, // Why the last paste was rejected
     graph_store: GraphStore,        // Incremental closed-component tracker
     last_fill_stats: Vec<f32>,      // [ok, steps, unique_states, cand_max, abort_code] + trace ring
+    leak_buf: Vec<f32>,             // Dangling endpoints + nearest gap target, ranked by gap
+    fill_strategy: FillStrategy,    // Region tracer used by fill_at
+    fill_result_buf: Vec<f32>,      // Structured result of the last fill (see write_fill_result)
+    preview_buf: Vec<f32>,          // [status, count, (x, y) * count] hover preview polygon
+    face_cache: Vec<CachedFace>,    // Clean faces sorted by min x; dropped on graph rebuild
+    face_cache_max_w: f32,          // Widest cached face, bounds the backwards scan
+    preview_miss: Option<PreviewMiss>,
 }
 
 // Compute a simple angle proxy for sorting (0-4 range for quadrants)
@@ -564,6 +700,18 @@ fn sqrt_approx(x: f32) -> f32 {
     guess
 }
 
+// Accurate sqrt for values shown to the user (bit-trick seed + Newton-Raphson)
+fn sqrt_precise(x: f32) -> f32 {
+    if x <= 0.0 || !x.is_finite() {
+        return if x == f32::INFINITY { x } else { 0.0 };
+    }
+    let mut guess = f32::from_bits((x.to_bits() >> 1) + 0x1fbd_1df5);
+    for _ in 0..3 {
+        guess = 0.5 * (guess + x / guess);
+    }
+    guess
+}
+
 // Fast atan2 approximation suitable for ordering angles; deterministic
 fn atan2_approx(y: f32, x: f32) -> f32 {
     if x == 0.0 {
@@ -591,16 +739,36 @@ fn atan2_approx(y: f32, x: f32) -> f32 {
     angle
 }
 
-#[derive(Clone, Copy, Debug)]
-#[allow(dead_code)]
+// sin and cos of an angle in radians (Taylor series after reduction to
+// [-PI/2, PI/2]); error below 1e-6, good enough for geometry
+fn sin_cos_approx(angle: f32) -> (f32, f32) {
+    fn sin_reduced(a: f32) -> f32 {
+        let mut x = a % (2.0 * PI);
+        if x > PI {
+            x -= 2.0 * PI;
+        } else if x < -PI {
+            x += 2.0 * PI;
+        }
+        if x > core::f32::consts::FRAC_PI_2 {
+            x = PI - x;
+        } else if x < -core::f32::consts::FRAC_PI_2 {
+            x = -PI - x;
+        }
+        let x2 = x * x;
+        x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0 * (1.0 - x2 / 110.0)))))
+    }
+    (sin_reduced(angle), sin_reduced(angle + core::f32::consts::FRAC_PI_2))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum FailReason {
     DeadEnd,
     StepLimit,
     PrematureCycle,
-    NoClosure,
+    NoClosure, // Traced, but nothing closed around the origin with enough area
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum SideRule {
     KeepLeft,
     KeepRight,
@@ -633,35 +801,370 @@ struct StepDebug {
 struct TraceResult {
     closed: bool,
     points: Vec<(f32, f32)>,
-    #[allow(dead_code)]
-    fail_reason: Option<FailReason>,
-    #[allow(dead_code)]
-    steps: u32,
+    fail_node: Option<u32>, // Node the walk was standing on when it gave up
+    stats: FillRunStats,
     step_debug: Vec<StepDebug>,
 }
 
 impl TraceResult {
-    fn success(points: Vec<(f32, f32)>, steps: u32, step_debug: Vec<StepDebug>) -> Self {
+    fn success(points: Vec<(f32, f32)>, stats: FillRunStats, step_debug: Vec<StepDebug>) -> Self {
         Self {
             closed: true,
             points,
-            fail_reason: None,
-            steps,
+            fail_node: None,
+            stats,
             step_debug,
         }
     }
 
-    fn fail(reason: FailReason, steps: u32, step_debug: Vec<StepDebug>) -> Self {
+    fn fail(reason: FailReason, mut stats: FillRunStats, fail_node: Option<u32>, step_debug: Vec<StepDebug>) -> Self {
+        stats.abort_reason = Some(reason);
         Self {
             closed: false,
             points: Vec::new(),
-            fail_reason: Some(reason),
-            steps,
+            fail_node,
+            stats,
             step_debug,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum FillStrategy {
+    Graph,  // Cut-segment graph + dual side-locked trace
+    Legacy, // find_nearest_line -> find_connected_lines -> trace_polygon
+}
+
+impl FillStrategy {
+    fn from_code(code: u32) -> Self {
+        match code {
+            1 => FillStrategy::Legacy,
+            _ => FillStrategy::Graph,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FillStatus {
+    Filled,
+    NoGeometry, // Nothing to trace against
+    NoBoundary, // No segment with both ends on a cycle
+    Failed(FailReason),
+}
+
+impl FillStatus {
+    // Status code returned by editor_fill / editor_fill_debug_at
+    fn code(self) -> u32 {
+        match self {
+            FillStatus::Filled => 0,
+            FillStatus::NoGeometry => 1,
+            FillStatus::NoBoundary => 2,
+            FillStatus::Failed(FailReason::DeadEnd) => 3,
+            FillStatus::Failed(FailReason::StepLimit) => 4,
+            FillStatus::Failed(FailReason::PrematureCycle) => 5,
+            FillStatus::Failed(FailReason::NoClosure) => 6,
+        }
+    }
+}
+
+// One attempted direction of the dual trace
+struct SideTrace {
+    from: u32,
+    to: u32,
+    side: SideRule,
+    cross: f32,
+    result: TraceResult,
+}
+
+// Region found by a fill strategy, before anything is committed
+struct RegionTrace {
+    status: FillStatus,
+    points: Vec<(f32, f32)>,
+    start: Option<u32>,    // Graph start node (trace log anchor)
+    sides: Vec<SideTrace>,
+    chosen: Option<usize>, // Side that produced the region, or whose failure is reported
+}
+
+impl RegionTrace {
+    fn empty(status: FillStatus) -> Self {
+        Self {
+            status,
+            points: Vec::new(),
+            start: None,
+            sides: Vec::new(),
+            chosen: None,
+        }
+    }
+}
+
+// Face cached for the hover preview. Its interior holds no geometry, so every
+// point inside it traces back to this same boundary.
+struct CachedFace {
+    bounds: (f32, f32, f32, f32),
+    points: Vec<(f32, f32)>,
+}
+
+// Pattern expansion of one fill with the inputs it was made from
+struct CachedPattern {
+    fill_id: u32,
+    kind: PatternKind,
+    angle: f32,
+    spacing: f32,
+    outline: Vec<(f32, f32)>,
+    holes: Vec<(f32, f32)>,
+    hole_ends: Vec<usize>,
+    segments: Vec<f32>, // (x1, y1, x2, y2) per segment
+}
+
+// Last traced preview region that could not go into the face cache, keyed by
+// the start segment and side it was traced from
+struct PreviewMiss {
+    seg: usize,
+    side: SideRule,
+    status: FillStatus,
+    points: Vec<(f32, f32)>,
+}
+
+struct FillOutcome {
+    status: FillStatus,
+    fill_idx: Option<usize>,
+    steps: u32, // Graph trace steps; 0 when not tracked (legacy strategy)
+    unique_states: u32,
+    side: Option<SideRule>,
+    area: f32,
+    fail_point: Option<(f32, f32)>,
+}
+
+// 2-core leaf stripping: marks every segment that does not lie on a cycle
+// (dangling chains and isolated pieces). Walks node_sectors with per-node
+// alive counters, so each segment is visited a bounded number of times.
+fn overhang_segments(graph: &FillGraph) -> Vec<bool> {
+    let mut deleted = alloc::vec![false; graph.segments.len()];
+    let mut alive: Vec<u32> = graph.node_sectors.iter().map(|s| s.len() as u32).collect();
+    let mut queue: Vec<usize> = (0..alive.len()).filter(|&n| alive[n] == 1).collect();
+
+    while let Some(node) = queue.pop() {
+        // May have dropped to 0 since it was queued
+        if alive[node] != 1 {
+            continue;
+        }
+        let Some(&seg_idx) = graph.node_sectors[node].iter().find(|&&s| !deleted[s as usize]) else {
+            continue;
+        };
+        let seg = &graph.segments[seg_idx as usize];
+        let other = if seg.a as usize == node { seg.b } else { seg.a } as usize;
+        deleted[seg_idx as usize] = true;
+        alive[node] = 0;
+        alive[other] -= 1;
+        if alive[other] == 1 {
+            queue.push(other);
+        }
+    }
+    deleted
+}
+
+// Outer boundary of the cycle segments connected to `start`, which must be the
+// leftmost of their nodes. Walks the face holding the ray west of `start`: at
+// each node the walk turns onto the first live segment counterclockwise from
+// the one it came along, until it is back at `start` about to repeat itself.
+fn outer_contour(graph: &FillGraph, dead: &[bool], start: u32, out: &mut Vec<(f32, f32)>) {
+    let angle = |from: u32, to: u32| {
+        let (a, b) = (graph.nodes[from as usize], graph.nodes[to as usize]);
+        atan2_approx(b.y - a.y, b.x - a.x)
+    };
+    let mut node = start;
+    let mut back = core::f32::consts::PI;
+    let mut first: Option<u32> = None;
+    for _ in 0..=2 * graph.segments.len() {
+        let mut best: Option<(u32, f32)> = None;
+        for &s in graph.node_sectors[node as usize].iter() {
+            if dead[s as usize] {
+                continue;
+            }
+            // In (0, 2PI]: the segment back counts as a full turn
+            let mut turn = angle(node, other_end(&graph.segments[s as usize], node)) - back;
+            while turn <= 0.0 {
+                turn += core::f32::consts::TAU;
+            }
+            if best.map(|(_, t)| turn < t).unwrap_or(true) {
+                best = Some((s, turn));
+            }
+        }
+        let Some((s, _)) = best else {
+            break;
+        };
+        if node == start && first == Some(s) {
+            break;
+        }
+        first.get_or_insert(s);
+        let p = graph.nodes[node as usize];
+        out.push((p.x, p.y));
+        let next = other_end(&graph.segments[s as usize], node);
+        back = angle(next, node);
+        node = next;
+    }
+}
+
+// Rebuild lines with the given parameter spans cut out of each one (spans are
+// indexed by line and may be unsorted). A line with no spans is kept as is;
+// otherwise each remaining run becomes its own line. The first run keeps the
+// line's id, so a shortened line stays the same line; further runs take fresh
+// ids from `next_id`.
+fn lines_without_spans(lines: &[Line], removed: &mut [Vec<(f32, f32)>], next_id: &mut u32) -> Vec<Line> {
+    const T_EPS: f32 = 1e-6;
+    let mut result: Vec<Line> = Vec::with_capacity(lines.len());
+    for (line, spans) in lines.iter().zip(removed.iter_mut()) {
+        if spans.is_empty() {
+            result.push(*line);
+            continue;
+        }
+        spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+        let at = |t: f32| (line.x1 + (line.x2 - line.x1) * t, line.y1 + (line.y2 - line.y1) * t);
+        let mut keep_from = 0.0;
+        let mut first = true;
+        for &(t0, t1) in spans.iter().chain(core::iter::once(&(1.0, 1.0))) {
+            if t0 > keep_from + T_EPS {
+                let (x1, y1) = at(keep_from);
+                let (x2, y2) = at(t0);
+                let id = if first {
+                    line.id
+                } else {
+                    *next_id += 1;
+                    *next_id - 1
+                };
+                first = false;
+                result.push(Line { x1, y1, x2, y2, id, stroke: line.stroke });
+            }
+            if t1 > keep_from {
+                keep_from = t1;
+            }
+        }
+    }
+    result
+}
+
+// Parameter span of `line` inside the capsule of radius r around segment p-q
+// (a disc when p == q). The capsule is convex, so the hull of the disc and slab
+// intervals is exact.
+fn capsule_span(line: &Line, p: (f32, f32), q: (f32, f32), r: f32) -> Option<(f32, f32)> {
+    let (dx, dy) = (line.x2 - line.x1, line.y2 - line.y1);
+    let a = dot(dx, dy, dx, dy);
+    if a < 1e-12 {
+        return None;
+    }
+    let mut span: Option<(f32, f32)> = None;
+    let mut merge = |t0: f32, t1: f32| {
+        span = Some(match span {
+            Some((s0, s1)) => (s0.min(t0), s1.max(t1)),
+            None => (t0, t1),
+        });
+    };
+
+    for c in [p, q] {
+        let (fx, fy) = (line.x1 - c.0, line.y1 - c.1);
+        let b = dot(fx, fy, dx, dy);
+        let disc = b * b - a * (dot(fx, fy, fx, fy) - r * r);
+        if disc >= 0.0 {
+            let root = sqrt_precise(disc);
+            merge((-b - root) / a, (-b + root) / a);
+        }
+    }
+
+    // Slab 0 <= u <= len, |v| <= r in the frame of p-q
+    let len = sqrt_precise(distance_sq(p.0, p.1, q.0, q.1));
+    if len > 1e-6 {
+        let (ux, uy) = ((q.0 - p.0) / len, (q.1 - p.1) / len);
+        let (wx, wy) = (line.x1 - p.0, line.y1 - p.1);
+        let axes = [
+            (dot(wx, wy, ux, uy), dot(dx, dy, ux, uy), 0.0, len),
+            (cross(ux, uy, wx, wy), cross(ux, uy, dx, dy), -r, r),
+        ];
+        let (mut t0, mut t1) = (f32::MIN, f32::MAX);
+        for (s0, ds, lo, hi) in axes {
+            if absf(ds) < 1e-9 {
+                if s0 < lo || s0 > hi {
+                    t1 = f32::MIN;
+                }
+                continue;
+            }
+            let (ta, tb) = ((lo - s0) / ds, (hi - s0) / ds);
+            t0 = t0.max(ta.min(tb));
+            t1 = t1.min(ta.max(tb));
+        }
+        if t0 <= t1 {
+            merge(t0, t1);
+        }
+    }
+
+    let (t0, t1) = span?;
+    let (t0, t1) = (t0.max(0.0), t1.min(1.0));
+    if t1 > t0 { Some((t0, t1)) } else { None }
+}
+
+// A point strictly inside the polygon: middle of the widest even-odd span
+// on a few horizontal scan lines
+fn interior_point(poly: &[(f32, f32)]) -> Option<(f32, f32)> {
+    let (_, miny, _, maxy) = poly_bounds(poly);
+    let eps2 = SNAP_EPS * SNAP_EPS;
+    for k in [0.5, 0.3, 0.7, 0.15, 0.85] {
+        let y = miny + (maxy - miny) * k;
+        let mut xs: Vec<f32> = Vec::new();
+        for i in 0..poly.len() {
+            let (x0, y0) = poly[i];
+            let (x1, y1) = poly[(i + 1) % poly.len()];
+            if (y0 > y) != (y1 > y) {
+                xs.push(x0 + (y - y0) / (y1 - y0) * (x1 - x0));
+            }
+        }
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+        let widest = xs
+            .chunks_exact(2)
+            .max_by(|a, b| (a[1] - a[0]).partial_cmp(&(b[1] - b[0])).unwrap_or(core::cmp::Ordering::Equal));
+        if let Some(pair) = widest {
+            let pt = ((pair[0] + pair[1]) * 0.5, y);
+            if point_in_poly_evenodd(pt, poly) && min_dist_sq_to_polygon(pt, poly) > eps2 {
+                return Some(pt);
+            }
+        }
+    }
+    None
+}
+
+// Fill record: [point_count, r, g, b, a, paint_kind, param_count, params..., (x, y) * point_count]
+// rgba is the solid color (swatch resolved); params are described in Paint::write_params
+fn write_fill_record(buf: &mut Vec<f32>, polygon: &Polygon, palette: &Palette) {
+    buf.push(polygon.points.len() as f32);
+    push_rgba(buf, palette.resolve(polygon.swatch, polygon.color));
+    buf.push(polygon.paint.kind() as f32);
+    let count_at = buf.len();
+    buf.push(0.0);
+    polygon.paint.write_params(buf);
+    buf[count_at] = (buf.len() - count_at - 1) as f32;
+    for (x, y) in polygon.points.iter() {
+        buf.push(*x);
+        buf.push(*y);
+    }
+}
+
+// Append a 0xRRGGBBAA color as four 0-1 floats
+fn push_rgba(buf: &mut Vec<f32>, color: u32) {
+    buf.push((color >> 24) as u8 as f32 / 255.0);
+    buf.push(((color >> 16) & 0xFF) as u8 as f32 / 255.0);
+    buf.push(((color >> 8) & 0xFF) as u8 as f32 / 255.0);
+    buf.push((color & 0xFF) as u8 as f32 / 255.0);
+}
+
+// Preview format: [status, count, (x, y) * count]
+fn write_preview_buf(buf: &mut Vec<f32>, status: FillStatus, points: &[(f32, f32)]) {
+    buf.clear();
+    buf.push(status.code() as f32);
+    buf.push(points.len() as f32);
+    for (x, y) in points.iter() {
+        buf.push(*x);
+        buf.push(*y);
+    }
+}
+
 // Compute signed area of polygon (2x area)
 // Positive = CCW winding, Negative = CW
 fn signed_area_2x(points: &[(f32, f32)]) -> f32 {
@@ -677,6 +1180,22 @@ fn signed_area_2x(points: &[(f32, f32)]) -> f32 {
     area
 }
 
+// Two fill outlines describe the same face when they have the same vertices
+// (within SNAP_EPS), regardless of start vertex, winding or closing point
+fn same_face(a: &[(f32, f32)], b: &[(f32, f32)]) -> bool {
+    let open = |p: &[(f32, f32)]| -> usize {
+        if p.len() > 1 && p.first() == p.last() { p.len() - 1 } else { p.len() }
+    };
+    let (na, nb) = (open(a), open(b));
+    if na != nb || na < 3 {
+        return false;
+    }
+    let eps2 = SNAP_EPS * SNAP_EPS;
+    a[..na].iter().all(|&(ax, ay)| {
+        b[..nb].iter().any(|&(bx, by)| distance_sq(ax, ay, bx, by) <= eps2)
+    })
+}
+
 // Point in polygon using even-odd rule with robustness against vertex hits
 fn point_in_poly_evenodd(pt: (f32, f32), poly: &[(f32, f32)]) -> bool {
     if poly.len() < 3 {
@@ -745,13 +1264,107 @@ fn min_dist_sq_to_polygon(pt: (f32, f32), poly: &[(f32, f32)]) -> f32 {
     min_dist2
 }
 
-// Check if polygon is simple (non-self-intersecting) - quick check
+// Segment a-b meets segment c-d. `strict` counts only proper crossings (each
+// segment's ends on opposite sides of the other); otherwise touching and
+// collinear overlap count too.
+fn segments_intersect(a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32), strict: bool) -> bool {
+    let side = |p: (f32, f32), q: (f32, f32), r: (f32, f32)| cross(q.0 - p.0, q.1 - p.1, r.0 - p.0, r.1 - p.1);
+    let (d1, d2) = (side(a, b, c), side(a, b, d));
+    let (d3, d4) = (side(c, d, a), side(c, d, b));
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0)) {
+        return true;
+    }
+    if strict {
+        return false;
+    }
+    // Touching: an endpoint lying on the other segment
+    let on_segment = |p: (f32, f32), q: (f32, f32), r: (f32, f32)| {
+        r.0 >= p.0.min(q.0) && r.0 <= p.0.max(q.0) && r.1 >= p.1.min(q.1) && r.1 <= p.1.max(q.1)
+    };
+    (d1 == 0.0 && on_segment(a, b, c))
+        || (d2 == 0.0 && on_segment(a, b, d))
+        || (d3 == 0.0 && on_segment(c, d, a))
+        || (d4 == 0.0 && on_segment(c, d, b))
+}
+
+fn segment_meets_polygon_edge(a: (f32, f32), b: (f32, f32), poly: &[(f32, f32)], strict: bool) -> bool {
+    (0..poly.len()).any(|i| segments_intersect(a, b, poly[i], poly[(i + 1) % poly.len()], strict))
+}
+
+// Segment entirely inside the polygon (even-odd). The segment is cut wherever
+// it meets the outline - crossings, vertices it passes through and collinear
+// overlaps - and every piece's midpoint must be inside or on the outline, so a
+// segment that slips out through a vertex does not count.
+fn segment_inside_polygon(a: (f32, f32), b: (f32, f32), poly: &[(f32, f32)]) -> bool {
+    const ON_OUTLINE_SQ: f32 = 1e-4;
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let mut cuts: Vec<f32> = Vec::from([0.0, 1.0]);
+    for i in 0..poly.len() {
+        let (c, d) = (poly[i], poly[(i + 1) % poly.len()]);
+        if !segments_intersect(a, b, c, d, false) {
+            continue;
+        }
+        let (ex, ey) = (d.0 - c.0, d.1 - c.1);
+        let denom = cross(dx, dy, ex, ey);
+        if denom != 0.0 {
+            cuts.push(cross(c.0 - a.0, c.1 - a.1, ex, ey) / denom);
+        } else if len2 > 0.0 {
+            // Collinear overlap: cut at the edge's ends
+            for p in [c, d] {
+                cuts.push(((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2);
+            }
+        }
+    }
+    for t in cuts.iter_mut() {
+        *t = t.clamp(0.0, 1.0);
+    }
+    cuts.sort_by(|x, y| x.partial_cmp(y).unwrap_or(core::cmp::Ordering::Equal));
+    cuts.windows(2).all(|w| {
+        let t = (w[0] + w[1]) * 0.5;
+        let mid = (a.0 + dx * t, a.1 + dy * t);
+        point_in_poly_evenodd(mid, poly) || min_dist_sq_to_polygon(mid, poly) <= ON_OUTLINE_SQ
+    })
+}
+
+// Segment at least partly inside the polygon or touching its outline
+fn segment_touches_polygon(a: (f32, f32), b: (f32, f32), poly: &[(f32, f32)]) -> bool {
+    point_in_poly_evenodd(a, poly) || point_in_poly_evenodd(b, poly) || segment_meets_polygon_edge(a, b, poly, false)
+}
+
+// Shared area or touching outlines
+fn polygons_intersect(p: &[(f32, f32)], q: &[(f32, f32)]) -> bool {
+    p.iter().any(|&v| point_in_poly_evenodd(v, q))
+        || q.iter().any(|&v| point_in_poly_evenodd(v, p))
+        || (0..p.len()).any(|i| segment_meets_polygon_edge(p[i], p[(i + 1) % p.len()], q, false))
+}
+
+fn rect_polygon(x1: f32, y1: f32, x2: f32, y2: f32) -> [(f32, f32); 4] {
+    let (minx, maxx) = (x1.min(x2), x1.max(x2));
+    let (miny, maxy) = (y1.min(y2), y1.max(y2));
+    [(minx, miny), (maxx, miny), (maxx, maxy), (minx, maxy)]
+}
+
+// Check if polygon is simple: no two non-adjacent edges meet. A repeated
+// closing point is ignored.
 fn is_simple_polygon(poly: &[(f32, f32)]) -> bool {
-    if poly.len() < 4 {
+    let n = if poly.len() > 1 && poly.first() == poly.last() { poly.len() - 1 } else { poly.len() };
+    if n < 4 {
         return true;
     }
-    // Quick check: if first == last, it's properly closed
-    poly.first() == poly.last()
+    let edge = |i: usize| (poly[i], poly[(i + 1) % n]);
+    for i in 0..n {
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let ((a, b), (c, d)) = (edge(i), edge(j));
+            if segments_intersect(a, b, c, d, false) {
+                return false;
+            }
+        }
+    }
+    true
 }
 
 // Get polygon bounding box
@@ -793,6 +1406,42 @@ impl Editor {
             },
             fill_trace_buf: Vec::new(),
             fills_export_buf: Vec::new(),
+            swatches_export_buf: Vec::new(),
+            pattern_lines_buf: Vec::new(),
+            pattern_cache: Vec::new(),
+            pattern_scratch: PatternScratch::new(),
+            pattern_holes: Vec::new(),
+            pattern_hole_ends: Vec::new(),
+            graph_rev: 0,
+            islands_rev: 0,
+            node_component: Vec::new(),
+            island_points: Vec::new(),
+            island_ends: Vec::new(),
+            overhang_preview_buf: Vec::new(),
+            stale_fills_buf: Vec::new(),
+            line_ids_buf: Vec::new(),
+            fill_ids_buf: Vec::new(),
+            next_line_id: 1,
+            next_fill_id: 1,
+            next_stroke_id: 1,
+            stroke_simplify: SimplifyMethod::DouglasPeucker,
+            stroke_tolerance: SIMPLIFY_TOLERANCE,
+            line_strokes_buf: Vec::new(),
+            strokes_buf: Vec::new(),
+            curves: Vec::new(),
+            curves_buf: Vec::new(),
+            selected_lines: Vec::new(),
+            selected_fills: Vec::new(),
+            selection_buf: Vec::new(),
+            query_buf: Vec::new(),
+            clipboard_buf: Vec::new(),
+            line_grid: SpatialGrid::new(),
+            fill_grid: SpatialGrid::new(),
+            spatial_buf: Vec::new(),
+            snap_grid: SNAP_GRID_SIZE,
+            snap_buf: Vec::new(),
+            weld_radius: WELD_RADIUS,
+            weld_buf: Vec::new(),
             fill_candidates_buf: Vec::new(),
             adjacency_debug_buf: Vec::new(),
             node_outgoing_buf: Vec::new(),
@@ -803,12 +1452,30 @@ impl Editor {
             effective_degree: Vec::new(),
             fill_walk_debug_buf: Vec::new(),
             fill_color: 0x747474FF,
+            background_color: 0xABABABFF,
+            fill_swatch: None,
+            palette: Palette::new(),
+            color_error: None,
+            paste_error: None,
             graph_store: GraphStore::new(),
             last_fill_stats: Vec::new(),
+            leak_buf: Vec::new(),
+            fill_strategy: FillStrategy::Graph,
+            fill_result_buf: Vec::new(),
+            preview_buf: Vec::new(),
+            face_cache: Vec::new(),
+            face_cache_max_w: 0.0,
+            preview_miss: None,
         }
     }
 
     fn build_fill_graph(&mut self) {
+        // Cached preview faces describe the previous graph
+        self.face_cache.clear();
+        self.face_cache_max_w = 0.0;
+        self.preview_miss = None;
+        self.graph_rev = self.graph_rev.wrapping_add(1);
+
         self.fill_graph.nodes.clear();
         self.fill_graph.segments.clear();
             self.fill_graph.half_edges.clear();
@@ -819,14 +1486,15 @@ impl Editor {
         self.intersections_export.clear();
 
         if self.lines.is_empty() {
+            self.refresh_pattern_lines();
             return;
         }
 
         // Build graph_store from all lines to track closed components
         // This is used ONLY for fill filtering, not for debug/graph visualization
         self.graph_store.clear();
-        for (idx, line) in self.lines.iter().enumerate() {
-            self.graph_store.add_segment(line.x1, line.y1, line.x2, line.y2, idx);
+        for line in self.lines.iter() {
+            self.graph_store.add_segment(line.x1, line.y1, line.x2, line.y2, line.id);
         }
 
         // For debug and graph visualization, we build the FULL graph (all lines)
@@ -876,7 +1544,7 @@ impl Editor {
         }
 
         // Build segments along each line using node ids
-        for nodes_on_line in per_line_nodes.iter_mut() {
+        for (line_idx, nodes_on_line) in per_line_nodes.iter_mut().enumerate() {
             if nodes_on_line.len() < 2 {
                 continue;
             }
@@ -903,7 +1571,13 @@ impl Editor {
                 let a = compact[k].1;
                 let b = compact[k + 1].1;
                 if a != b {
-                    self.fill_graph.segments.push(Seg { a, b });
+                    self.fill_graph.segments.push(Seg {
+                        a,
+                        b,
+                        line: line_idx as u32,
+                        t0: compact[k].0,
+                        t1: compact[k + 1].0,
+                    });
                 }
             }
         }
@@ -1089,6 +1763,8 @@ impl Editor {
         }
 
         self.rebuild_graph_debug_buf();
+        // Pattern holes follow the islands of the new graph
+        self.refresh_pattern_lines();
         
         // Verify fill graph integrity in debug builds
         check_fill_graph_integrity(&self.fill_graph);
@@ -1120,6 +1796,176 @@ impl Editor {
         }
     }
 
+    // Leak detector: every degree-1 endpoint of the cut-segment graph paired with
+    // the nearest geometry within radius that is not just its own stroke continuing.
+    // Format: [count, (ex, ey, tx, ty, gap, kind) * count]
+    // kind: 0 = nothing within radius (tx,ty = ex,ey, gap = -1), 1 = node, 2 = segment interior
+    // Entries with a target come first, sorted by gap ascending.
+    fn analyze_leaks(&mut self, radius: f32) -> u32 {
+        struct Leak {
+            node: u32,
+            target: (f32, f32),
+            target_node: Option<u32>,
+            gap: f32,
+            kind: u32,
+        }
+
+        self.leak_buf.clear();
+
+        let graph = &self.fill_graph;
+        let node_len = graph.nodes.len();
+        let r2 = radius * radius;
+        let walk_limit = radius * LEAK_DETOUR;
+
+        // Bounded shortest-path distances from the endpoint along the graph. A target
+        // reachable by a walk not much longer than the gap is the stroke itself
+        // (e.g. the stub beyond a crossing), not a leak.
+        let mut walk: Vec<f32> = Vec::new();
+        walk.resize(node_len, f32::INFINITY);
+        let mut touched: Vec<u32> = Vec::new();
+        let mut heap: BinaryHeap<Reverse<(u32, u32)>> = BinaryHeap::new();
+        let mut leaks: Vec<Leak> = Vec::new();
+
+        for nid in 0..node_len {
+            if nid >= self.node_degree.len() || self.node_degree[nid] != 1 {
+                continue;
+            }
+            // Degree-1 nodes are always leaf-stripped; anything else is not a leak
+            debug_assert!(nid >= self.allow_node.len() || !self.allow_node[nid]);
+
+            for t in touched.drain(..) {
+                walk[t as usize] = f32::INFINITY;
+            }
+            walk[nid] = 0.0;
+            touched.push(nid as u32);
+            heap.clear();
+            // Non-negative f32 bit patterns order like the values themselves
+            heap.push(Reverse((0.0f32.to_bits(), nid as u32)));
+            while let Some(Reverse((bits, cur))) = heap.pop() {
+                let d = f32::from_bits(bits);
+                let cur = cur as usize;
+                if d > walk[cur] {
+                    continue;
+                }
+                let cp = graph.nodes[cur];
+                for sid in graph.node_sectors[cur].iter() {
+                    let seg = graph.segments[*sid as usize];
+                    let other = other_end(&seg, cur as u32) as usize;
+                    let op = graph.nodes[other];
+                    let nd = d + sqrt_precise(distance_sq(cp.x, cp.y, op.x, op.y));
+                    if nd < walk[other] && nd <= walk_limit {
+                        if walk[other] == f32::INFINITY {
+                            touched.push(other as u32);
+                        }
+                        walk[other] = nd;
+                        heap.push(Reverse((nd.to_bits(), other as u32)));
+                    }
+                }
+            }
+
+            let p = graph.nodes[nid];
+            let mut best: Option<Leak> = None;
+            let mut best_d2 = r2;
+            for seg in graph.segments.iter() {
+                if seg.a as usize == nid || seg.b as usize == nid {
+                    continue;
+                }
+                let a = graph.nodes[seg.a as usize];
+                let b = graph.nodes[seg.b as usize];
+                let (t, qx, qy, d2) = point_segment_nearest(p.x, p.y, a.x, a.y, b.x, b.y);
+                if d2 > best_d2 {
+                    continue;
+                }
+                let gap = sqrt_precise(d2);
+                // Cheapest way to q: walk to another reached node, then jump straight to q
+                let mut walk_q = f32::INFINITY;
+                for t in touched.iter() {
+                    if *t as usize == nid {
+                        continue;
+                    }
+                    let n = graph.nodes[*t as usize];
+                    let w = walk[*t as usize] + sqrt_precise(distance_sq(n.x, n.y, qx, qy));
+                    if w < walk_q {
+                        walk_q = w;
+                    }
+                }
+                if walk_q <= gap * LEAK_DETOUR + SNAP_EPS {
+                    continue;
+                }
+                best_d2 = d2;
+                let target_node = if t <= 0.0 {
+                    Some(seg.a)
+                } else if t >= 1.0 {
+                    Some(seg.b)
+                } else {
+                    None
+                };
+                best = Some(Leak {
+                    node: nid as u32,
+                    target: (qx, qy),
+                    target_node,
+                    gap,
+                    kind: if target_node.is_some() { 1 } else { 2 },
+                });
+            }
+
+            leaks.push(best.unwrap_or(Leak {
+                node: nid as u32,
+                target: (p.x, p.y),
+                target_node: None,
+                gap: -1.0,
+                kind: 0,
+            }));
+        }
+
+        // Two endpoints that point at each other describe the same gap: keep one
+        let mut keep: Vec<bool> = Vec::new();
+        keep.resize(leaks.len(), true);
+        for i in 0..leaks.len() {
+            if !keep[i] {
+                continue;
+            }
+            for j in (i + 1)..leaks.len() {
+                if leaks[i].target_node == Some(leaks[j].node)
+                    && leaks[j].target_node == Some(leaks[i].node)
+                {
+                    keep[j] = false;
+                }
+            }
+        }
+
+        let mut ranked: Vec<&Leak> = Vec::new();
+        for (i, leak) in leaks.iter().enumerate() {
+            if keep[i] {
+                ranked.push(leak);
+            }
+        }
+        ranked.sort_by(|a, b| {
+            let ka = if a.kind == 0 { f32::INFINITY } else { a.gap };
+            let kb = if b.kind == 0 { f32::INFINITY } else { b.gap };
+            if ka < kb { core::cmp::Ordering::Less }
+            else if ka > kb { core::cmp::Ordering::Greater }
+            else { a.node.cmp(&b.node) }
+        });
+
+        let mut with_target = 0u32;
+        self.leak_buf.push(ranked.len() as f32);
+        for leak in ranked.iter() {
+            let p = graph.nodes[leak.node as usize];
+            self.leak_buf.push(p.x);
+            self.leak_buf.push(p.y);
+            self.leak_buf.push(leak.target.0);
+            self.leak_buf.push(leak.target.1);
+            self.leak_buf.push(leak.gap);
+            self.leak_buf.push(leak.kind as f32);
+            if leak.kind != 0 {
+                with_target += 1;
+            }
+        }
+
+        with_target
+    }
+
     fn trace_face_side(
         &mut self,
         start_from: u32,
@@ -1246,7 +2092,7 @@ impl Editor {
         if (start_from as usize) >= self.fill_graph.nodes.len()
             || (start_to as usize) >= self.fill_graph.nodes.len()
         {
-            return TraceResult::fail(FailReason::DeadEnd, 0, step_debug);
+            return TraceResult::fail(FailReason::DeadEnd, FillRunStats::empty(), None, step_debug);
         }
 
         let mut cur_node = start_to;
@@ -1266,10 +2112,10 @@ impl Editor {
         let mut step_idx = 0u32;
         let gap_r2 = gap_radius * gap_radius;
 
-        loop {
+        let fail_reason = 'walk: loop {
             // GUARDRAIL 1: Hard step limit
             if step_idx >= MAX_FILL_STEPS {
-                return TraceResult::fail(FailReason::StepLimit, step_idx, step_debug);
+                break 'walk Some(FailReason::StepLimit);
             }
 
             // GUARDRAIL 2: NO_PROGRESS detector - check if we're cycling in a small state set
@@ -1294,7 +2140,7 @@ impl Editor {
                 
                 // If recent window only has <=3 unique states, we're stuck
                 if unique_recent.len() <= NO_PROGRESS_THRESHOLD {
-                    return TraceResult::fail(FailReason::DeadEnd, step_idx, step_debug);
+                    break 'walk Some(FailReason::DeadEnd);
                 }
             }
 
@@ -1451,7 +2297,7 @@ impl Editor {
             }
 
             if usable.is_empty() {
-                return TraceResult::fail(FailReason::DeadEnd, step_idx, step_debug);
+                break 'walk Some(FailReason::DeadEnd);
             }
 
             let mut side_indices: Vec<usize> = Vec::new();
@@ -1470,7 +2316,7 @@ impl Editor {
 
             let chosen_idx = match pick_candidate_idx(&usable, pool_indices) {
                 Some(i) => i,
-                None => return TraceResult::fail(FailReason::DeadEnd, step_idx, step_debug),
+                None => break 'walk Some(FailReason::DeadEnd),
             };
 
             let chosen = usable[chosen_idx].clone();
@@ -1522,7 +2368,7 @@ impl Editor {
             for &prev_state in visited_states.iter() {
                 if prev_state == state_key {
                     // We've been in this exact state before - abort
-                    return TraceResult::fail(FailReason::PrematureCycle, step_idx, step_debug);
+                    break 'walk Some(FailReason::PrematureCycle);
                 }
             }
             visited_states.push(state_key);
@@ -1569,56 +2415,245 @@ impl Editor {
                 }
                 
                 if unique_nodes.len() < 3 {
-                    return TraceResult::fail(FailReason::DeadEnd, step_idx, step_debug);
+                    break 'walk Some(FailReason::DeadEnd);
                 }
                 
-                return TraceResult::success(boundary_points, step_idx + 1, step_debug);
+                step_idx += 1;
+                break 'walk None;
             }
 
             if edge_visited(&visited_edges, selected_key) {
-                return TraceResult::fail(FailReason::PrematureCycle, step_idx, step_debug);
+                break 'walk Some(FailReason::PrematureCycle);
             }
 
             visited_edges.push(selected_key);
             prev_node = cur_node;
             cur_node = chosen.next;
             step_idx += 1;
+        };
+
+        let stats = FillRunStats {
+            steps: step_idx,
+            unique_states: visited_states.len() as u32,
+            cand_max,
+            abort_reason: fail_reason,
+            trace: trace_ring,
+        };
+
+        match fail_reason {
+            None => TraceResult::success(boundary_points, stats, step_debug),
+            Some(reason) => TraceResult::fail(reason, stats, Some(cur_node), step_debug),
         }
     }
 
-    fn fill_debug_at(&mut self, ox: f32, oy: f32) {
+    // Single fill entry point: trace the region around (ox, oy) with the active
+    // strategy, publish the diagnostic buffers, then commit the polygon.
+    fn fill_at(&mut self, ox: f32, oy: f32, color: u32, swatch: Option<u32>) -> FillOutcome {
         // Validate input coordinates
         check_line_coordinates(ox, oy, ox, oy);
-        
-        self.fill_trace_buf.clear();
-        self.fill_walk_debug_buf.clear();
-        self.fill_candidates_buf.clear();
-        self.adjacency_debug_buf.clear();
-        self.build_fill_graph();
 
-        // trace count placeholder for step debug log
-        self.fill_candidates_buf.push(0.0);
+        let region = match self.fill_strategy {
+            FillStrategy::Graph => {
+                self.build_fill_graph();
+                self.trace_region_graph(ox, oy)
+            }
+            FillStrategy::Legacy => self.trace_region_legacy(ox, oy),
+        };
 
-        // Step 0: origin
-        self.fill_trace_buf.push(ox);
-        self.fill_trace_buf.push(oy);
-        self.fill_trace_buf.push(0.0); // type = origin
+        self.write_fill_diagnostics(ox, oy, &region);
 
-        if self.fill_graph.segments.is_empty() {
-            let mut result = Vec::new();
-            result.push((self.fill_trace_buf.len() / 3) as f32);
-            result.extend(self.fill_trace_buf.drain(..));
-            self.fill_trace_buf = result;
-            self.fill_candidates_buf[0] = 0.0;
-            return;
+        let fill_idx = if region.status == FillStatus::Filled {
+            self.create_polygon_from_selected(&region.points, color, swatch)
+        } else {
+            None
+        };
+
+        let chosen = region.chosen.map(|idx| &region.sides[idx]);
+        let outcome = FillOutcome {
+            status: region.status,
+            fill_idx,
+            // The legacy tracer does not count steps; it reports 0 (not tracked)
+            steps: chosen.map(|s| s.result.stats.steps).unwrap_or(0),
+            unique_states: chosen.map(|s| s.result.stats.unique_states).unwrap_or(0),
+            side: chosen.map(|s| s.side),
+            // For a rejected face this is the area that failed the MIN_AREA/inside test
+            area: absf(signed_area_2x(chosen.map(|s| &s.result.points).unwrap_or(&region.points))) * 0.5,
+            fail_point: chosen.and_then(|s| s.result.fail_node).map(|n| {
+                let p = self.fill_graph.nodes[n as usize];
+                (p.x, p.y)
+            }),
+        };
+        self.write_fill_result(&outcome, chosen.map(|s| &s.result.stats));
+        outcome
+    }
+
+    // Hover preview: the region fill_at would commit at (x, y), written to preview_buf
+    // without touching fills or history. Uses the current graph (rebuilt on every edit).
+    fn fill_preview(&mut self, x: f32, y: f32) -> FillStatus {
+        if self.fill_strategy == FillStrategy::Legacy {
+            let region = self.trace_region_legacy(x, y);
+            write_preview_buf(&mut self.preview_buf, region.status, &region.points);
+            return region.status;
         }
 
-        // Pick starting segment nearest to origin
-        // CRITICAL: only consider segments where BOTH endpoints have degree >= 2
-        // This prevents starting fill from dangling edges (open chains)
-        
-        let mut candidates: Vec<(usize, f32)> = Vec::new();
-        for (i, seg) in self.fill_graph.segments.iter().enumerate() {
+        if let Some(idx) = self.lookup_cached_face(x, y) {
+            write_preview_buf(&mut self.preview_buf, FillStatus::Filled, &self.face_cache[idx].points);
+            return FillStatus::Filled;
+        }
+
+        if self.fill_graph.segments.is_empty() {
+            write_preview_buf(&mut self.preview_buf, FillStatus::NoGeometry, &[]);
+            return FillStatus::NoGeometry;
+        }
+        let seg_idx = match self.pick_start_segment(x, y) {
+            Some(idx) => idx,
+            None => {
+                write_preview_buf(&mut self.preview_buf, FillStatus::NoBoundary, &[]);
+                return FillStatus::NoBoundary;
+            }
+        };
+        let (side, _) = self.start_side(seg_idx, x, y);
+
+        // Same start segment and side as the last uncached trace: same walk
+        if let Some(miss) = &self.preview_miss {
+            if miss.seg == seg_idx
+                && miss.side == side
+                && (miss.status != FillStatus::Filled || point_in_poly_evenodd((x, y), &miss.points))
+            {
+                write_preview_buf(&mut self.preview_buf, miss.status, &miss.points);
+                return miss.status;
+            }
+        }
+
+        let region = self.trace_region_from(seg_idx, x, y);
+        write_preview_buf(&mut self.preview_buf, region.status, &region.points);
+        if region.status == FillStatus::Filled && self.face_is_clean(&region.points) {
+            self.insert_cached_face(region.points);
+        } else {
+            self.preview_miss = Some(PreviewMiss {
+                seg: seg_idx,
+                side,
+                status: region.status,
+                points: region.points,
+            });
+        }
+        region.status
+    }
+
+    // Binary search on min x, then scan back no further than the widest face
+    fn lookup_cached_face(&self, x: f32, y: f32) -> Option<usize> {
+        let mut i = self.face_cache.partition_point(|f| f.bounds.0 <= x);
+        while i > 0 {
+            i -= 1;
+            let face = &self.face_cache[i];
+            if face.bounds.0 < x - self.face_cache_max_w {
+                break;
+            }
+            if x <= face.bounds.2
+                && y >= face.bounds.1
+                && y <= face.bounds.3
+                && point_in_poly_evenodd((x, y), &face.points)
+            {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // A face is cacheable when no node or segment midpoint lies strictly inside it
+    fn face_is_clean(&self, points: &[(f32, f32)]) -> bool {
+        let eps2 = SNAP_EPS * SNAP_EPS;
+        let strictly_inside = |p: (f32, f32)| {
+            point_in_poly_evenodd(p, points) && min_dist_sq_to_polygon(p, points) > eps2
+        };
+        for node in self.fill_graph.nodes.iter() {
+            if strictly_inside((node.x, node.y)) {
+                return false;
+            }
+        }
+        for seg in self.fill_graph.segments.iter() {
+            let a = self.fill_graph.nodes[seg.a as usize];
+            let b = self.fill_graph.nodes[seg.b as usize];
+            if strictly_inside(((a.x + b.x) * 0.5, (a.y + b.y) * 0.5)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn insert_cached_face(&mut self, points: Vec<(f32, f32)>) {
+        let bounds = poly_bounds(&points);
+        let width = bounds.2 - bounds.0;
+        if width > self.face_cache_max_w {
+            self.face_cache_max_w = width;
+        }
+        let pos = self.face_cache.partition_point(|f| f.bounds.0 <= bounds.0);
+        self.face_cache.insert(pos, CachedFace { bounds, points });
+    }
+
+    // Fill result: [status, steps, unique_states, side, area, fill_idx, has_fail_node, fail_x, fail_y]
+    // side: -1 = none, 0 = KeepLeft, 1 = KeepRight; fill_idx: -1 = nothing created
+    // Also refreshes last_fill_stats: [ok, steps, unique_states, cand_max, abort_code] + trace ring
+    fn write_fill_result(&mut self, outcome: &FillOutcome, stats: Option<&FillRunStats>) {
+        self.fill_result_buf.clear();
+        self.fill_result_buf.push(outcome.status.code() as f32);
+        self.fill_result_buf.push(outcome.steps as f32);
+        self.fill_result_buf.push(outcome.unique_states as f32);
+        self.fill_result_buf.push(match outcome.side {
+            Some(SideRule::KeepLeft) => 0.0,
+            Some(SideRule::KeepRight) => 1.0,
+            None => -1.0,
+        });
+        self.fill_result_buf.push(outcome.area);
+        self.fill_result_buf.push(outcome.fill_idx.map(|i| i as f32).unwrap_or(-1.0));
+        let (has_fail, fx, fy) = match outcome.fail_point {
+            Some((x, y)) => (1.0, x, y),
+            None => (0.0, 0.0, 0.0),
+        };
+        self.fill_result_buf.push(has_fail);
+        self.fill_result_buf.push(fx);
+        self.fill_result_buf.push(fy);
+
+        self.last_fill_stats.clear();
+        let ok = outcome.status == FillStatus::Filled;
+        self.last_fill_stats.push(if ok { 1.0 } else { 0.0 });
+        self.last_fill_stats.push(outcome.steps as f32);
+        self.last_fill_stats.push(outcome.unique_states as f32);
+        self.last_fill_stats.push(stats.map(|s| s.cand_max).unwrap_or(0) as f32);
+        self.last_fill_stats.push(outcome.status.code() as f32);
+        if let Some(stats) = stats {
+            // Trace ring: (step, node, incoming, chosen, cand_count, score) per entry
+            for entry in stats.trace.iter() {
+                self.last_fill_stats.push(entry.step as f32);
+                self.last_fill_stats.push(entry.node as f32);
+                self.last_fill_stats.push(entry.incoming as f32);
+                self.last_fill_stats.push(entry.chosen as f32);
+                self.last_fill_stats.push(entry.cand_count as f32);
+                self.last_fill_stats.push(entry.score);
+            }
+        }
+    }
+
+    // Cut-segment graph strategy: pick the nearest boundary segment, trace both
+    // directions side-locked, keep the smallest closed face containing the origin.
+    fn trace_region_graph(&mut self, ox: f32, oy: f32) -> RegionTrace {
+        if self.fill_graph.segments.is_empty() {
+            return RegionTrace::empty(FillStatus::NoGeometry);
+        }
+
+        match self.pick_start_segment(ox, oy) {
+            Some(seg_idx) => self.trace_region_from(seg_idx, ox, oy),
+            // No valid boundary edge found - abort fill
+            None => RegionTrace::empty(FillStatus::NoBoundary),
+        }
+    }
+
+    // Pick starting segment nearest to origin
+    // CRITICAL: only consider segments where BOTH endpoints have degree >= 2
+    // This prevents starting fill from dangling edges (open chains)
+    fn pick_start_segment(&self, ox: f32, oy: f32) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, seg) in self.fill_graph.segments.iter().enumerate() {
             let degree_a = if (seg.a as usize) < self.fill_graph.node_sectors.len() {
                 self.fill_graph.node_sectors[seg.a as usize].len()
             } else {
@@ -1653,38 +2688,35 @@ impl Editor {
                     distance_sq(ox, oy, a.x, a.y)
                 };
                 
-                candidates.push((i, d2));
+                // Closest wins; ties keep the lower segment index
+                match best {
+                    Some((_, best_d2)) if best_d2 <= d2 => {}
+                    _ => best = Some((i, d2)),
+                }
             }
         }
+        best.map(|(i, _)| i)
+    }
 
-        // Sort by distance and pick closest valid segment
-        if candidates.is_empty() {
-            // No valid boundary edge found - abort fill
-            // Fill aborted: no valid boundary edge near cursor
-            let mut result = Vec::new();
-            result.push((self.fill_trace_buf.len() / 3) as f32);
-            result.extend(self.fill_trace_buf.drain(..));
-            self.fill_trace_buf = result;
-            self.fill_candidates_buf[0] = 0.0;
-            return;
-        }
-
-        candidates.sort_by(|a, b| {
-            if a.1 < b.1 { core::cmp::Ordering::Less }
-            else if a.1 > b.1 { core::cmp::Ordering::Greater }
-            else { core::cmp::Ordering::Equal }
-        });
+    // Side rule for the a->b direction of a start segment; b->a uses the opposite side
+    fn start_side(&self, seg_idx: usize, ox: f32, oy: f32) -> (SideRule, f32) {
+        let seg = self.fill_graph.segments[seg_idx];
+        let pa = self.fill_graph.nodes[seg.a as usize];
+        let pb = self.fill_graph.nodes[seg.b as usize];
+        let cross_ab = cross(pb.x - pa.x, pb.y - pa.y, ox - pa.x, oy - pa.y);
+        let side_ab = if cross_ab > 0.0 { SideRule::KeepLeft } else { SideRule::KeepRight };
+        (side_ab, cross_ab)
+    }
 
-        let best_seg_idx = candidates[0].0;
+    fn trace_region_from(&mut self, best_seg_idx: usize, ox: f32, oy: f32) -> RegionTrace {
         let start_seg = self.fill_graph.segments[best_seg_idx];
         let node_a = start_seg.a;
         let node_b = start_seg.b;
 
         let pa = self.fill_graph.nodes[node_a as usize];
         let pb = self.fill_graph.nodes[node_b as usize];
-        let cross_ab = cross(pb.x - pa.x, pb.y - pa.y, ox - pa.x, oy - pa.y);
+        let (side_ab, cross_ab) = self.start_side(best_seg_idx, ox, oy);
         let cross_ba = cross(pa.x - pb.x, pa.y - pb.y, ox - pb.x, oy - pb.y);
-        let side_ab = if cross_ab > 0.0 { SideRule::KeepLeft } else { SideRule::KeepRight };
         let side_ba = if cross_ba > 0.0 { SideRule::KeepLeft } else { SideRule::KeepRight };
         let origin = (ox, oy);
 
@@ -1692,56 +2724,106 @@ impl Editor {
         let result_ab = self.trace_face_side(node_a, node_b, side_ab, origin, GAP_RADIUS);
         let result_ba = self.trace_face_side(node_b, node_a, side_ba, origin, GAP_RADIUS);
 
-        let origin = (ox, oy);
+        let sides: Vec<SideTrace> = alloc::vec![
+            SideTrace { from: node_a, to: node_b, side: side_ab, cross: cross_ab, result: result_ab },
+            SideTrace { from: node_b, to: node_a, side: side_ba, cross: cross_ba, result: result_ba },
+        ];
+
+        // Validate results: closed, large enough and containing the origin
+        let mut valid_results: Vec<(usize, f32)> = Vec::new();
+        for (idx, side) in sides.iter().enumerate() {
+            let result = &side.result;
+            if result.closed && result.points.len() >= 3 {
+                let area = absf(signed_area_2x(&result.points)) * 0.5;
+                let inside = point_in_poly_evenodd(origin, &result.points);
+                if area > MIN_AREA && inside {
+                    valid_results.push((idx, area));
+                }
+            }
+        }
 
-        // Debug log: start direction and side
-        let mut trace_counter = 0u32;
-        {
-            let side_code = if let SideRule::KeepLeft = side_ab { 0.0 } else { 1.0 };
-            trace_counter += 1;
-            self.fill_candidates_buf.push(trace_counter as f32);
-            self.fill_candidates_buf.push(node_a as f32);
-            self.fill_candidates_buf.push(node_b as f32);
-            self.fill_candidates_buf.push(side_code);
-            self.fill_candidates_buf.push(cross_ab);
-            self.fill_candidates_buf.push(if result_ab.closed { 1.0 } else { 0.0 });
-            self.fill_candidates_buf.push(result_ab.steps as f32);
-            self.fill_candidates_buf.push(result_ab.step_debug.len() as f32);
-            for step in result_ab.step_debug.iter() {
-                self.fill_candidates_buf.push(step.step_idx as f32);
-                self.fill_candidates_buf.push(step.cur as f32);
-                self.fill_candidates_buf.push(step.prev as f32);
-                self.fill_candidates_buf.push(step.vin.0);
-                self.fill_candidates_buf.push(step.vin.1);
-                self.fill_candidates_buf.push(step.candidates.len() as f32);
-                for cand in step.candidates.iter() {
-                    self.fill_candidates_buf.push(cand.next as f32);
-                    self.fill_candidates_buf.push(cand.turn);
-                    self.fill_candidates_buf.push(cand.abs_turn);
-                    self.fill_candidates_buf.push(if cand.is_virtual { 1.0 } else { 0.0 });
-                    self.fill_candidates_buf.push(cand.out_x);
-                    self.fill_candidates_buf.push(cand.out_y);
-                    self.fill_candidates_buf.push(if cand.accepted_side { 1.0 } else { 0.0 });
-                    self.fill_candidates_buf.push(if cand.was_visited { 1.0 } else { 0.0 });
-                    self.fill_candidates_buf.push(cand.len);
-                    self.fill_candidates_buf.push(cand.mid_dist);
-                    self.fill_candidates_buf.push(if cand.chosen { 1.0 } else { 0.0 });
+        // Selection logic: smallest valid face wins
+        let mut selected: Option<(usize, f32)> = None;
+        for (idx, area) in valid_results.iter() {
+            match selected {
+                Some((_, best_area)) if best_area <= *area => {}
+                _ => selected = Some((*idx, *area)),
+            }
+        }
+
+        let (status, points, chosen) = match selected {
+            Some((idx, _)) => (FillStatus::Filled, sides[idx].result.points.clone(), idx),
+            None => {
+                // A closed-but-rejected face explains more than a walk failure;
+                // otherwise report the direction that got furthest.
+                let mut report = 0;
+                for (idx, side) in sides.iter().enumerate() {
+                    if side.result.closed {
+                        report = idx;
+                        break;
+                    }
+                    if side.result.stats.steps > sides[report].result.stats.steps {
+                        report = idx;
+                    }
                 }
+                let reason = sides[report].result.stats.abort_reason.unwrap_or(FailReason::NoClosure);
+                (FillStatus::Failed(reason), Vec::new(), report)
             }
+        };
+
+        RegionTrace {
+            status,
+            points,
+            start: Some(node_a),
+            sides,
+            chosen: Some(chosen),
         }
+    }
 
-        {
-            let side_code = if let SideRule::KeepLeft = side_ba { 0.0 } else { 1.0 };
-            trace_counter += 1;
-            self.fill_candidates_buf.push(trace_counter as f32);
-            self.fill_candidates_buf.push(node_b as f32);
-            self.fill_candidates_buf.push(node_a as f32);
+    // Legacy strategy: nearest line endpoint -> lines connected within TOLERANCE ->
+    // endpoint chaining. Ignores intersections; kept for comparison.
+    fn trace_region_legacy(&mut self, ox: f32, oy: f32) -> RegionTrace {
+        let nearest_idx = match self.nearest_line_by(ox, oy, f32::INFINITY, line_endpoint_dist_sq) {
+            Some((idx, _)) => idx,
+            None => return RegionTrace::empty(FillStatus::NoGeometry),
+        };
+
+        let connected = find_connected_lines(&self.lines, nearest_idx);
+        let polygon = trace_polygon(&self.lines, &connected);
+        if !polygon.is_closed() {
+            return RegionTrace::empty(FillStatus::Failed(FailReason::NoClosure));
+        }
+
+        RegionTrace {
+            status: FillStatus::Filled,
+            points: polygon.points,
+            start: None,
+            sides: Vec::new(),
+            chosen: None,
+        }
+    }
+
+    // Publish fill_trace_buf, fill_candidates_buf and fill_walk_debug_buf for a traced region
+    fn write_fill_diagnostics(&mut self, ox: f32, oy: f32, region: &RegionTrace) {
+        self.fill_trace_buf.clear();
+        self.fill_walk_debug_buf.clear();
+        self.fill_candidates_buf.clear();
+        self.adjacency_debug_buf.clear();
+
+        // Step debug log: [trace_count, per trace: id, from, to, side, cross, closed, steps, step_count, steps...]
+        self.fill_candidates_buf.push(region.sides.len() as f32);
+        for (trace_idx, side) in region.sides.iter().enumerate() {
+            let result = &side.result;
+            let side_code = if let SideRule::KeepLeft = side.side { 0.0 } else { 1.0 };
+            self.fill_candidates_buf.push((trace_idx + 1) as f32);
+            self.fill_candidates_buf.push(side.from as f32);
+            self.fill_candidates_buf.push(side.to as f32);
             self.fill_candidates_buf.push(side_code);
-            self.fill_candidates_buf.push(cross_ba);
-            self.fill_candidates_buf.push(if result_ba.closed { 1.0 } else { 0.0 });
-            self.fill_candidates_buf.push(result_ba.steps as f32);
-            self.fill_candidates_buf.push(result_ba.step_debug.len() as f32);
-            for step in result_ba.step_debug.iter() {
+            self.fill_candidates_buf.push(side.cross);
+            self.fill_candidates_buf.push(if result.closed { 1.0 } else { 0.0 });
+            self.fill_candidates_buf.push(result.stats.steps as f32);
+            self.fill_candidates_buf.push(result.step_debug.len() as f32);
+            for step in result.step_debug.iter() {
                 self.fill_candidates_buf.push(step.step_idx as f32);
                 self.fill_candidates_buf.push(step.cur as f32);
                 self.fill_candidates_buf.push(step.prev as f32);
@@ -1764,134 +2846,63 @@ impl Editor {
             }
         }
 
-        if !self.fill_candidates_buf.is_empty() {
-            self.fill_candidates_buf[0] = trace_counter as f32;
+        // COMPUTE DIAGNOSTICS for every closed candidate (12 floats each)
+        let origin = (ox, oy);
+        for (trace_idx, side) in region.sides.iter().enumerate() {
+            let points = &side.result.points;
+            if !side.result.closed || points.len() < 3 {
+                continue;
+            }
+            let area2 = absf(signed_area_2x(points));
+            let inside = point_in_poly_evenodd(origin, points);
+            let dist_sq = min_dist_sq_to_polygon(origin, points);
+            let (minx, miny, maxx, maxy) = poly_bounds(points);
+            let is_simple = is_simple_polygon(points);
+            self.fill_walk_debug_buf.push((trace_idx + 1) as f32); // direction
+            self.fill_walk_debug_buf.push(points.len() as f32);
+            self.fill_walk_debug_buf.push(area2); // signed_area*2
+            self.fill_walk_debug_buf.push(area2 * 0.5); // area
+            self.fill_walk_debug_buf.push(if inside { 1.0 } else { 0.0 });
+            self.fill_walk_debug_buf.push(dist_sq);
+            self.fill_walk_debug_buf.push(sqrt_approx(dist_sq));
+            self.fill_walk_debug_buf.push(if is_simple { 1.0 } else { 0.0 });
+            self.fill_walk_debug_buf.push(minx);
+            self.fill_walk_debug_buf.push(miny);
+            self.fill_walk_debug_buf.push(maxx);
+            self.fill_walk_debug_buf.push(maxy);
         }
 
-        // COMPUTE DIAGNOSTICS for both candidates
-        let diag_ab = if result_ab.closed && result_ab.points.len() >= 3 {
-            let area = absf(signed_area_2x(&result_ab.points)) * 0.5;
-            let inside = point_in_poly_evenodd(origin, &result_ab.points);
-            let dist_sq = min_dist_sq_to_polygon(origin, &result_ab.points);
-            let (minx, miny, maxx, maxy) = poly_bounds(&result_ab.points);
-            let is_simple = is_simple_polygon(&result_ab.points);
-            Some((1.0, result_ab.points.len() as f32, absf(signed_area_2x(&result_ab.points)), area, 
-                  if inside { 1.0 } else { 0.0 }, dist_sq, sqrt_approx(dist_sq), 
-                  if is_simple { 1.0 } else { 0.0 }, minx, miny, maxx, maxy, inside, area))
-        } else {
-            None
-        };
-
-        let diag_ba = if result_ba.closed && result_ba.points.len() >= 3 {
-            let area = absf(signed_area_2x(&result_ba.points)) * 0.5;
-            let inside = point_in_poly_evenodd(origin, &result_ba.points);
-            let dist_sq = min_dist_sq_to_polygon(origin, &result_ba.points);
-            let (minx, miny, maxx, maxy) = poly_bounds(&result_ba.points);
-            let is_simple = is_simple_polygon(&result_ba.points);
-            Some((2.0, result_ba.points.len() as f32, absf(signed_area_2x(&result_ba.points)), area,
-                  if inside { 1.0 } else { 0.0 }, dist_sq, sqrt_approx(dist_sq),
-                  if is_simple { 1.0 } else { 0.0 }, minx, miny, maxx, maxy, inside, area))
-        } else {
-            None
-        };
-
-        // Store diagnostics for visualization
-        if let Some(d) = &diag_ab {
-            self.fill_walk_debug_buf.clear();
-            self.fill_walk_debug_buf.push(d.0); // direction
-            self.fill_walk_debug_buf.push(d.1); // points.len
-            self.fill_walk_debug_buf.push(d.2); // signed_area*2
-            self.fill_walk_debug_buf.push(d.3); // area
-            self.fill_walk_debug_buf.push(d.4); // inside
-            self.fill_walk_debug_buf.push(d.5); // dist_sq
-            self.fill_walk_debug_buf.push(d.6); // dist
-            self.fill_walk_debug_buf.push(d.7); // is_simple
-            self.fill_walk_debug_buf.push(d.8); // minx
-            self.fill_walk_debug_buf.push(d.9); // miny
-            self.fill_walk_debug_buf.push(d.10); // maxx
-            self.fill_walk_debug_buf.push(d.11); // maxy
-        }
-        if let Some(d) = &diag_ba {
-            self.fill_walk_debug_buf.push(d.0);
-            self.fill_walk_debug_buf.push(d.1);
-            self.fill_walk_debug_buf.push(d.2);
-            self.fill_walk_debug_buf.push(d.3);
-            self.fill_walk_debug_buf.push(d.4);
-            self.fill_walk_debug_buf.push(d.5);
-            self.fill_walk_debug_buf.push(d.6);
-            self.fill_walk_debug_buf.push(d.7);
-            self.fill_walk_debug_buf.push(d.8);
-            self.fill_walk_debug_buf.push(d.9);
-            self.fill_walk_debug_buf.push(d.10);
-            self.fill_walk_debug_buf.push(d.11);
-        }
-
-        // Validate results
-        let mut valid_results: Vec<(bool, bool, f32, &TraceResult)> = Vec::new();
-
-        if result_ab.closed && result_ab.points.len() >= 3 {
-            let area = absf(signed_area_2x(&result_ab.points)) * 0.5;
-            let inside = point_in_poly_evenodd(origin, &result_ab.points);
-            if area > MIN_AREA && inside {
-                valid_results.push((true, inside, area, &result_ab));
-            }
-        }
-
-        if result_ba.closed && result_ba.points.len() >= 3 {
-            let area = absf(signed_area_2x(&result_ba.points)) * 0.5;
-            let inside = point_in_poly_evenodd(origin, &result_ba.points);
-            if area > MIN_AREA && inside {
-                valid_results.push((true, inside, area, &result_ba));
-            }
-        }
-
-        // Selection logic
-        let selected: Option<&TraceResult> = if valid_results.is_empty() {
-            None
-        } else if valid_results.len() == 1 {
-            Some(valid_results[0].3)
-        } else {
-            let inside_results: Vec<_> = valid_results.iter().filter(|(_, inside, _, _)| *inside).collect();
-            if !inside_results.is_empty() {
-                inside_results.iter().min_by(|a, b| {
-                    let a_area = a.2;
-                    let b_area = b.2;
-                    if a_area < b_area { core::cmp::Ordering::Less } else { core::cmp::Ordering::Greater }
-                }).map(|x| x.3)
-            } else {
-                Some(valid_results[0].3)
-            }
-        };
+        // Trace log: [count, (x, y, type) * count]
+        // type: 0 = origin, 2 = start node, 3 = chain point, 9 = closure
+        self.fill_trace_buf.push(0.0);
+        self.fill_trace_buf.push(ox);
+        self.fill_trace_buf.push(oy);
+        self.fill_trace_buf.push(0.0);
 
-        // Log and create polygon
-        if let Some(sel) = selected {
-            let pt_a = self.fill_graph.nodes[node_a as usize];
-            self.fill_trace_buf.push(pt_a.x);
-            self.fill_trace_buf.push(pt_a.y);
+        if region.status == FillStatus::Filled && !region.points.is_empty() {
+            let start = match region.start {
+                Some(node) => {
+                    let p = self.fill_graph.nodes[node as usize];
+                    (p.x, p.y)
+                }
+                None => region.points[0],
+            };
+            self.fill_trace_buf.push(start.0);
+            self.fill_trace_buf.push(start.1);
             self.fill_trace_buf.push(2.0);
-            
-            for pt in sel.points.iter().skip(1) {
+
+            for pt in region.points.iter().skip(1) {
                 self.fill_trace_buf.push(pt.0);
                 self.fill_trace_buf.push(pt.1);
                 self.fill_trace_buf.push(3.0);
             }
 
-            self.fill_trace_buf.push(pt_a.x);
-            self.fill_trace_buf.push(pt_a.y);
+            self.fill_trace_buf.push(start.0);
+            self.fill_trace_buf.push(start.1);
             self.fill_trace_buf.push(9.0);
-
-            let mut result = Vec::new();
-            result.push((self.fill_trace_buf.len() / 3) as f32);
-            result.extend(self.fill_trace_buf.drain(..));
-            self.fill_trace_buf = result;
-            
-            self.create_polygon_from_selected(&sel.points);
-        } else {
-            let mut result = Vec::new();
-            result.push((self.fill_trace_buf.len() / 3) as f32);
-            result.extend(self.fill_trace_buf.drain(..));
-            self.fill_trace_buf = result;
         }
+
+        self.fill_trace_buf[0] = ((self.fill_trace_buf.len() - 1) / 3) as f32;
     }
 
 
@@ -1930,16 +2941,16 @@ impl Editor {
         }
 
         if polygon.points.len() >= 4 {
+            polygon.id = self.take_fill_id();
             self.fills.push(polygon);
             self.history.push(Command::AddFill);
             self.refresh_export_fills();
         }
     }
 
-    fn create_polygon_from_selected(&mut self, points: &[(f32, f32)]) {
+    fn create_polygon_from_selected(&mut self, points: &[(f32, f32)], color: u32, swatch: Option<u32>) -> Option<usize> {
         // Create polygon directly from selected TraceResult points
-        let mut polygon = Polygon::new();
-        polygon.color = self.fill_color;
+        let mut polygon = Polygon::new().with_color(color).with_swatch(swatch);
         
         if points.len() >= 3 {
             polygon.points = points.to_vec();
@@ -1949,14 +2960,361 @@ impl Editor {
                 polygon.points.push(polygon.points[0]);
             }
             
+            // Refilling an existing face replaces its color instead of stacking a copy
+            if let Some(idx) = self.fills.iter().rposition(|f| same_face(&f.points, &polygon.points)) {
+                self.recolor_fill(idx, color, swatch);
+                self.fills[idx].stale = false;
+                return Some(idx);
+            }
+
+            polygon.id = self.take_fill_id();
             self.fills.push(polygon);
             self.history.push(Command::AddFill);
             self.refresh_export_fills();
+            return Some(self.fills.len() - 1);
+        }
+        None
+    }
+
+    // Topmost fill containing the point (fills later in the list draw on top)
+    fn fill_hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        self.fill_grid
+            .at_point(x, y)
+            .map(|i| i as usize)
+            .filter(|&i| {
+                self.fills
+                    .get(i)
+                    .map(|f| f.is_closed() && point_in_poly_evenodd((x, y), &f.points))
+                    .unwrap_or(false)
+            })
+            .max()
+    }
+
+    // Eyedropper: color of the topmost fill at the point, else the background
+    fn color_at(&self, x: f32, y: f32) -> u32 {
+        self.fill_hit_test(x, y)
+            .map(|idx| {
+                let fill = &self.fills[idx];
+                fill.paint.sample(x, y, self.fill_color_of(fill))
+            })
+            .unwrap_or(self.background_color)
+    }
+
+    fn fill_color_of(&self, fill: &Polygon) -> u32 {
+        self.palette.resolve(fill.swatch, fill.color)
+    }
+
+    fn recolor_fill(&mut self, idx: usize, color: u32, swatch: Option<u32>) -> bool {
+        let Some(fill) = self.fills.get_mut(idx) else {
+            return false;
+        };
+        if fill.color == color && fill.swatch == swatch && fill.paint == Paint::Solid {
+            return true;
+        }
+        self.history.push(Command::EditFill(idx, fill.clone()));
+        fill.color = color;
+        fill.swatch = swatch;
+        fill.paint = Paint::Solid;
+        self.refresh_export_fills();
+        true
+    }
+
+    fn set_fill_paint(&mut self, idx: usize, paint: Paint) -> bool {
+        let Some(fill) = self.fills.get_mut(idx) else {
+            return false;
+        };
+        if fill.paint != paint {
+            self.history.push(Command::EditFill(idx, fill.clone()));
+            fill.paint = paint;
+            self.refresh_export_fills();
+        }
+        true
+    }
+
+    // Drag a gradient handle (see Paint::set_handle); one undo step per call
+    fn move_gradient_handle(&mut self, idx: usize, handle: u32, x: f32, y: f32) -> bool {
+        let Some(fill) = self.fills.get(idx) else {
+            return false;
+        };
+        let mut paint = fill.paint.clone();
+        paint.set_handle(handle, x, y) && self.set_fill_paint(idx, paint)
+    }
+
+    // Parse a color string, remembering the error for editor_color_error
+    fn read_color(&mut self, bytes: &[u8]) -> Option<u32> {
+        match parse_color(bytes) {
+            Ok(color) => {
+                self.color_error = None;
+                Some(color)
+            }
+            Err(err) => {
+                self.color_error = Some(err);
+                None
+            }
+        }
+    }
+
+    // Color and swatch reference a new fill gets from the current fill settings
+    fn current_fill(&self) -> (u32, Option<u32>) {
+        (self.palette.resolve(self.fill_swatch, self.fill_color), self.fill_swatch)
+    }
+
+    fn add_swatch(&mut self, name: &str, color: u32) -> u32 {
+        let id = self.palette.add(name, color);
+        self.history.push(Command::AddSwatch);
+        self.refresh_export_fills();
+        id
+    }
+
+    // Recolors every fill referencing the swatch
+    fn set_swatch_color(&mut self, id: u32, color: u32) -> bool {
+        match self.palette.set_color(id, color) {
+            Some(previous) => {
+                if previous != color {
+                    self.history.push(Command::SetSwatchColor(id, previous));
+                    self.refresh_export_fills();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Fills referencing the swatch keep its current color as their own
+    fn remove_swatch(&mut self, id: u32) -> bool {
+        let Some(pos) = self.palette.position(id) else {
+            return false;
+        };
+        let removed = self.palette.swatches.remove(pos);
+        for fill in self.fills.iter_mut().filter(|f| f.swatch == Some(id)) {
+            fill.color = removed.color;
+        }
+        if self.fill_swatch == Some(id) {
+            self.fill_color = removed.color;
+            self.fill_swatch = None;
+        }
+        self.history.push(Command::RemoveSwatch(pos, removed));
+        self.refresh_export_fills();
+        true
+    }
+
+    fn refresh_swatches_export_buf(&mut self) {
+        self.swatches_export_buf.clear();
+        self.swatches_export_buf.push(self.palette.swatches.len() as f32);
+        for swatch in self.palette.swatches.iter() {
+            self.swatches_export_buf.push(swatch.id as f32);
+            push_rgba(&mut self.swatches_export_buf, swatch.color);
+        }
+    }
+
+    fn delete_fill(&mut self, idx: usize) -> bool {
+        if idx >= self.fills.len() {
+            return false;
+        }
+        let removed = self.fills.remove(idx);
+        self.history.push(Command::DeleteFill(idx, removed));
+        self.refresh_export_fills();
+        true
+    }
+
+    // Move a fill one step up (towards the top) or down in z-order.
+    // Returns its new index, or None when it is already at that end.
+    fn move_fill(&mut self, idx: usize, up: bool) -> Option<usize> {
+        if idx >= self.fills.len() {
+            return None;
         }
+        let target = if up {
+            if idx + 1 >= self.fills.len() {
+                return None;
+            }
+            idx + 1
+        } else {
+            idx.checked_sub(1)?
+        };
+        self.fills.swap(idx, target);
+        self.history.push(Command::SwapFills(idx, target));
+        self.refresh_export_fills();
+        Some(target)
     }
 
     fn refresh_export_fills(&mut self) {
         self.refresh_fills_export_buf();
+        self.refresh_swatches_export_buf();
+        self.refresh_pattern_lines();
+        self.refresh_stale_fills_buf();
+    }
+
+    fn refresh_stale_fills_buf(&mut self) {
+        self.stale_fills_buf.clear();
+        self.stale_fills_buf.push(0.0);
+        for (idx, fill) in self.fills.iter().enumerate() {
+            if fill.stale {
+                self.stale_fills_buf.push(idx as f32);
+            }
+        }
+        self.stale_fills_buf[0] = (self.stale_fills_buf.len() - 1) as f32;
+    }
+
+    fn stale_fills_ptr(&self) -> *const f32 {
+        self.stale_fills_buf.as_ptr()
+    }
+
+    fn stale_fills_len(&self) -> u32 {
+        self.stale_fills_buf.len() as u32
+    }
+
+    // Split the fill graph into islands (connected groups of cycle segments)
+    // and trace the outer contour of each. Cached until the graph changes.
+    fn refresh_graph_islands(&mut self) {
+        if self.islands_rev == self.graph_rev {
+            return;
+        }
+        self.islands_rev = self.graph_rev;
+        let graph = &self.fill_graph;
+        self.node_component.clear();
+        self.node_component.resize(graph.nodes.len(), u32::MAX);
+        self.island_points.clear();
+        self.island_ends.clear();
+        if graph.segments.is_empty() {
+            return;
+        }
+
+        let dead = overhang_segments(graph);
+        let mut stack: Vec<u32> = Vec::new();
+        for root in 0..graph.nodes.len() {
+            if self.node_component[root] != u32::MAX
+                || graph.node_sectors[root].iter().all(|&s| dead[s as usize])
+            {
+                continue;
+            }
+            let island = self.island_ends.len() as u32;
+            self.node_component[root] = island;
+            stack.push(root as u32);
+            let mut leftmost = root as u32;
+            while let Some(node) = stack.pop() {
+                let (p, l) = (graph.nodes[node as usize], graph.nodes[leftmost as usize]);
+                if p.x < l.x || (p.x == l.x && p.y < l.y) {
+                    leftmost = node;
+                }
+                for &s in graph.node_sectors[node as usize].iter() {
+                    let other = other_end(&graph.segments[s as usize], node);
+                    if !dead[s as usize] && self.node_component[other as usize] == u32::MAX {
+                        self.node_component[other as usize] = island;
+                        stack.push(other);
+                    }
+                }
+            }
+            outer_contour(graph, &dead, leftmost, &mut self.island_points);
+            self.island_ends.push(self.island_points.len());
+        }
+    }
+
+    // Expand pattern paints into line segments. A fill's holes are the inner
+    // boundaries of its face: the islands of lines inside its outline that are
+    // not part of its own boundary (islands inside those islands are not).
+    // Expansions are cached per fill and redone only when its outline, paint
+    // or holes change.
+    fn refresh_pattern_lines(&mut self) {
+        let fills = &self.fills;
+        self.pattern_cache.retain(|c| {
+            fills.iter().any(|f| f.id == c.fill_id && matches!(f.paint, Paint::Pattern { .. }))
+        });
+        self.pattern_lines_buf.clear();
+        self.pattern_lines_buf.push(0.0);
+        for idx in 0..self.fills.len() {
+            let fill = &self.fills[idx];
+            let Paint::Pattern { kind, angle, spacing } = fill.paint else {
+                continue;
+            };
+            self.refresh_graph_islands();
+            let fill = &self.fills[idx];
+
+            // The island the outline runs along, if it still lies on the graph
+            let boundary = fill.points.first().and_then(|&(x, y)| {
+                let node = self.fill_graph.nodes.iter().position(|n| {
+                    distance_sq(n.x, n.y, x, y) <= SNAP_EPS * SNAP_EPS
+                })?;
+                Some(self.node_component[node])
+            });
+            let (points, ends) = (&self.island_points, &self.island_ends);
+            let island = |i: usize| &points[if i == 0 { 0 } else { ends[i - 1] }..ends[i]];
+            let inside = |i: usize| {
+                let contour = island(i);
+                boundary != Some(i as u32)
+                    && !contour.is_empty()
+                    && point_in_poly_evenodd(contour[0], &fill.points)
+                    && !same_face(contour, &fill.points)
+            };
+            self.pattern_holes.clear();
+            self.pattern_hole_ends.clear();
+            for i in 0..ends.len() {
+                let nested = |j: usize| j != i && inside(j) && point_in_poly_evenodd(island(i)[0], island(j));
+                if inside(i) && !(0..ends.len()).any(nested) {
+                    self.pattern_holes.extend_from_slice(island(i));
+                    self.pattern_hole_ends.push(self.pattern_holes.len());
+                }
+            }
+
+            let slot = match self.pattern_cache.iter().position(|c| c.fill_id == fill.id) {
+                Some(slot) => slot,
+                None => {
+                    self.pattern_cache.push(CachedPattern {
+                        fill_id: fill.id,
+                        kind,
+                        angle: f32::NAN, // Never equal, forces the first expansion
+                        spacing,
+                        outline: Vec::new(),
+                        holes: Vec::new(),
+                        hole_ends: Vec::new(),
+                        segments: Vec::new(),
+                    });
+                    self.pattern_cache.len() - 1
+                }
+            };
+            let cached = &mut self.pattern_cache[slot];
+            let fresh = cached.kind == kind
+                && cached.angle == angle
+                && cached.spacing == spacing
+                && cached.outline == fill.points
+                && cached.holes == self.pattern_holes
+                && cached.hole_ends == self.pattern_hole_ends;
+            if !fresh {
+                cached.kind = kind;
+                cached.angle = angle;
+                cached.spacing = spacing;
+                cached.outline.clear();
+                cached.outline.extend_from_slice(&fill.points);
+                cached.holes.clear();
+                cached.holes.extend_from_slice(&self.pattern_holes);
+                cached.hole_ends.clear();
+                cached.hole_ends.extend_from_slice(&self.pattern_hole_ends);
+                cached.segments.clear();
+                let segments = &mut cached.segments;
+                expand_pattern(
+                    kind,
+                    angle,
+                    spacing,
+                    &fill.points,
+                    &self.pattern_holes,
+                    &self.pattern_hole_ends,
+                    &mut self.pattern_scratch,
+                    &mut |x1, y1, x2, y2| segments.extend_from_slice(&[x1, y1, x2, y2]),
+                );
+            }
+            for seg in cached.segments.chunks_exact(4) {
+                self.pattern_lines_buf.push(idx as f32);
+                self.pattern_lines_buf.extend_from_slice(seg);
+            }
+        }
+        self.pattern_lines_buf[0] = ((self.pattern_lines_buf.len() - 1) / 5) as f32;
+    }
+
+    fn pattern_lines_ptr(&self) -> *const f32 {
+        self.pattern_lines_buf.as_ptr()
+    }
+
+    fn pattern_lines_len(&self) -> u32 {
+        self.pattern_lines_buf.len() as u32
     }
 
     fn refresh_export(&mut self) {
@@ -1968,21 +3326,78 @@ impl Editor {
             self.export_buf.push(line.x2);
             self.export_buf.push(line.y2);
         }
+        self.line_ids_buf.clear();
+        self.line_ids_buf.extend(self.lines.iter().map(|l| l.id));
+        self.line_grid.rebuild_segments(self.lines.iter().map(|l| (l.x1, l.y1, l.x2, l.y2)));
+        self.line_strokes_buf.clear();
+        self.line_strokes_buf.extend(self.lines.iter().map(|l| l.stroke));
+        self.refresh_strokes_buf();
+        self.refresh_curves_buf();
+    }
+
+    // Curves whose stroke still exports as the one path they were flattened to;
+    // once trimmed, erased or simplified the stroke shows as its polyline
+    fn refresh_curves_buf(&mut self) {
+        self.curves_buf.clear();
+        self.curves_buf.push(0.0);
+        let mut i = 1;
+        for _ in 0..self.strokes_buf[0] as usize {
+            let (stroke, point_count) = (self.strokes_buf[i] as u32, self.strokes_buf[i + 1] as usize);
+            let points = &self.strokes_buf[i + 2..i + 2 + point_count * 2];
+            i += 2 + point_count * 2;
+            let Ok(at) = self.curves.binary_search_by_key(&stroke, |c| c.stroke) else {
+                continue;
+            };
+            let curve = &self.curves[at];
+            let (first, last) = (curve.segments[0].p0, curve.segments[curve.segments.len() - 1].p3);
+            if point_count != curve.point_count
+                || (points[0], points[1]) != first
+                || (points[points.len() - 2], points[points.len() - 1]) != last
+            {
+                continue;
+            }
+            self.curves_buf[0] += 1.0;
+            self.curves_buf.extend_from_slice(&[stroke as f32, curve.segments.len() as f32, first.0, first.1]);
+            for seg in curve.segments.iter() {
+                self.curves_buf.extend_from_slice(&[seg.p1.0, seg.p1.1, seg.p2.0, seg.p2.1, seg.p3.0, seg.p3.1]);
+            }
+        }
+    }
+
+    // Consecutive segments of one stroke that still chain end to start export as
+    // one path; a stroke cut apart (trim, erase, weld) exports as several
+    fn refresh_strokes_buf(&mut self) {
+        self.strokes_buf.clear();
+        self.strokes_buf.push(0.0);
+        let mut open_path: Option<usize> = None; // Index of the open path's point count
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.stroke == 0 {
+                open_path = None;
+                continue;
+            }
+            let chained = open_path.is_some() && {
+                let prev = &self.lines[i - 1];
+                prev.stroke == line.stroke && prev.x2 == line.x1 && prev.y2 == line.y1
+            };
+            if !chained {
+                self.strokes_buf[0] += 1.0;
+                self.strokes_buf.push(line.stroke as f32);
+                open_path = Some(self.strokes_buf.len());
+                self.strokes_buf.extend_from_slice(&[1.0, line.x1, line.y1]);
+            }
+            if let Some(at) = open_path {
+                self.strokes_buf[at] += 1.0;
+            }
+            self.strokes_buf.push(line.x2);
+            self.strokes_buf.push(line.y2);
+        }
     }
 
     #[allow(dead_code)]
     fn export_fills(&mut self) -> Vec<f32> {
         let mut result: Vec<f32> = Vec::new();
         for polygon in self.fills.iter() {
-            result.push(polygon.points.len() as f32);
-            result.push((polygon.color >> 24) as u8 as f32 / 255.0);
-            result.push(((polygon.color >> 16) & 0xFF) as u8 as f32 / 255.0);
-            result.push(((polygon.color >> 8) & 0xFF) as u8 as f32 / 255.0);
-            result.push((polygon.color & 0xFF) as u8 as f32 / 255.0);
-            for (x, y) in polygon.points.iter() {
-                result.push(*x);
-                result.push(*y);
-            }
+            write_fill_record(&mut result, polygon, &self.palette);
         }
         result
     }
@@ -1990,16 +3405,11 @@ impl Editor {
     fn refresh_fills_export_buf(&mut self) {
         self.fills_export_buf.clear();
         for polygon in self.fills.iter() {
-            self.fills_export_buf.push(polygon.points.len() as f32);
-            self.fills_export_buf.push((polygon.color >> 24) as u8 as f32 / 255.0);
-            self.fills_export_buf.push(((polygon.color >> 16) & 0xFF) as u8 as f32 / 255.0);
-            self.fills_export_buf.push(((polygon.color >> 8) & 0xFF) as u8 as f32 / 255.0);
-            self.fills_export_buf.push((polygon.color & 0xFF) as u8 as f32 / 255.0);
-            for (x, y) in polygon.points.iter() {
-                self.fills_export_buf.push(*x);
-                self.fills_export_buf.push(*y);
-            }
+            write_fill_record(&mut self.fills_export_buf, polygon, &self.palette);
         }
+        self.fill_ids_buf.clear();
+        self.fill_ids_buf.extend(self.fills.iter().map(|f| f.id));
+        self.fill_grid.rebuild_boxes(self.fills.iter().map(|f| poly_bounds(&f.points)));
     }
 
     // Recompute and cache intersection points whenever geometry changes
@@ -2017,24 +3427,13 @@ impl Editor {
             return;
         }
 
-        let mut best_line_idx = 0;
-        let mut best_t = 0.0;
-        let mut best_qx = 0.0;
-        let mut best_qy = 0.0;
-        let mut best_dist2 = f32::INFINITY;
+        let Some((best_line_idx, _)) = self.nearest_line_by(px, py, f32::INFINITY, line_segment_dist_sq) else {
+            self.debug_buf.push(0.0);
+            return;
+        };
+        let line = self.lines[best_line_idx];
+        let (best_t, best_qx, best_qy, best_dist2) = point_segment_nearest(px, py, line.x1, line.y1, line.x2, line.y2);
 
-        for (i, line) in self.lines.iter().enumerate() {
-            let (t, qx, qy, dist2) = point_segment_nearest(px, py, line.x1, line.y1, line.x2, line.y2);
-            if dist2 < best_dist2 {
-                best_dist2 = dist2;
-                best_line_idx = i;
-                best_t = t;
-                best_qx = qx;
-                best_qy = qy;
-            }
-        }
-
-        let line = &self.lines[best_line_idx];
         self.debug_buf.push(1.0); // hit flag
         self.debug_buf.push(line.x1);
         self.debug_buf.push(line.y1);
@@ -2046,7 +3445,145 @@ impl Editor {
         self.debug_buf.push(best_t);
     }
 
-    fn add_line(&mut self, line: Line) {
+    // Nearest line within `max_dist` by `dist_sq(line, x, y)`, searched through
+    // line_grid. `dist_sq` must measure to a point on the line.
+    fn nearest_line_by(&self, x: f32, y: f32, max_dist: f32, dist_sq: fn(&Line, f32, f32) -> f32) -> Option<(usize, f32)> {
+        self.line_grid
+            .nearest(x, y, max_dist, |i| self.lines.get(i as usize).map(|l| dist_sq(l, x, y)).unwrap_or(f32::INFINITY))
+            .map(|(i, d)| (i as usize, d))
+    }
+
+    // Up to `k` lines closest to the point within `max_dist` into spatial_buf.
+    // Returns the number found.
+    fn k_nearest_lines(&mut self, x: f32, y: f32, k: usize, max_dist: f32) -> u32 {
+        let found = self.line_grid.k_nearest(x, y, k, max_dist, |i| {
+            self.lines.get(i as usize).map(|l| line_segment_dist_sq(l, x, y)).unwrap_or(f32::INFINITY)
+        });
+        self.write_spatial_buf(found.iter().map(|&(i, _)| i as usize))
+    }
+
+    // Lines passing within `radius` of the point into spatial_buf, closest first
+    fn lines_in_radius(&mut self, x: f32, y: f32, radius: f32) -> u32 {
+        let mut found: Vec<(usize, f32)> = self
+            .line_grid
+            .query_rect(x - radius, y - radius, x + radius, y + radius)
+            .into_iter()
+            .filter_map(|i| {
+                let d = line_segment_dist_sq(self.lines.get(i as usize)?, x, y);
+                (d <= radius * radius).then_some((i as usize, d))
+            })
+            .collect();
+        found.sort_unstable_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(core::cmp::Ordering::Equal));
+        self.write_spatial_buf(found.iter().map(|&(i, _)| i))
+    }
+
+    fn write_spatial_buf(&mut self, line_idx: impl Iterator<Item = usize>) -> u32 {
+        self.spatial_buf.clear();
+        self.spatial_buf.push(0);
+        for idx in line_idx {
+            self.spatial_buf.push(self.lines[idx].id);
+        }
+        let count = self.spatial_buf.len() as u32 - 1;
+        self.spatial_buf[0] = count;
+        count
+    }
+
+    // Snap the pointer and record it in snap_buf. The closest endpoint,
+    // intersection node or midpoint within SNAP_RADIUS wins (ties in that order);
+    // otherwise the direction from the anchor is rounded to SNAP_ANGLE_STEP, else
+    // the point goes to the grid. Only kinds in `flags` apply; a non-finite anchor
+    // disables angle snapping. Returns the kind applied (0 = none).
+    fn snap(&mut self, x: f32, y: f32, anchor_x: f32, anchor_y: f32, flags: u32) -> u32 {
+        let (kind, sx, sy) = self.snap_point(x, y, (anchor_x, anchor_y), flags);
+        self.snap_buf.clear();
+        self.snap_buf.push(kind as f32);
+        self.snap_buf.push(sx);
+        self.snap_buf.push(sy);
+        kind
+    }
+
+    // Runs on every pointer move while drawing, so no allocation here
+    fn snap_point(&self, x: f32, y: f32, (ax, ay): (f32, f32), flags: u32) -> (u32, f32, f32) {
+        let mut best: Option<(u32, f32, f32, f32)> = None; // kind, x, y, dist2
+        let mut consider = |kind: u32, px: f32, py: f32| {
+            let d2 = distance_sq(x, y, px, py);
+            if d2 <= SNAP_RADIUS * SNAP_RADIUS
+                && best.map(|(bk, _, _, bd)| d2 < bd || (d2 == bd && kind < bk)).unwrap_or(true)
+            {
+                best = Some((kind, px, py, d2));
+            }
+        };
+        if flags & (SNAP_ENDPOINT | SNAP_MIDPOINT) != 0 {
+            let r = SNAP_RADIUS;
+            self.line_grid.visit_rect(x - r, y - r, x + r, y + r, |i| {
+                let Some(l) = self.lines.get(i as usize) else {
+                    return;
+                };
+                if flags & SNAP_ENDPOINT != 0 {
+                    consider(SNAP_ENDPOINT, l.x1, l.y1);
+                    consider(SNAP_ENDPOINT, l.x2, l.y2);
+                }
+                if flags & SNAP_MIDPOINT != 0 {
+                    consider(SNAP_MIDPOINT, (l.x1 + l.x2) * 0.5, (l.y1 + l.y2) * 0.5);
+                }
+            });
+        }
+        if flags & SNAP_INTERSECTION != 0 {
+            // intersections_export: [count, (x, y) * count]
+            for p in self.intersections_export.get(1..).unwrap_or(&[]).chunks_exact(2) {
+                consider(SNAP_INTERSECTION, p[0], p[1]);
+            }
+        }
+        if let Some((kind, sx, sy, _)) = best {
+            return (kind, sx, sy);
+        }
+
+        let (dx, dy) = (x - ax, y - ay);
+        if flags & SNAP_ANGLE != 0 && ax.is_finite() && ay.is_finite() && (dx != 0.0 || dy != 0.0) {
+            // Project onto the closest allowed direction (each axis covers both senses)
+            let mut best_axis = (0.0, 1.0, 0.0); // (along, cos, sin)
+            for k in 0..(180.0 / SNAP_ANGLE_STEP) as u32 {
+                let (sin, cos) = sin_cos_approx((k as f32 * SNAP_ANGLE_STEP).to_radians());
+                // Clean the series error so axis-aligned directions stay exact
+                let tidy = |v: f32| if absf(v) < 1e-6 { 0.0 } else if v > 1.0 - 1e-6 { 1.0 } else if v < -1.0 + 1e-6 { -1.0 } else { v };
+                let (sin, cos) = (tidy(sin), tidy(cos));
+                let along = dx * cos + dy * sin;
+                if absf(along) > absf(best_axis.0) {
+                    best_axis = (along, cos, sin);
+                }
+            }
+            let (along, cos, sin) = best_axis;
+            return (SNAP_ANGLE, ax + along * cos, ay + along * sin);
+        }
+
+        if flags & SNAP_GRID != 0 && self.snap_grid > 0.0 {
+            let g = self.snap_grid;
+            return (SNAP_GRID, floor_f32(x / g + 0.5) * g, floor_f32(y / g + 0.5) * g);
+        }
+        (0, x, y)
+    }
+
+    fn take_line_id(&mut self) -> u32 {
+        let id = self.next_line_id;
+        self.next_line_id += 1;
+        id
+    }
+
+    fn take_fill_id(&mut self) -> u32 {
+        let id = self.next_fill_id;
+        self.next_fill_id += 1;
+        id
+    }
+
+    fn line_index(&self, id: u32) -> Option<usize> {
+        self.lines.iter().position(|l| l.id == id)
+    }
+
+    fn fill_index(&self, id: u32) -> Option<usize> {
+        self.fills.iter().position(|f| f.id == id)
+    }
+
+    fn add_line(&mut self, mut line: Line) {
         // Validate coordinates
         check_line_coordinates(line.x1, line.y1, line.x2, line.y2);
         
@@ -2057,20 +3594,234 @@ impl Editor {
             return;
         }
         
+        // Auto-weld: pull each end onto nearby geometry, unless that collapses the line
+        let mut ends = [(line.x1, line.y1), (line.x2, line.y2)];
+        let split_lines = self.weld_chain_ends(&mut ends);
+        ((line.x1, line.y1), (line.x2, line.y2)) = (ends[0], ends[1]);
+
+        line.id = self.take_line_id();
         self.lines.push(line);
-        self.history.push(Command::Add);
+        self.history.push(if split_lines.is_empty() { Command::Add } else { Command::AddWelded(split_lines) });
+        self.refresh_export();
+        self.recompute_intersections();
+        self.build_fill_graph();
+    }
+
+    // Auto-weld the ends of a new chain of points (a line is a chain of two): pull
+    // each end onto nearby geometry, unless that collapses a segment at the end,
+    // record the welds in weld_buf and split the segments welded onto. Returns
+    // the split lines (index, original, pieces) for undo.
+    fn weld_chain_ends(&mut self, chain: &mut [(f32, f32)]) -> Vec<(usize, Line, usize)> {
+        self.weld_buf.clear();
+        self.weld_buf.push(0.0);
+        let n = chain.len();
+        if n < 2 {
+            return Vec::new();
+        }
+        let (first, last) = (chain[0], chain[n - 1]);
+        let mut targets = [self.weld_target(first.0, first.1), self.weld_target(last.0, last.1)];
+        let mut ends = [first, last];
+        for (end, target) in ends.iter_mut().zip(targets.iter()) {
+            if let Some((x, y, _)) = *target {
+                *end = (x, y);
+            }
+        }
+        let (second, before_last) = if n == 2 { (ends[1], ends[0]) } else { (chain[1], chain[n - 2]) };
+        if distance_sq(ends[0].0, ends[0].1, second.0, second.1) < 1.0
+            || distance_sq(before_last.0, before_last.1, ends[1].0, ends[1].1) < 1.0
+        {
+            targets = [None, None];
+        } else {
+            for (end, from, target) in [(0, first, targets[0]), (1, last, targets[1])] {
+                let Some((x, y, split)) = target else {
+                    continue;
+                };
+                if split.is_none() && (x, y) == from {
+                    continue; // Already exactly on the endpoint
+                }
+                let kind = if split.is_some() { WELD_SEGMENT } else { WELD_ENDPOINT };
+                self.weld_buf.extend_from_slice(&[end as f32, kind as f32, from.0, from.1, x, y]);
+                self.weld_buf[0] += 1.0;
+            }
+            (chain[0], chain[n - 1]) = (ends[0], ends[1]);
+        }
+
+        // Split the segments welded onto, highest index first so lower ones stay put
+        let mut splits: Vec<(usize, f32)> = targets.iter().flatten().filter_map(|t| t.2).collect();
+        splits.sort_by_key(|s| Reverse(s.0));
+        let mut split_lines: Vec<(usize, Line, usize)> = Vec::new();
+        for (k, &(idx, _)) in splits.iter().enumerate() {
+            if k > 0 && splits[k - 1].0 == idx {
+                continue; // Both ends on one line, split together
+            }
+            // Zero-width spans cut the line without removing anything
+            let spans: Vec<(f32, f32)> = splits.iter().filter(|s| s.0 == idx).map(|s| (s.1, s.1)).collect();
+            let original = self.lines[idx];
+            let pieces = lines_without_spans(&[original], &mut [spans], &mut self.next_line_id);
+            split_lines.push((idx, original, pieces.len()));
+            self.lines.splice(idx..idx + 1, pieces);
+        }
+        split_lines
+    }
+
+    // Undo weld_chain_ends' splits: put each original line back over its pieces
+    fn restore_split_lines(&mut self, split_lines: Vec<(usize, Line, usize)>) {
+        for (idx, original, pieces) in split_lines.into_iter().rev() {
+            let end = (idx + pieces).min(self.lines.len());
+            self.lines.splice(idx..end, core::iter::once(original));
+        }
+    }
+
+    // Remove the curves whose stroke has no lines left and return them for
+    // the command's undo
+    fn take_dead_curves(&mut self) -> Vec<Curve> {
+        if self.curves.is_empty() {
+            return Vec::new();
+        }
+        let mut strokes: Vec<u32> = self.lines.iter().map(|l| l.stroke).collect();
+        strokes.sort_unstable();
+        let mut dead = Vec::new();
+        let mut i = 0;
+        while i < self.curves.len() {
+            if strokes.binary_search(&self.curves[i].stroke).is_err() {
+                dead.push(self.curves.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        dead
+    }
+
+    // Undo take_dead_curves: curves stay sorted by stroke id
+    fn restore_curves(&mut self, curves: Vec<Curve>) {
+        for curve in curves {
+            if let Err(at) = self.curves.binary_search_by_key(&curve.stroke, |c| c.stroke) {
+                self.curves.insert(at, curve);
+            }
+        }
+    }
+
+    // Where a new line end at (x, y) welds to within weld_radius: the nearest
+    // endpoint, else the nearest point on a segment with the line index and
+    // parameter to split at (None when that point is the segment's end)
+    fn weld_target(&self, x: f32, y: f32) -> Option<WeldTarget> {
+        let radius = self.weld_radius;
+        if radius <= 0.0 {
+            return None;
+        }
+        if let Some((idx, _)) = self.nearest_line_by(x, y, radius, line_endpoint_dist_sq) {
+            let l = self.lines[idx];
+            let start_closer = distance_sq(x, y, l.x1, l.y1) <= distance_sq(x, y, l.x2, l.y2);
+            let (px, py) = if start_closer { (l.x1, l.y1) } else { (l.x2, l.y2) };
+            return Some((px, py, None));
+        }
+        let (idx, _) = self.nearest_line_by(x, y, radius, line_segment_dist_sq)?;
+        let l = self.lines[idx];
+        let (t, qx, qy, _) = point_segment_nearest(x, y, l.x1, l.y1, l.x2, l.y2);
+        let len = sqrt_precise(distance_sq(l.x1, l.y1, l.x2, l.y2));
+        let interior = t * len > SNAP_EPS && (1.0 - t) * len > SNAP_EPS;
+        Some((qx, qy, interior.then_some((idx, t))))
+    }
+
+    // Polyline through `points` as one stroke: a segment per step sharing a
+    // stroke id, added and undone as one step. Repeated points are dropped and
+    // the rest simplified with stroke_simplify within stroke_tolerance; the ends
+    // auto-weld like a line's. Returns the stroke id, 0 when fewer than two
+    // distinct points remain.
+    fn add_polyline(&mut self, points: &[(f32, f32)]) -> u32 {
+        let mut chain = simplify_points(&stroke_points(points), self.stroke_tolerance, self.stroke_simplify);
+        if chain.len() < 2 {
+            return 0;
+        }
+        let split_lines = self.weld_chain_ends(&mut chain);
+        let stroke = self.push_stroke(&chain);
+        self.history.push(Command::AddPolyline(chain.len() - 1, split_lines));
+        self.refresh_export();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        stroke
+    }
+
+    // Smooth stroke through freehand `points`, one undo step. The input is first
+    // decimated within `tolerance` (Chaikin, Catmull-Rom) or fitted within it
+    // (Fit). Chaikin adds a plain polyline stroke; the cubic methods keep their
+    // segments as a Curve and add its flattening as the stroke's lines. The ends
+    // auto-weld like a line's, the curve's end segments following them.
+    // Returns the stroke id, 0 when fewer than two distinct points remain.
+    fn add_curve(&mut self, points: &[(f32, f32)], method: CurveMethod, tolerance: f32) -> u32 {
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return 0;
+        }
+        let points = stroke_points(points);
+        if points.len() < 2 {
+            return 0;
+        }
+        let (mut chain, mut segments) = match method {
+            CurveMethod::Chaikin => {
+                let kept = simplify_points(&points, tolerance, SimplifyMethod::DouglasPeucker);
+                let smooth = chaikin(&kept, CHAIKIN_ITERATIONS);
+                (simplify_points(&smooth, CURVE_FLATNESS, SimplifyMethod::DouglasPeucker), None)
+            }
+            CurveMethod::CatmullRom => {
+                let segments = catmull_rom(&simplify_points(&points, tolerance, SimplifyMethod::DouglasPeucker));
+                (flatten(&segments, CURVE_FLATNESS), Some(segments))
+            }
+            CurveMethod::Fit => {
+                let segments = fit_cubics(&points, tolerance);
+                (flatten(&segments, CURVE_FLATNESS), Some(segments))
+            }
+        };
+
+        let (first, last) = (chain[0], chain[chain.len() - 1]);
+        let split_lines = self.weld_chain_ends(&mut chain);
+        if let Some(segments) = segments.as_mut() {
+            // Shift each welded end with its control point to keep the tangent
+            let (start, end) = (chain[0], chain[chain.len() - 1]);
+            let head = &mut segments[0];
+            head.p0 = start;
+            head.p1 = (head.p1.0 + start.0 - first.0, head.p1.1 + start.1 - first.1);
+            let tail = segments.last_mut().unwrap();
+            tail.p3 = end;
+            tail.p2 = (tail.p2.0 + end.0 - last.0, tail.p2.1 + end.1 - last.1);
+        }
+
+        let stroke = self.push_stroke(&chain);
+        match segments {
+            Some(segments) => {
+                self.curves.push(Curve { stroke, segments, point_count: chain.len() });
+                self.history.push(Command::AddCurve(chain.len() - 1, split_lines));
+            }
+            None => self.history.push(Command::AddPolyline(chain.len() - 1, split_lines)),
+        }
         self.refresh_export();
         self.recompute_intersections();
         self.build_fill_graph();
+        stroke
+    }
+
+    // Lines along `chain` under a new stroke id (no history, no refresh)
+    fn push_stroke(&mut self, chain: &[(f32, f32)]) -> u32 {
+        let stroke = self.next_stroke_id;
+        self.next_stroke_id += 1;
+        for pair in chain.windows(2) {
+            let mut line = Line::new(pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+            line.id = self.take_line_id();
+            line.stroke = stroke;
+            self.lines.push(line);
+        }
+        stroke
     }
 
     fn add_frame(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32, x4: f32, y4: f32) {
         // Add 4 lines forming a rectangle frame
         // Corners in clockwise order: (x1,y1), (x2,y2), (x3,y3), (x4,y4)
-        self.lines.push(Line { x1, y1, x2, y2 }); // top
-        self.lines.push(Line { x1: x2, y1: y2, x2: x3, y2: y3 }); // right
-        self.lines.push(Line { x1: x3, y1: y3, x2: x4, y2: y4 }); // bottom
-        self.lines.push(Line { x1: x4, y1: y4, x2: x1, y2: y1 }); // left
+        let corners = [(x1, y1), (x2, y2), (x3, y3), (x4, y4)]; // top, right, bottom, left edges
+        for i in 0..4 {
+            let ((ax, ay), (bx, by)) = (corners[i], corners[(i + 1) % 4]);
+            let mut line = Line::new(ax, ay, bx, by);
+            line.id = self.take_line_id();
+            self.lines.push(line);
+        }
         
         // Push single grouped undo command
         self.history.push(Command::AddFrame);
@@ -2082,11 +3833,13 @@ impl Editor {
     fn clear(&mut self) {
         let previous_lines = self.lines.clone();
         let previous_fills = self.fills.clone();
+        let previous_curves = core::mem::take(&mut self.curves);
         self.lines.clear();
         self.fills.clear();
-        self.history.push(Command::Clear(previous_lines, previous_fills));
+        self.history.push(Command::Clear(previous_lines, previous_fills, previous_curves));
         self.fill_trace_buf.clear();
         self.refresh_export();
+        self.refresh_export_fills();
         self.recompute_intersections();
         self.build_fill_graph();
         
@@ -2098,7 +3851,23 @@ impl Editor {
         }
     }
 
-    // Overhang trimming = 2-core leaf stripping on cut-segment graph
+    // Segments Clean would remove, written to overhang_preview_buf without
+    // changing anything. Uses the current graph (rebuilt on every edit).
+    fn preview_overhangs(&mut self) -> u32 {
+        let graph = &self.fill_graph;
+        let deleted = overhang_segments(graph);
+        self.overhang_preview_buf.clear();
+        self.overhang_preview_buf.push(0.0);
+        for (seg, _) in graph.segments.iter().zip(deleted.iter()).filter(|(_, &d)| d) {
+            let a = graph.nodes[seg.a as usize];
+            let b = graph.nodes[seg.b as usize];
+            self.overhang_preview_buf.extend_from_slice(&[a.x, a.y, b.x, b.y]);
+        }
+        let count = ((self.overhang_preview_buf.len() - 1) / 4) as u32;
+        self.overhang_preview_buf[0] = count as f32;
+        count
+    }
+
     fn cleanup_overhangs(&mut self) {
         // Save current state for undo
         let previous_lines = self.lines.clone();
@@ -2118,81 +3887,8 @@ impl Editor {
             return;
         }
         
-        // Compute initial node degrees from segments
-        let mut degree: Vec<u32> = Vec::new();
-        degree.resize(graph.nodes.len(), 0);
-        
-        for seg in &graph.segments {
-            let a_idx = seg.a as usize;
-            let b_idx = seg.b as usize;
-            if a_idx < degree.len() {
-                degree[a_idx] += 1;
-            }
-            if b_idx < degree.len() {
-                degree[b_idx] += 1;
-            }
-        }
-        
-        // Initialize queue with all nodes where degree <= 1 (leaves and isolated)
-        let mut queue: Vec<usize> = Vec::new();
-        for (node_idx, &deg) in degree.iter().enumerate() {
-            if deg <= 1 {
-                queue.push(node_idx);
-            }
-        }
-        
-        // Mark segments for deletion via 2-core leaf stripping
-        let mut delete_segment: Vec<bool> = Vec::new();
-        delete_segment.resize(total_segments, false);
-        let mut deleted_count: u32 = 0;
-        
-        while let Some(node_idx) = queue.pop() {
-            // Re-check degree (may have changed)
-            if degree[node_idx] > 1 {
-                continue;
-            }
-            
-            if degree[node_idx] == 0 {
-                // Isolated node, nothing to do
-                continue;
-            }
-            
-            // degree[node_idx] == 1: find the single alive segment
-            let mut alive_segment_idx: Option<usize> = None;
-            for (seg_idx, &is_deleted) in delete_segment.iter().enumerate() {
-                if is_deleted {
-                    continue;
-                }
-                let seg = &graph.segments[seg_idx];
-                let a_idx = seg.a as usize;
-                let b_idx = seg.b as usize;
-                if a_idx == node_idx || b_idx == node_idx {
-                    alive_segment_idx = Some(seg_idx);
-                    break;
-                }
-            }
-            
-            if let Some(seg_idx) = alive_segment_idx {
-                let seg = &graph.segments[seg_idx];
-                let a_idx = seg.a as usize;
-                let b_idx = seg.b as usize;
-                let other_idx = if a_idx == node_idx { b_idx } else { a_idx };
-                
-                // Mark segment for deletion
-                delete_segment[seg_idx] = true;
-                deleted_count += 1;
-                
-                // Update degrees
-                degree[node_idx] = 0;
-                if other_idx < degree.len() && degree[other_idx] > 0 {
-                    degree[other_idx] -= 1;
-                    // If other node becomes a leaf, add to queue
-                    if degree[other_idx] <= 1 {
-                        queue.push(other_idx);
-                    }
-                }
-            }
-        }
+        let delete_segment = overhang_segments(graph);
+        let deleted_count = delete_segment.iter().filter(|&&d| d).count() as u32;
         
         let kept_count = total_segments - (deleted_count as usize);
         
@@ -2209,345 +3905,1952 @@ impl Editor {
             return;
         }
         
-        // Collect all KEPT segments as new lines
-        let mut new_lines: Vec<Line> = Vec::new();
-        
+        // Cut the deleted spans out of their source lines. Lines without
+        // dangling parts stay untouched; the rest are shortened or split, and
+        // kept neighbouring segments of one line stay a single line.
+        let mut removed: Vec<Vec<(f32, f32)>> = Vec::new();
+        removed.resize(self.lines.len(), Vec::new());
         for (seg_idx, &is_deleted) in delete_segment.iter().enumerate() {
             if is_deleted {
-                continue;
-            }
-            
-            if seg_idx >= graph.segments.len() {
-                continue;
-            }
-            
-            let seg = &graph.segments[seg_idx];
-            let a_idx = seg.a as usize;
-            let b_idx = seg.b as usize;
-            
-            if a_idx >= graph.nodes.len() || b_idx >= graph.nodes.len() {
-                continue;
+                let seg = &graph.segments[seg_idx];
+                removed[seg.line as usize].push((seg.t0, seg.t1));
             }
-            
-            let node_a = &graph.nodes[a_idx];
-            let node_b = &graph.nodes[b_idx];
-            
-            new_lines.push(Line {
-                x1: node_a.x,
-                y1: node_a.y,
-                x2: node_b.x,
-                y2: node_b.y,
-            });
         }
-        
+
         // Update lines with trimmed segments
-        self.lines = new_lines;
+        self.lines = lines_without_spans(&previous_lines, &mut removed, &mut self.next_line_id);
         
         // Push undo command
-        self.history.push(Command::CleanOverhangs(previous_lines));
+        let dead_curves = self.take_dead_curves();
+        self.history.push(Command::CleanOverhangs(previous_lines, dead_curves));
         
         // Refresh everything
         self.refresh_export();
         self.recompute_intersections();
         self.build_fill_graph();
     }
-    fn undo(&mut self) {
-        match self.history.pop() {
-            Some(Command::Add) => {
-                self.lines.pop();
+
+    // Trim: delete the parts of strokes lying strictly inside fills (all fills when
+    // `only` is None). Lines are split at the cut-segment graph nodes; untouched
+    // lines are kept exactly. Returns the number of segments removed.
+    fn trim_inside_fills(&mut self, only: Option<&[usize]>) -> u32 {
+        if self.lines.is_empty() || self.fills.is_empty() {
+            return 0;
+        }
+        self.build_fill_graph();
+
+        let eps2 = SNAP_EPS * SNAP_EPS;
+        let mut removed: Vec<Vec<(f32, f32)>> = Vec::new();
+        removed.resize(self.lines.len(), Vec::new());
+        let mut removed_count: u32 = 0;
+        for seg in self.fill_graph.segments.iter() {
+            let a = self.fill_graph.nodes[seg.a as usize];
+            let b = self.fill_graph.nodes[seg.b as usize];
+            let mid = ((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
+            let inside = self.fills.iter().enumerate().any(|(idx, fill)| {
+                only.map(|sel| sel.contains(&idx)).unwrap_or(true)
+                    && fill.is_closed()
+                    && point_in_poly_evenodd(mid, &fill.points)
+                    && min_dist_sq_to_polygon(mid, &fill.points) > eps2
+            });
+            if inside {
+                removed[seg.line as usize].push((seg.t0, seg.t1));
+                removed_count += 1;
             }
-            Some(Command::AddFill) => {
-                self.fills.pop();
+        }
+
+        if removed_count == 0 {
+            return 0;
+        }
+        let previous_lines = core::mem::take(&mut self.lines);
+        self.lines = lines_without_spans(&previous_lines, &mut removed, &mut self.next_line_id);
+        let dead_curves = self.take_dead_curves();
+        self.history.push(Command::TrimFills(previous_lines, dead_curves));
+        self.refresh_export();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        removed_count
+    }
+
+    // Scissors: delete the span of the nearest line between its neighbouring
+    // intersections (or endpoints), i.e. the cut-graph segment under the cursor
+    fn trim_at(&mut self, x: f32, y: f32) -> bool {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, seg) in self.fill_graph.segments.iter().enumerate() {
+            let a = self.fill_graph.nodes[seg.a as usize];
+            let b = self.fill_graph.nodes[seg.b as usize];
+            let (_, _, _, d2) = point_segment_nearest(x, y, a.x, a.y, b.x, b.y);
+            if d2 <= TRIM_PICK_RADIUS * TRIM_PICK_RADIUS && best.map(|(_, bd)| d2 < bd).unwrap_or(true) {
+                best = Some((i, d2));
             }
-            Some(Command::AddFrame) => {
-                // Remove last 4 lines (frame is always 4 lines added together)
-                for _ in 0..4 {
-                    self.lines.pop();
+        }
+        let Some((seg_idx, _)) = best else {
+            return false;
+        };
+
+        let seg = self.fill_graph.segments[seg_idx];
+        let line_idx = seg.line as usize;
+        let original = self.lines[line_idx];
+        let pieces = lines_without_spans(&[original], &mut [alloc::vec![(seg.t0, seg.t1)]], &mut self.next_line_id);
+        let piece_count = pieces.len();
+        self.lines.splice(line_idx..line_idx + 1, pieces);
+        let dead_curves = self.take_dead_curves();
+        self.history.push(Command::TrimAt(line_idx, original, piece_count, dead_curves));
+        self.refresh_export();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        true
+    }
+
+    // Extend: move the free line endpoint nearest (x, y) along the line direction
+    // to the first line the ray hits within max_dist. Ends another line's end
+    // touches are joints and never move, so a corner cannot come apart.
+    fn extend_at(&mut self, x: f32, y: f32, max_dist: f32) -> bool {
+        let pick2 = TRIM_PICK_RADIUS * TRIM_PICK_RADIUS;
+        let eps2 = SNAP_EPS * SNAP_EPS;
+        let is_joint = |idx: usize, px: f32, py: f32| {
+            self.lines.iter().enumerate().any(|(j, other)| {
+                j != idx
+                    && (distance_sq(px, py, other.x1, other.y1) <= eps2 || distance_sq(px, py, other.x2, other.y2) <= eps2)
+            })
+        };
+        let mut best: Option<(usize, bool, f32)> = None; // (line, moves end 2, dist2)
+        for (i, line) in self.lines.iter().enumerate() {
+            for (is_end, px, py) in [(false, line.x1, line.y1), (true, line.x2, line.y2)] {
+                let d2 = distance_sq(x, y, px, py);
+                if d2 <= pick2 && best.map(|(_, _, bd)| d2 < bd).unwrap_or(true) && !is_joint(i, px, py) {
+                    best = Some((i, is_end, d2));
                 }
             }
-            Some(Command::Clear(previous_lines, previous_fills)) => {
-                self.lines = previous_lines;
-                self.fills = previous_fills;
+        }
+        let Some((line_idx, is_end, _)) = best else {
+            return false;
+        };
+
+        let line = self.lines[line_idx];
+        let (ox, oy, fx, fy) = if is_end {
+            (line.x2, line.y2, line.x1, line.y1)
+        } else {
+            (line.x1, line.y1, line.x2, line.y2)
+        };
+        let len = sqrt_precise(distance_sq(fx, fy, ox, oy));
+        if len < SNAP_EPS {
+            return false;
+        }
+        let (dx, dy) = ((ox - fx) / len, (oy - fy) / len);
+
+        // Ray o + s * d against every other line: nearest hit with SNAP_EPS < s <= max_dist
+        let mut hit: Option<f32> = None;
+        for (i, other) in self.lines.iter().enumerate() {
+            if i == line_idx {
+                continue;
             }
-            Some(Command::CleanOverhangs(previous_lines)) => {
-                self.lines = previous_lines;
+            let (ex, ey) = (other.x2 - other.x1, other.y2 - other.y1);
+            let denom = dx * ey - dy * ex;
+            if absf(denom) < 1e-9 {
+                continue; // Parallel
+            }
+            let (wx, wy) = (other.x1 - ox, other.y1 - oy);
+            let s = (wx * ey - wy * ex) / denom;
+            let u = (wx * dy - wy * dx) / denom;
+            if s > SNAP_EPS && s <= max_dist && (0.0..=1.0).contains(&u) && hit.map(|h| s < h).unwrap_or(true) {
+                hit = Some(s);
+            }
+        }
+        let Some(s) = hit else {
+            return false;
+        };
+
+        let (nx, ny) = (ox + dx * s, oy + dy * s);
+        if is_end {
+            self.lines[line_idx].x2 = nx;
+            self.lines[line_idx].y2 = ny;
+        } else {
+            self.lines[line_idx].x1 = nx;
+            self.lines[line_idx].y1 = ny;
+        }
+        self.history.push(Command::ExtendLine(line_idx, line));
+        self.refresh_export();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        true
+    }
+
+    // Eraser: cut every line where it enters and leaves the brush (capsules of
+    // `radius` around each path segment) and drop the covered spans, as one undo
+    // step. Fills whose outline the brush touched are traced again from an inside
+    // point: a changed face replaces the outline, no closed face marks it stale.
+    // Returns the number of lines touched.
+    fn erase(&mut self, path: &[(f32, f32)], radius: f32) -> u32 {
+        if path.is_empty() || radius <= 0.0 {
+            return 0;
+        }
+        let brush: Vec<((f32, f32), (f32, f32))> = if path.len() == 1 {
+            alloc::vec![(path[0], path[0])]
+        } else {
+            path.windows(2).map(|w| (w[0], w[1])).collect()
+        };
+
+        let (minx, miny, maxx, maxy) = poly_bounds(path);
+        let (minx, miny, maxx, maxy) = (minx - radius, miny - radius, maxx + radius, maxy + radius);
+
+        let mut removed: Vec<Vec<(f32, f32)>> = Vec::new();
+        removed.resize(self.lines.len(), Vec::new());
+        let mut touched: u32 = 0;
+        for idx in self.line_grid.query_rect(minx, miny, maxx, maxy) {
+            let (spans, line) = (&mut removed[idx as usize], &self.lines[idx as usize]);
+            spans.extend(brush.iter().filter_map(|&(p, q)| capsule_span(line, p, q, radius)));
+            if !spans.is_empty() {
+                touched += 1;
             }
-            None => {}
         }
+        if touched == 0 {
+            return 0;
+        }
+
+        let touched_fills: Vec<usize> = self
+            .fill_grid
+            .query_rect(minx, miny, maxx, maxy)
+            .into_iter()
+            .map(|i| i as usize)
+            .filter(|&idx| {
+                let points = &self.fills[idx].points;
+                (0..points.len()).any(|i| {
+                    let (x1, y1) = points[i];
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    let edge = Line::new(x1, y1, x2, y2);
+                    brush.iter().any(|&(p, q)| capsule_span(&edge, p, q, radius).is_some())
+                })
+            })
+            .collect();
+
+        let previous_lines = core::mem::take(&mut self.lines);
+        self.lines = lines_without_spans(&previous_lines, &mut removed, &mut self.next_line_id);
+        self.build_fill_graph();
+
+        let previous_fills = self.retrace_fills(touched_fills);
+        let dead_curves = self.take_dead_curves();
+        self.history.push(Command::Erase(previous_lines, previous_fills, dead_curves));
         self.refresh_export();
         self.refresh_export_fills();
         self.recompute_intersections();
         self.build_fill_graph();
-        
-        // Verify state after undo in debug builds
-        check_editor_integrity(self);
+        touched
     }
 
-    fn line_count(&self) -> u32 {
-        self.lines.len() as u32
+    // Trace `fills` again from an inside point after their boundary lines
+    // changed (the fill graph must be current): a changed face replaces the
+    // outline, no closed face marks the fill stale. Returns the previous state
+    // of every fill that changed.
+    fn retrace_fills(&mut self, fills: Vec<usize>) -> Vec<(usize, Polygon)> {
+        let mut previous_fills: Vec<(usize, Polygon)> = Vec::new();
+        for idx in fills {
+            let seed = interior_point(&self.fills[idx].points);
+            let region = match seed {
+                Some((x, y)) => self.trace_region_graph(x, y),
+                None => RegionTrace::empty(FillStatus::NoBoundary),
+            };
+            let fill = &self.fills[idx];
+            if region.status == FillStatus::Filled && same_face(&fill.points, &region.points) && !fill.stale {
+                continue;
+            }
+            previous_fills.push((idx, fill.clone()));
+            let fill = &mut self.fills[idx];
+            if region.status == FillStatus::Filled {
+                fill.points = region.points;
+                if fill.points.first() != fill.points.last() {
+                    fill.points.push(fill.points[0]);
+                }
+                fill.stale = false;
+            } else {
+                fill.stale = true;
+            }
+        }
+        previous_fills
     }
 
-    fn export_ptr(&self) -> *const f32 {
-        self.export_buf.as_ptr()
+    // Selection: `add` keeps the current selection, otherwise it is replaced.
+    // Returns the number of lines and fills selected afterwards.
+    fn select(&mut self, lines: Vec<u32>, fills: Vec<u32>, add: bool) -> u32 {
+        if !add {
+            self.selected_lines.clear();
+            self.selected_fills.clear();
+        }
+        for id in lines {
+            if !self.selected_lines.contains(&id) {
+                self.selected_lines.push(id);
+            }
+        }
+        for id in fills {
+            if !self.selected_fills.contains(&id) {
+                self.selected_fills.push(id);
+            }
+        }
+        self.refresh_selection_buf();
+        (self.selected_lines.len() + self.selected_fills.len()) as u32
+    }
+
+    // Lines and fills entirely inside the rectangle
+    fn select_rect(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, add: bool) -> u32 {
+        self.select_region(&rect_polygon(x1, y1, x2, y2), add)
+    }
+
+    // Lines and fills entirely inside the lasso polygon
+    fn select_lasso(&mut self, lasso: &[(f32, f32)], add: bool) -> u32 {
+        self.select_region(lasso, add)
+    }
+
+    fn select_region(&mut self, region: &[(f32, f32)], add: bool) -> u32 {
+        let (line_idx, fill_idx) = self.query_region(region, false);
+        let lines: Vec<u32> = line_idx.iter().map(|&i| self.lines[i].id).collect();
+        let fills: Vec<u32> = fill_idx.iter().map(|&i| self.fills[i].id).collect();
+        self.select(lines, fills, add)
+    }
+
+    // Region query: indices of the lines and fills entirely inside `region`, or
+    // with `partial` those that cross, touch or lie in it (fills: the polygons
+    // intersect). A region with fewer than 3 points matches nothing. A
+    // self-intersecting region (see is_simple_polygon) is not rejected: inside
+    // is even-odd, so the lobes of a figure-eight lasso both count while an area
+    // the outline wraps twice is outside.
+    fn query_region(&self, region: &[(f32, f32)], partial: bool) -> (Vec<usize>, Vec<usize>) {
+        if region.len() < 3 {
+            return (Vec::new(), Vec::new());
+        }
+        let segment_matches = |a: (f32, f32), b: (f32, f32)| {
+            if partial {
+                segment_touches_polygon(a, b, region)
+            } else {
+                segment_inside_polygon(a, b, region)
+            }
+        };
+        let (minx, miny, maxx, maxy) = poly_bounds(region);
+        let lines: Vec<usize> = self
+            .line_grid
+            .query_rect(minx, miny, maxx, maxy)
+            .into_iter()
+            .map(|i| i as usize)
+            .filter(|&i| {
+                let l = &self.lines[i];
+                segment_matches((l.x1, l.y1), (l.x2, l.y2))
+            })
+            .collect();
+        let fills: Vec<usize> = self
+            .fill_grid
+            .query_rect(minx, miny, maxx, maxy)
+            .into_iter()
+            .map(|i| i as usize)
+            .filter(|&i| {
+                let points = &self.fills[i].points;
+                self.fills[i].is_closed()
+                    && if partial {
+                        polygons_intersect(points, region)
+                    } else {
+                        (0..points.len()).all(|k| segment_matches(points[k], points[(k + 1) % points.len()]))
+                    }
+            })
+            .collect();
+        (lines, fills)
+    }
+
+    // Run a region query into query_buf (as ids). Returns the number of matches.
+    fn query_into_buf(&mut self, region: &[(f32, f32)], partial: bool) -> u32 {
+        let (lines, fills) = self.query_region(region, partial);
+        self.query_buf.clear();
+        self.query_buf.push(lines.len() as u32);
+        self.query_buf.extend(lines.iter().map(|&i| self.lines[i].id));
+        self.query_buf.push(fills.len() as u32);
+        self.query_buf.extend(fills.iter().map(|&i| self.fills[i].id));
+        (lines.len() + fills.len()) as u32
+    }
+
+    // Make the last query result the selection (for bulk operations)
+    fn select_query(&mut self, add: bool) -> u32 {
+        let Some(&line_count) = self.query_buf.first() else {
+            return self.select(Vec::new(), Vec::new(), add);
+        };
+        let line_count = line_count as usize;
+        let lines = self.query_buf[1..1 + line_count].to_vec();
+        let fills = self.query_buf[2 + line_count..].to_vec();
+        self.select(lines, fills, add)
+    }
+
+    // Copy: serialize the given lines and fills into clipboard_buf (out-of-range
+    // indices are skipped). Strokes are renumbered from 1 in order of appearance;
+    // a curve goes along when every line of its stroke is copied. Returns the
+    // blob length.
+    fn copy_items(&mut self, line_idx: &[usize], fill_idx: &[usize]) -> u32 {
+        let mut lines: Vec<Line> = line_idx.iter().filter_map(|&i| self.lines.get(i).copied()).collect();
+        let mut strokes: Vec<u32> = Vec::new(); // Editor stroke id per blob stroke - 1
+        for line in lines.iter_mut().filter(|l| l.stroke != 0) {
+            line.stroke = match strokes.iter().position(|&s| s == line.stroke) {
+                Some(at) => at as u32 + 1,
+                None => {
+                    strokes.push(line.stroke);
+                    strokes.len() as u32
+                }
+            };
+        }
+        let curves: Vec<(u32, &Curve)> = strokes
+            .iter()
+            .enumerate()
+            .filter_map(|(at, &stroke)| {
+                let curve = &self.curves[self.curves.binary_search_by_key(&stroke, |c| c.stroke).ok()?];
+                let local = at as u32 + 1;
+                let copied = lines.iter().filter(|l| l.stroke == local).count();
+                let whole = self.lines.iter().filter(|l| l.stroke == stroke).count();
+                (copied == whole).then_some((local, curve))
+            })
+            .collect();
+        let fills: Vec<(&Polygon, u32)> = fill_idx
+            .iter()
+            .filter_map(|&i| self.fills.get(i))
+            .map(|f| (f, self.fill_color_of(f)))
+            .collect();
+        self.clipboard_buf = clipboard::encode(&lines, &curves, &fills);
+        self.clipboard_buf.len() as u32
+    }
+
+    fn copy_rect(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> u32 {
+        let (lines, fills) = self.query_region(&rect_polygon(x1, y1, x2, y2), false);
+        self.copy_items(&lines, &fills)
+    }
+
+    fn copy_selection(&mut self) -> u32 {
+        let lines: Vec<usize> = self.selected_lines.iter().filter_map(|&id| self.line_index(id)).collect();
+        let fills: Vec<usize> = self.selected_fills.iter().filter_map(|&id| self.fill_index(id)).collect();
+        self.copy_items(&lines, &fills)
+    }
+
+    // Paste a blob offset by (dx, dy) as one undo step. Pasted items get fresh ids
+    // (strokes too, so they stay separate from the ones copied) and become the
+    // selection. Returns the number of lines + fills inserted.
+    fn paste(&mut self, blob: &[u8], dx: f32, dy: f32) -> Result<u32, clipboard::ClipError> {
+        let clip = clipboard::decode(blob).inspect_err(|&err| self.paste_error = Some(err))?;
+        self.paste_error = None;
+        let offset = Affine::translate(dx, dy);
+        let (line_count, fill_count, curve_count) = (clip.lines.len(), clip.fills.len(), clip.curves.len());
+        let mut line_ids: Vec<u32> = Vec::with_capacity(line_count);
+        let mut fill_ids: Vec<u32> = Vec::with_capacity(fill_count);
+        // Blob stroke n becomes stroke first_stroke + n - 1
+        let first_stroke = self.next_stroke_id;
+        let stroke_count = clip.lines.iter().map(|l| l.stroke).max().unwrap_or(0);
+        self.next_stroke_id += stroke_count;
+        let restroke = |stroke: u32| if stroke == 0 { 0 } else { first_stroke + stroke - 1 };
+        for mut line in clip.lines {
+            (line.x1, line.y1) = offset.apply(line.x1, line.y1);
+            (line.x2, line.y2) = offset.apply(line.x2, line.y2);
+            line.id = self.take_line_id();
+            line.stroke = restroke(line.stroke);
+            line_ids.push(line.id);
+            self.lines.push(line);
+        }
+        // New stroke ids are above every existing one, so curves stay sorted
+        for mut curve in clip.curves {
+            curve.stroke = restroke(curve.stroke);
+            for seg in curve.segments.iter_mut() {
+                *seg = seg.map(|x, y| offset.apply(x, y));
+            }
+            self.curves.push(curve);
+        }
+        for mut fill in clip.fills {
+            for p in fill.points.iter_mut() {
+                *p = offset.apply(p.0, p.1);
+            }
+            fill.paint.transform(&offset, 0.0);
+            fill.id = self.take_fill_id();
+            fill_ids.push(fill.id);
+            self.fills.push(fill);
+        }
+        if line_count + fill_count == 0 {
+            return Ok(0);
+        }
+
+        self.history.push(Command::Paste(line_count, fill_count, curve_count));
+        self.select(line_ids, fill_ids, false);
+        self.refresh_export();
+        self.refresh_export_fills();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        Ok((line_count + fill_count) as u32)
+    }
+
+    // Hit test: the nearest line (its whole stroke for a polyline segment)
+    // within TRIM_PICK_RADIUS, else the topmost fill
+    fn select_at(&mut self, x: f32, y: f32, add: bool) -> u32 {
+        if let Some((idx, _)) = self.nearest_line_by(x, y, TRIM_PICK_RADIUS, line_segment_dist_sq) {
+            let hit = self.lines[idx];
+            let ids: Vec<u32> = if hit.stroke == 0 {
+                alloc::vec![hit.id]
+            } else {
+                self.lines.iter().filter(|l| l.stroke == hit.stroke).map(|l| l.id).collect()
+            };
+            return self.select(ids, Vec::new(), add);
+        }
+        match self.fill_hit_test(x, y) {
+            Some(idx) => {
+                let id = self.fills[idx].id;
+                self.select(Vec::new(), alloc::vec![id], add)
+            }
+            None => self.select(Vec::new(), Vec::new(), add),
+        }
+    }
+
+    fn refresh_selection_buf(&mut self) {
+        let lines: Vec<u32> = self.selected_lines.iter().copied().filter(|&id| self.line_index(id).is_some()).collect();
+        let fills: Vec<u32> = self.selected_fills.iter().copied().filter(|&id| self.fill_index(id).is_some()).collect();
+        self.selection_buf.clear();
+        self.selection_buf.push(lines.len() as u32);
+        self.selection_buf.extend_from_slice(&lines);
+        self.selection_buf.push(fills.len() as u32);
+        self.selection_buf.extend_from_slice(&fills);
+    }
+
+    // Apply `m` to the selected lines, the selected fills and every fill whose
+    // outline lies on selected lines, as one undo step. `turn` is the rotation in
+    // degrees carried into pattern angles. Returns the number of items moved.
+    fn transform_selection(&mut self, m: Affine, turn: f32) -> u32 {
+        if !m.is_finite() {
+            return 0;
+        }
+        let line_idx: Vec<usize> = self.selected_lines.iter().filter_map(|&id| self.line_index(id)).collect();
+        let eps2 = SNAP_EPS * SNAP_EPS;
+        let on_selected_lines = |p: &(f32, f32)| {
+            line_idx.iter().any(|&i| {
+                let l = &self.lines[i];
+                point_segment_nearest(p.0, p.1, l.x1, l.y1, l.x2, l.y2).3 <= eps2
+            })
+        };
+        let fill_idx: Vec<usize> = (0..self.fills.len())
+            .filter(|&i| {
+                let fill = &self.fills[i];
+                self.selected_fills.contains(&fill.id)
+                    || (!line_idx.is_empty() && fill.is_closed() && fill.points.iter().all(on_selected_lines))
+            })
+            .collect();
+        if line_idx.is_empty() && fill_idx.is_empty() {
+            return 0;
+        }
+
+        let mut previous_lines: Vec<(usize, Line)> = Vec::with_capacity(line_idx.len());
+        for &i in line_idx.iter() {
+            let line = &mut self.lines[i];
+            previous_lines.push((i, *line));
+            (line.x1, line.y1) = m.apply(line.x1, line.y1);
+            (line.x2, line.y2) = m.apply(line.x2, line.y2);
+        }
+        let mut previous_fills: Vec<(usize, Polygon)> = Vec::with_capacity(fill_idx.len());
+        for &i in fill_idx.iter() {
+            let fill = &mut self.fills[i];
+            previous_fills.push((i, fill.clone()));
+            for p in fill.points.iter_mut() {
+                *p = m.apply(p.0, p.1);
+            }
+            fill.paint.transform(&m, turn);
+        }
+        // Curves move along when their whole stroke is selected
+        let mut strokes: Vec<u32> = line_idx.iter().map(|&i| self.lines[i].stroke).filter(|&s| s != 0).collect();
+        strokes.sort_unstable();
+        strokes.dedup();
+        let mut previous_curves: Vec<(usize, Curve)> = Vec::new();
+        for stroke in strokes {
+            let Ok(i) = self.curves.binary_search_by_key(&stroke, |c| c.stroke) else {
+                continue;
+            };
+            let selected = line_idx.iter().filter(|&&l| self.lines[l].stroke == stroke).count();
+            if selected != self.lines.iter().filter(|l| l.stroke == stroke).count() {
+                continue;
+            }
+            let curve = &mut self.curves[i];
+            previous_curves.push((i, curve.clone()));
+            for seg in curve.segments.iter_mut() {
+                *seg = seg.map(|x, y| m.apply(x, y));
+            }
+        }
+
+        let moved = (previous_lines.len() + previous_fills.len()) as u32;
+        self.history.push(Command::Transform(previous_lines, previous_fills, previous_curves));
+        self.refresh_export();
+        self.refresh_export_fills();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        moved
+    }
+
+    // Replace runs of selected lines with simplified polylines that stay within
+    // `tolerance` of the original, as one undo step. A run continues through a
+    // point only where exactly two selected lines meet end to end, so junctions,
+    // crossings and the ends of unselected lines keep their place. Fills whose
+    // outline lay on a replaced line are traced again. Returns the number of
+    // lines removed.
+    fn simplify_selection(&mut self, tolerance: f32, method: SimplifyMethod) -> u32 {
+        if !tolerance.is_finite() || tolerance <= 0.0 {
+            return 0;
+        }
+        let mut selected: Vec<bool> = alloc::vec![false; self.lines.len()];
+        for &id in self.selected_lines.iter() {
+            if let Some(i) = self.line_index(id) {
+                selected[i] = true;
+            }
+        }
+
+        let eps2 = SNAP_EPS * SNAP_EPS;
+        let ends_at = |l: &Line, (x, y): (f32, f32)| {
+            distance_sq(l.x1, l.y1, x, y) <= eps2 || distance_sq(l.x2, l.y2, x, y) <= eps2
+        };
+        // The two selected lines joined at `p`, when nothing else touches it
+        let joint = |p: (f32, f32)| -> Option<(usize, usize)> {
+            let near: Vec<usize> = self
+                .line_grid
+                .query_rect(p.0 - SNAP_EPS, p.1 - SNAP_EPS, p.0 + SNAP_EPS, p.1 + SNAP_EPS)
+                .into_iter()
+                .map(|i| i as usize)
+                .filter(|&i| {
+                    let l = &self.lines[i];
+                    point_segment_nearest(p.0, p.1, l.x1, l.y1, l.x2, l.y2).3 <= eps2
+                })
+                .collect();
+            match near[..] {
+                [a, b] if selected[a] && selected[b] && ends_at(&self.lines[a], p) && ends_at(&self.lines[b], p) => Some((a, b)),
+                _ => None,
+            }
+        };
+        // Far end of `line` from `p`
+        let other_end = |l: &Line, (x, y): (f32, f32)| {
+            if distance_sq(l.x1, l.y1, x, y) <= eps2 { (l.x2, l.y2) } else { (l.x1, l.y1) }
+        };
+
+        // Walk each run both ways from its first unvisited line
+        let mut visited: Vec<bool> = alloc::vec![false; self.lines.len()];
+        let mut chains: Vec<Vec<usize>> = Vec::new();
+        let mut chain_points: Vec<Vec<(f32, f32)>> = Vec::new();
+        for start in 0..self.lines.len() {
+            if !selected[start] || visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let l = self.lines[start];
+            let mut forward: (Vec<usize>, Vec<(f32, f32)>) = (alloc::vec![start], alloc::vec![(l.x1, l.y1), (l.x2, l.y2)]);
+            let mut backward: (Vec<usize>, Vec<(f32, f32)>) = (Vec::new(), Vec::new());
+            let mut closed = false;
+            for (run, (mut at, mut from)) in [(&mut forward, ((l.x2, l.y2), start)), (&mut backward, ((l.x1, l.y1), start))] {
+                while !closed {
+                    let Some((a, b)) = joint(at) else {
+                        break;
+                    };
+                    let next = if a == from { b } else { a };
+                    if next == start {
+                        closed = true;
+                    }
+                    if visited[next] {
+                        break;
+                    }
+                    visited[next] = true;
+                    at = other_end(&self.lines[next], at);
+                    from = next;
+                    run.0.push(next);
+                    run.1.push(at);
+                }
+            }
+            let (mut lines, mut points) = backward;
+            lines.reverse();
+            points.reverse();
+            lines.extend(forward.0);
+            points.extend(forward.1);
+            if lines.len() >= 2 {
+                chains.push(lines);
+                chain_points.push(points);
+            }
+        }
+
+        // New lines per chain, placed where the chain's first line was
+        let mut removed: Vec<bool> = alloc::vec![false; self.lines.len()];
+        let mut inserted: Vec<(usize, Vec<Line>)> = Vec::new();
+        let mut bounds: Vec<(f32, f32, f32, f32)> = Vec::new();
+        for (chain, points) in chains.into_iter().zip(chain_points) {
+            let simplified = simplify_points(&points, tolerance, method);
+            if simplified.len() > chain.len() {
+                continue;
+            }
+            let stroke = self.lines[chain[0]].stroke;
+            let stroke = if chain.iter().all(|&i| self.lines[i].stroke == stroke) { stroke } else { 0 };
+            let mut lines: Vec<Line> = Vec::with_capacity(simplified.len() - 1);
+            for pair in simplified.windows(2) {
+                let mut line = Line::new(pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+                line.id = self.take_line_id();
+                line.stroke = stroke;
+                lines.push(line);
+            }
+            for &i in chain.iter() {
+                removed[i] = true;
+            }
+            bounds.push(poly_bounds(&points));
+            inserted.push((chain.iter().copied().min().unwrap_or(0), lines));
+        }
+        if inserted.is_empty() {
+            return 0;
+        }
+
+        // Fills with an outline point on a replaced line
+        let mut touched_fills: Vec<usize> = Vec::new();
+        for &(minx, miny, maxx, maxy) in bounds.iter() {
+            touched_fills.extend(self.fill_grid.query_rect(minx, miny, maxx, maxy).into_iter().map(|i| i as usize));
+        }
+        touched_fills.sort_unstable();
+        touched_fills.dedup();
+        touched_fills.retain(|&idx| {
+            self.fills[idx].points.iter().any(|&(x, y)| {
+                (0..self.lines.len()).any(|i| {
+                    let l = &self.lines[i];
+                    removed[i] && point_segment_nearest(x, y, l.x1, l.y1, l.x2, l.y2).3 <= eps2
+                })
+            })
+        });
+
+        let added: usize = inserted.iter().map(|(_, l)| l.len()).sum();
+        let dropped = removed.iter().filter(|&&r| r).count();
+        inserted.sort_unstable_by_key(|(at, _)| *at);
+        let previous_lines = core::mem::take(&mut self.lines);
+        let mut lines: Vec<Line> = Vec::with_capacity(previous_lines.len() + added - dropped);
+        let mut pending = inserted.into_iter().peekable();
+        for (i, line) in previous_lines.iter().enumerate() {
+            if let Some((_, new_lines)) = pending.next_if(|(at, _)| *at == i) {
+                self.selected_lines.extend(new_lines.iter().map(|l| l.id));
+                lines.extend(new_lines);
+            }
+            if !removed[i] {
+                lines.push(*line);
+            }
+        }
+        self.selected_lines.retain(|&id| !previous_lines.iter().zip(removed.iter()).any(|(l, &r)| r && l.id == id));
+        self.lines = lines;
+        self.build_fill_graph();
+
+        let previous_fills = self.retrace_fills(touched_fills);
+        let dead_curves = self.take_dead_curves();
+        self.history.push(Command::Simplify(previous_lines, previous_fills, dead_curves));
+        self.refresh_export();
+        self.refresh_export_fills();
+        self.refresh_selection_buf();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        (dropped - added) as u32
+    }
+
+    fn undo(&mut self) {
+        match self.history.pop() {
+            Some(Command::Add) => {
+                self.lines.pop();
+            }
+            Some(Command::AddWelded(split_lines)) => {
+                self.lines.pop();
+                self.restore_split_lines(split_lines);
+            }
+            Some(Command::AddFill) => {
+                self.fills.pop();
+            }
+            Some(Command::AddPolyline(segments, split_lines)) => {
+                self.lines.truncate(self.lines.len().saturating_sub(segments));
+                self.restore_split_lines(split_lines);
+            }
+            Some(Command::AddCurve(segments, split_lines)) => {
+                self.lines.truncate(self.lines.len().saturating_sub(segments));
+                self.curves.pop();
+                self.restore_split_lines(split_lines);
+            }
+            Some(Command::AddFrame) => {
+                // Remove last 4 lines (frame is always 4 lines added together)
+                for _ in 0..4 {
+                    self.lines.pop();
+                }
+            }
+            Some(Command::Clear(previous_lines, previous_fills, previous_curves)) => {
+                self.lines = previous_lines;
+                self.fills = previous_fills;
+                self.curves = previous_curves;
+            }
+            Some(Command::CleanOverhangs(previous_lines, dead_curves))
+            | Some(Command::TrimFills(previous_lines, dead_curves)) => {
+                self.lines = previous_lines;
+                self.restore_curves(dead_curves);
+            }
+            Some(Command::Erase(previous_lines, previous_fills, dead_curves))
+            | Some(Command::Simplify(previous_lines, previous_fills, dead_curves)) => {
+                self.lines = previous_lines;
+                self.restore_curves(dead_curves);
+                for (idx, fill) in previous_fills {
+                    if let Some(slot) = self.fills.get_mut(idx) {
+                        *slot = fill;
+                    }
+                }
+            }
+            Some(Command::Transform(previous_lines, previous_fills, previous_curves)) => {
+                for (idx, line) in previous_lines {
+                    if let Some(slot) = self.lines.get_mut(idx) {
+                        *slot = line;
+                    }
+                }
+                for (idx, fill) in previous_fills {
+                    if let Some(slot) = self.fills.get_mut(idx) {
+                        *slot = fill;
+                    }
+                }
+                for (idx, curve) in previous_curves {
+                    if let Some(slot) = self.curves.get_mut(idx) {
+                        *slot = curve;
+                    }
+                }
+            }
+            Some(Command::Paste(line_count, fill_count, curve_count)) => {
+                self.lines.truncate(self.lines.len().saturating_sub(line_count));
+                self.fills.truncate(self.fills.len().saturating_sub(fill_count));
+                self.curves.truncate(self.curves.len().saturating_sub(curve_count));
+            }
+            Some(Command::ExtendLine(idx, original)) => {
+                if let Some(line) = self.lines.get_mut(idx) {
+                    *line = original;
+                }
+            }
+            Some(Command::TrimAt(idx, original, pieces, dead_curves)) => {
+                let end = (idx + pieces).min(self.lines.len());
+                self.lines.splice(idx..end, core::iter::once(original));
+                self.restore_curves(dead_curves);
+            }
+            Some(Command::EditFill(idx, previous)) => {
+                if let Some(fill) = self.fills.get_mut(idx) {
+                    *fill = previous;
+                }
+            }
+            Some(Command::DeleteFill(idx, polygon)) => {
+                self.fills.insert(idx.min(self.fills.len()), polygon);
+            }
+            Some(Command::SwapFills(a, b)) if a < self.fills.len() && b < self.fills.len() => {
+                self.fills.swap(a, b);
+            }
+            Some(Command::SwapFills(..)) => {}
+            Some(Command::AddSwatch) => {
+                if let Some(swatch) = self.palette.swatches.pop() {
+                    if self.fill_swatch == Some(swatch.id) {
+                        self.fill_swatch = None;
+                    }
+                }
+            }
+            Some(Command::SetSwatchColor(id, previous)) => {
+                self.palette.set_color(id, previous);
+            }
+            Some(Command::RemoveSwatch(pos, swatch)) => {
+                let pos = pos.min(self.palette.swatches.len());
+                self.palette.swatches.insert(pos, swatch);
+            }
+            None => {}
+        }
+        self.refresh_export();
+        self.refresh_export_fills();
+        self.recompute_intersections();
+        self.build_fill_graph();
+        
+        // Verify state after undo in debug builds
+        check_editor_integrity(self);
+    }
+
+    fn line_count(&self) -> u32 {
+        self.lines.len() as u32
+    }
+
+    fn export_ptr(&self) -> *const f32 {
+        self.export_buf.as_ptr()
+    }
+
+    fn export_len(&self) -> u32 {
+        self.export_buf.len() as u32
+    }
+
+    fn debug_ptr(&self) -> *const f32 {
+        self.debug_buf.as_ptr()
+    }
+
+    fn debug_len(&self) -> u32 {
+        self.debug_buf.len() as u32
+    }
+
+    fn intersections_ptr(&self) -> *const f32 {
+        self.intersections_export.as_ptr()
+    }
+
+    fn intersections_len(&self) -> u32 {
+        self.intersections_export.len() as u32
+    }
+
+    fn fill_trace_ptr(&self) -> *const f32 {
+        self.fill_trace_buf.as_ptr()
+    }
+
+    fn fill_trace_len(&self) -> u32 {
+        self.fill_trace_buf.len() as u32
+    }
+
+    fn fill_candidates_ptr(&self) -> *const f32 {
+        self.fill_candidates_buf.as_ptr()
+    }
+
+    fn fill_candidates_len(&self) -> u32 {
+        self.fill_candidates_buf.len() as u32
+    }
+
+    fn adjacency_debug_ptr(&self) -> *const f32 {
+        self.adjacency_debug_buf.as_ptr()
+    }
+
+    fn adjacency_debug_len(&self) -> u32 {
+        self.adjacency_debug_buf.len() as u32
+    }
+
+    fn node_outgoing_ptr(&self) -> *const f32 {
+        self.node_outgoing_buf.as_ptr()
+    }
+
+    fn node_outgoing_len(&self) -> u32 {
+        self.node_outgoing_buf.len() as u32
+    }
+
+    fn node_audit_ptr(&self) -> *const f32 {
+        self.node_audit_buf.as_ptr()
+    }
+
+    fn node_audit_len(&self) -> u32 {
+        self.node_audit_buf.len() as u32
+    }
+
+    fn graph_debug_ptr(&self) -> *const f32 {
+        self.graph_debug_buf.as_ptr()
+    }
+
+    fn graph_debug_len(&self) -> u32 {
+        self.graph_debug_buf.len() as u32
+    }
+
+    fn fill_walk_debug_ptr(&self) -> *const f32 {
+        self.fill_walk_debug_buf.as_ptr()
+    }
+
+    fn fill_walk_debug_len(&self) -> u32 {
+        self.fill_walk_debug_buf.len() as u32
+    }
+
+    fn fill_result_ptr(&self) -> *const f32 {
+        self.fill_result_buf.as_ptr()
+    }
+
+    fn fill_result_len(&self) -> u32 {
+        self.fill_result_buf.len() as u32
+    }
+
+    fn overhang_preview_ptr(&self) -> *const f32 {
+        self.overhang_preview_buf.as_ptr()
+    }
+
+    fn overhang_preview_len(&self) -> u32 {
+        self.overhang_preview_buf.len() as u32
+    }
+
+    fn swatches_ptr(&self) -> *const f32 {
+        self.swatches_export_buf.as_ptr()
+    }
+
+    fn swatches_len(&self) -> u32 {
+        self.swatches_export_buf.len() as u32
+    }
+
+    fn preview_ptr(&self) -> *const f32 {
+        self.preview_buf.as_ptr()
+    }
+
+    fn preview_len(&self) -> u32 {
+        self.preview_buf.len() as u32
+    }
+
+    fn leak_ptr(&self) -> *const f32 {
+        self.leak_buf.as_ptr()
+    }
+
+    fn leak_len(&self) -> u32 {
+        self.leak_buf.len() as u32
+    }
+}
+
+// LOCK POLICY:
+// This global is accessed only from single-threaded WASM.
+// JavaScript calls all editor_* functions from the main thread only.
+// SAFETY: UnsafeCell is wrapped in Sync ONLY because we guarantee single-threaded access.
+// If Web Workers or WASM threads are used, this MUST be changed to Mutex<Option<Editor>>.
+struct EditorCell {
+    inner: UnsafeCell<Option<Editor>>,
+}
+
+unsafe impl Sync for EditorCell {}  // Only safe for single-threaded WASM
+
+static EDITOR: EditorCell = EditorCell {
+    inner: UnsafeCell::new(None),
+};
+
+fn editor_mut() -> Option<&'static mut Editor> {
+    unsafe { (&mut *EDITOR.inner.get()).as_mut() }
+}
+
+fn editor_ref() -> Option<&'static Editor> {
+    unsafe { (&*EDITOR.inner.get()).as_ref() }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_init() {
+    unsafe {
+        *EDITOR.inner.get() = Some(Editor::new());
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_add_line(x1: f32, y1: f32, x2: f32, y2: f32) {
+    if let Some(editor) = editor_mut() {
+        editor.add_line(Line::new(x1, y1, x2, y2));
+    }
+}
+
+// `points` holds `count` (x, y) pairs, added as one polyline stroke (one undo
+// step). Returns the stroke id, 0 if nothing was added.
+// Safety: a non-null `points` must address `count` readable (x, y) f32 pairs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_add_polyline(points: *const f32, count: usize) -> u32 {
+    if points.is_null() || count < 2 {
+        return 0;
+    }
+    let raw = unsafe { core::slice::from_raw_parts(points, count * 2) };
+    let chain: Vec<(f32, f32)> = raw.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    editor_mut().map(|e| e.add_polyline(&chain)).unwrap_or(0)
+}
+
+// Simplification applied to editor_add_polyline input: method 0 = Ramer-Douglas-Peucker,
+// 1 = Visvalingam; tolerance 0 keeps every point. Returns 0 for an unknown method.
+#[no_mangle]
+pub extern "C" fn editor_set_stroke_simplify(method: u32, tolerance: f32) -> u32 {
+    match (editor_mut(), SimplifyMethod::from_code(method)) {
+        (Some(editor), Some(method)) if tolerance >= 0.0 && tolerance.is_finite() => {
+            editor.stroke_simplify = method;
+            editor.stroke_tolerance = tolerance;
+            1
+        }
+        _ => 0,
+    }
+}
+
+// Stroke ids: one u32 per line, same order as the export buffer (0 = plain line)
+#[no_mangle]
+pub extern "C" fn editor_line_strokes_ptr() -> *const u32 {
+    editor_ref().map(|e| e.line_strokes_buf.as_ptr()).unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_line_strokes_len() -> u32 {
+    editor_ref().map(|e| e.line_strokes_buf.len() as u32).unwrap_or(0)
+}
+
+// Strokes buffer layout: [count, (stroke_id, point_count, (x, y) * point_count) * count],
+// one path per unbroken run of a polyline's segments
+#[no_mangle]
+pub extern "C" fn editor_strokes_ptr_f32() -> *const f32 {
+    editor_ref().map(|e| e.strokes_buf.as_ptr()).unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_strokes_len_f32() -> u32 {
+    editor_ref().map(|e| e.strokes_buf.len() as u32).unwrap_or(0)
+}
+
+// `points` holds `count` (x, y) pairs smoothed into one stroke: method 0 =
+// Chaikin, 1 = Catmull-Rom, 2 = cubic Bézier fit, with `tolerance` the max
+// deviation from the input. Returns the stroke id, 0 if nothing was added.
+// Safety: a non-null `points` must address `count` readable (x, y) f32 pairs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_add_curve(points: *const f32, count: usize, method: u32, tolerance: f32) -> u32 {
+    if points.is_null() || count < 2 {
+        return 0;
+    }
+    let Some(method) = CurveMethod::from_code(method) else {
+        return 0;
+    };
+    let raw = unsafe { core::slice::from_raw_parts(points, count * 2) };
+    let chain: Vec<(f32, f32)> = raw.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    editor_mut().map(|e| e.add_curve(&chain, method, tolerance)).unwrap_or(0)
+}
+
+// Curves buffer layout: [count, (stroke_id, segment_count, x0, y0,
+// (c1x, c1y, c2x, c2y, x, y) * segment_count) * count], the cubic form of
+// strokes_buf paths that are still an unchanged curve
+#[no_mangle]
+pub extern "C" fn editor_curves_ptr_f32() -> *const f32 {
+    editor_ref().map(|e| e.curves_buf.as_ptr()).unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_curves_len_f32() -> u32 {
+    editor_ref().map(|e| e.curves_buf.len() as u32).unwrap_or(0)
+}
+
+// Auto-weld reach for new lines (0 disables); returns 0 (unchanged) for negative or non-finite values
+#[no_mangle]
+pub extern "C" fn editor_set_weld_radius(radius: f32) -> u32 {
+    match editor_mut() {
+        Some(editor) if radius >= 0.0 && radius.is_finite() => {
+            editor.weld_radius = radius;
+            1
+        }
+        _ => 0,
+    }
+}
+
+// Weld buffer layout: [count, (end, kind, from_x, from_y, to_x, to_y) * count]
+// for the last editor_add_line; end 0 = start, 1 = end; kind 1 = onto an
+// endpoint, 2 = onto a segment (split there)
+#[no_mangle]
+pub extern "C" fn editor_welds_ptr_f32() -> *const f32 {
+    editor_ref().map(|e| e.weld_buf.as_ptr()).unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_welds_len_f32() -> u32 {
+    editor_ref().map(|e| e.weld_buf.len() as u32).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_add_frame(x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32, x4: f32, y4: f32) {
+    if let Some(editor) = editor_mut() {
+        editor.add_frame(x1, y1, x2, y2, x3, y3, x4, y4);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_undo() {
+    if let Some(editor) = editor_mut() {
+        editor.undo();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_clear() {
+    if let Some(editor) = editor_mut() {
+        editor.clear();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_cleanup_overhangs() {
+    if let Some(editor) = editor_mut() {
+        editor.cleanup_overhangs();
+    }
+}
+
+// Preview of editor_cleanup_overhangs: returns how many segments it would remove
+#[no_mangle]
+pub extern "C" fn editor_preview_overhangs() -> u32 {
+    editor_mut().map(|e| e.preview_overhangs()).unwrap_or(0)
+}
+
+// Format: [count, (x1, y1, x2, y2) * count]
+#[no_mangle]
+pub extern "C" fn editor_overhang_preview_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.overhang_preview_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_overhang_preview_len_f32() -> u32 {
+    editor_ref().map(|e| e.overhang_preview_len()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_line_count() -> u32 {
+    editor_ref().map(|e| e.line_count()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_export_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.export_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_export_len_f32() -> u32 {
+    editor_ref().map(|e| e.export_len()).unwrap_or(0)
+}
+
+// Line ids: one u32 per line, same order as the export buffer (editor_line_count entries)
+#[no_mangle]
+pub extern "C" fn editor_line_ids_ptr() -> *const u32 {
+    editor_ref()
+        .map(|e| e.line_ids_buf.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_line_ids_len() -> u32 {
+    editor_ref().map(|e| e.line_ids_buf.len() as u32).unwrap_or(0)
+}
+
+// Current index of the line with this id, -1 when it no longer exists
+#[no_mangle]
+pub extern "C" fn editor_line_index(id: u32) -> i32 {
+    editor_ref()
+        .and_then(|e| e.line_index(id))
+        .map(|idx| idx as i32)
+        .unwrap_or(-1)
+}
+
+// Fill ids: one u32 per fill record, same order as the fills export buffer
+#[no_mangle]
+pub extern "C" fn editor_fill_ids_ptr() -> *const u32 {
+    if let Some(editor) = editor_mut() {
+        editor.refresh_fills_export_buf();
+        return editor.fill_ids_buf.as_ptr();
+    }
+    ptr::null()
+}
+
+#[no_mangle]
+pub extern "C" fn editor_fill_ids_len() -> u32 {
+    editor_ref().map(|e| e.fill_ids_buf.len() as u32).unwrap_or(0)
+}
+
+// Current index of the fill with this id, -1 when it no longer exists
+#[no_mangle]
+pub extern "C" fn editor_fill_index(id: u32) -> i32 {
+    editor_ref()
+        .and_then(|e| e.fill_index(id))
+        .map(|idx| idx as i32)
+        .unwrap_or(-1)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_export_fills_ptr() -> *const f32 {
+    if let Some(editor) = editor_mut() {
+        editor.refresh_fills_export_buf();
+        return editor.fills_export_buf.as_ptr();
+    }
+    ptr::null()
+}
+
+#[no_mangle]
+pub extern "C" fn editor_export_fills_len() -> u32 {
+    if let Some(editor) = editor_mut() {
+        editor.refresh_fills_export_buf();
+        return editor.fills_export_buf.len() as u32;
+    }
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn editor_set_debug(enabled: u32) {
+    if let Some(editor) = editor_mut() {
+        editor.debug_enabled = enabled != 0;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_nearest(px: f32, py: f32) {
+    if let Some(editor) = editor_mut() {
+        if editor.debug_enabled {
+            editor.compute_nearest_debug(px, py);
+        } else {
+            editor.debug_buf.clear();
+            editor.debug_buf.push(0.0);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_debug_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.debug_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_debug_len_f32() -> u32 {
+    editor_ref().map(|e| e.debug_len()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_intersections_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.intersections_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_intersections_len_f32() -> u32 {
+    editor_ref().map(|e| e.intersections_len()).unwrap_or(0)
+}
+
+// Returns a fill status code: 0 = filled, 1 = no geometry, 2 = no boundary near
+// the click, 3 = dead end, 4 = step limit, 5 = premature cycle, 6 = no closure
+#[no_mangle]
+pub extern "C" fn editor_fill(x: f32, y: f32, color_ptr: *const u8, color_len: usize) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
+        // An unparsable color falls back to the current fill color (see editor_color_error)
+        let color = editor.read_color(color_slice).unwrap_or(editor.fill_color);
+        return editor.fill_at(x, y, color, None).status.code();
+    }
+    FillStatus::NoGeometry.code()
+}
+
+// Same fill pipeline as editor_fill, using the color or swatch set by
+// editor_set_fill_color / editor_set_fill_swatch
+#[no_mangle]
+pub extern "C" fn editor_fill_debug_at(ox: f32, oy: f32) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let (color, swatch) = editor.current_fill();
+        return editor.fill_at(ox, oy, color, swatch).status.code();
+    }
+    FillStatus::NoGeometry.code()
+}
+
+// Hover preview of the region a fill click would create; nothing is committed.
+// Returns the same status codes as editor_fill.
+#[no_mangle]
+pub extern "C" fn editor_fill_preview(x: f32, y: f32) -> u32 {
+    if let Some(editor) = editor_mut() {
+        return editor.fill_preview(x, y).code();
+    }
+    FillStatus::NoGeometry.code()
+}
+
+// Format: [status, count, (x, y) * count]
+#[no_mangle]
+pub extern "C" fn editor_fill_preview_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.preview_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_fill_preview_len_f32() -> u32 {
+    editor_ref().map(|e| e.preview_len()).unwrap_or(0)
+}
+
+// 0 = cut-segment graph (default), 1 = legacy connected-lines tracer
+#[no_mangle]
+pub extern "C" fn editor_set_fill_strategy(strategy: u32) {
+    if let Some(editor) = editor_mut() {
+        editor.fill_strategy = FillStrategy::from_code(strategy);
+    }
+}
+
+// Linear gradient from (x1, y1) to (x2, y2). Stops: "0 #ff0000; 1 rgba(0,0,255,0.5)".
+// Returns 1 on success; stop parse errors are reported through editor_color_error.
+// Safety: `stops_ptr` must address `stops_len` readable bytes
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_set_fill_linear(idx: u32, x1: f32, y1: f32, x2: f32, y2: f32, stops_ptr: *const u8, stops_len: usize) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let stops_slice = unsafe { core::slice::from_raw_parts(stops_ptr, stops_len) };
+        match parse_stops(stops_slice) {
+            Ok(stops) => {
+                editor.color_error = None;
+                let paint = Paint::Linear { start: (x1, y1), end: (x2, y2), stops };
+                return editor.set_fill_paint(idx as usize, paint) as u32;
+            }
+            Err(err) => editor.color_error = Some(err),
+        }
+    }
+    0
+}
+
+// Radial gradient around (cx, cy); same stop syntax as editor_set_fill_linear
+// Safety: `stops_ptr` must address `stops_len` readable bytes
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_set_fill_radial(idx: u32, cx: f32, cy: f32, radius: f32, stops_ptr: *const u8, stops_len: usize) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let stops_slice = unsafe { core::slice::from_raw_parts(stops_ptr, stops_len) };
+        match parse_stops(stops_slice) {
+            Ok(stops) => {
+                editor.color_error = None;
+                let paint = Paint::Radial { center: (cx, cy), radius, stops };
+                return editor.set_fill_paint(idx as usize, paint) as u32;
+            }
+            Err(err) => editor.color_error = Some(err),
+        }
+    }
+    0
+}
+
+// Scissors: remove the piece of line under (x, y) bounded by its nearest
+// intersections or endpoints. Returns 1 when something was cut.
+#[no_mangle]
+pub extern "C" fn editor_trim_at(x: f32, y: f32) -> u32 {
+    editor_mut().map(|e| e.trim_at(x, y) as u32).unwrap_or(0)
+}
+
+// Extend the free line end nearest (x, y), one no other line end touches, to the
+// first line it would hit within max_dist (<= 0 uses EXTEND_MAX_DIST). Returns 1
+// when the line was extended.
+#[no_mangle]
+pub extern "C" fn editor_extend_at(x: f32, y: f32, max_dist: f32) -> u32 {
+    let max_dist = if max_dist > 0.0 { max_dist } else { EXTEND_MAX_DIST };
+    editor_mut().map(|e| e.extend_at(x, y, max_dist) as u32).unwrap_or(0)
+}
+
+// Erase along a brush path: `points` holds `count` (x, y) pairs. Every line
+// under the brush is cut at its edge and the covered spans deleted, as one undo
+// step. Returns the number of lines touched.
+// Safety: `points` must address `count` readable (x, y) f32 pairs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_erase(points: *const f32, count: usize, radius: f32) -> u32 {
+    if points.is_null() || count == 0 {
+        return 0;
+    }
+    let raw = unsafe { core::slice::from_raw_parts(points, count * 2) };
+    let path: Vec<(f32, f32)> = raw.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    editor_mut().map(|e| e.erase(&path, radius)).unwrap_or(0)
+}
+
+// Stale fills buffer layout: [count, fill_idx * count]
+// Fills whose outline no longer closes after an erase
+#[no_mangle]
+pub extern "C" fn editor_stale_fills_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.stale_fills_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_stale_fills_len_f32() -> u32 {
+    editor_ref().map(|e| e.stale_fills_len()).unwrap_or(0)
+}
+
+// Selection. `add` != 0 extends the current selection instead of replacing it.
+// Each returns the number of lines + fills selected afterwards.
+#[no_mangle]
+pub extern "C" fn editor_select_clear() {
+    if let Some(editor) = editor_mut() {
+        editor.select(Vec::new(), Vec::new(), false);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn editor_select_line(id: u32, add: u32) -> u32 {
+    editor_mut()
+        .map(|e| {
+            let ids = if e.line_index(id).is_some() { alloc::vec![id] } else { Vec::new() };
+            e.select(ids, Vec::new(), add != 0)
+        })
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_select_fill(id: u32, add: u32) -> u32 {
+    editor_mut()
+        .map(|e| {
+            let ids = if e.fill_index(id).is_some() { alloc::vec![id] } else { Vec::new() };
+            e.select(Vec::new(), ids, add != 0)
+        })
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_select_rect(x1: f32, y1: f32, x2: f32, y2: f32, add: u32) -> u32 {
+    editor_mut().map(|e| e.select_rect(x1, y1, x2, y2, add != 0)).unwrap_or(0)
+}
+
+// Lasso: `points` holds `count` (x, y) pairs of a closed polygon
+// Safety: `points` must address `count` readable (x, y) f32 pairs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_select_lasso(points: *const f32, count: usize, add: u32) -> u32 {
+    let lasso: Vec<(f32, f32)> = if points.is_null() || count == 0 {
+        Vec::new()
+    } else {
+        let raw = unsafe { core::slice::from_raw_parts(points, count * 2) };
+        raw.chunks_exact(2).map(|p| (p[0], p[1])).collect()
+    };
+    editor_mut().map(|e| e.select_lasso(&lasso, add != 0)).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn editor_select_at(x: f32, y: f32, add: u32) -> u32 {
+    editor_mut().map(|e| e.select_at(x, y, add != 0)).unwrap_or(0)
+}
+
+// Selection buffer layout (u32): [line_count, line_id * line_count, fill_count, fill_id * fill_count]
+#[no_mangle]
+pub extern "C" fn editor_selection_ptr() -> *const u32 {
+    if let Some(editor) = editor_mut() {
+        editor.refresh_selection_buf();
+        return editor.selection_buf.as_ptr();
     }
+    ptr::null()
+}
 
-    fn export_len(&self) -> u32 {
-        self.export_buf.len() as u32
-    }
+#[no_mangle]
+pub extern "C" fn editor_selection_len() -> u32 {
+    editor_ref().map(|e| e.selection_buf.len() as u32).unwrap_or(0)
+}
 
-    fn debug_ptr(&self) -> *const f32 {
-        self.debug_buf.as_ptr()
-    }
+// Transforms of the selection (and fills bounded by selected lines), one undo
+// step each. Return the number of lines + fills transformed.
+#[no_mangle]
+pub extern "C" fn editor_translate_selection(dx: f32, dy: f32) -> u32 {
+    editor_mut().map(|e| e.transform_selection(Affine::translate(dx, dy), 0.0)).unwrap_or(0)
+}
 
-    fn debug_len(&self) -> u32 {
-        self.debug_buf.len() as u32
-    }
+// Angle in degrees, clockwise on screen
+#[no_mangle]
+pub extern "C" fn editor_rotate_selection(angle: f32, px: f32, py: f32) -> u32 {
+    editor_mut()
+        .map(|e| e.transform_selection(Affine::rotate(angle, (px, py)), angle))
+        .unwrap_or(0)
+}
 
-    fn intersections_ptr(&self) -> *const f32 {
-        self.intersections_export.as_ptr()
+#[no_mangle]
+pub extern "C" fn editor_scale_selection(sx: f32, sy: f32, px: f32, py: f32) -> u32 {
+    if sx == 0.0 || sy == 0.0 {
+        return 0; // Would collapse the geometry
     }
+    editor_mut()
+        .map(|e| e.transform_selection(Affine::scale(sx, sy, (px, py)), 0.0))
+        .unwrap_or(0)
+}
 
-    fn intersections_len(&self) -> u32 {
-        self.intersections_export.len() as u32
-    }
+// horizontal != 0 mirrors left-right across x = px, otherwise top-bottom across y = py
+#[no_mangle]
+pub extern "C" fn editor_flip_selection(horizontal: u32, px: f32, py: f32) -> u32 {
+    editor_mut()
+        .map(|e| e.transform_selection(Affine::flip(horizontal != 0, (px, py)), 0.0))
+        .unwrap_or(0)
+}
 
-    fn fill_trace_ptr(&self) -> *const f32 {
-        self.fill_trace_buf.as_ptr()
+// Simplify runs of selected lines within `tolerance` (method codes as in
+// editor_set_stroke_simplify). Returns the number of lines removed.
+#[no_mangle]
+pub extern "C" fn editor_simplify_selection(tolerance: f32, method: u32) -> u32 {
+    match (editor_mut(), SimplifyMethod::from_code(method)) {
+        (Some(editor), Some(method)) => editor.simplify_selection(tolerance, method),
+        _ => 0,
     }
+}
 
-    fn fill_trace_len(&self) -> u32 {
-        self.fill_trace_buf.len() as u32
-    }
+// Region queries: lines and fills entirely inside the region, or with
+// partial != 0 those crossing or touching it. Results go to the query buffer
+// (editor_query_ptr/len); each returns the number of matches.
+#[no_mangle]
+pub extern "C" fn editor_query_rect(x1: f32, y1: f32, x2: f32, y2: f32, partial: u32) -> u32 {
+    editor_mut()
+        .map(|e| e.query_into_buf(&rect_polygon(x1, y1, x2, y2), partial != 0))
+        .unwrap_or(0)
+}
 
-    fn fill_candidates_ptr(&self) -> *const f32 {
-        self.fill_candidates_buf.as_ptr()
-    }
+// `points` holds `count` (x, y) pairs of a closed polygon
+// Safety: `points` must address `count` readable (x, y) f32 pairs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_query_polygon(points: *const f32, count: usize, partial: u32) -> u32 {
+    let region: Vec<(f32, f32)> = if points.is_null() || count == 0 {
+        Vec::new()
+    } else {
+        let raw = unsafe { core::slice::from_raw_parts(points, count * 2) };
+        raw.chunks_exact(2).map(|p| (p[0], p[1])).collect()
+    };
+    editor_mut().map(|e| e.query_into_buf(&region, partial != 0)).unwrap_or(0)
+}
 
-    fn fill_candidates_len(&self) -> u32 {
-        self.fill_candidates_buf.len() as u32
-    }
+// Query buffer layout (u32): [line_count, line_id * line_count, fill_count, fill_id * fill_count]
+#[no_mangle]
+pub extern "C" fn editor_query_ptr() -> *const u32 {
+    editor_ref().map(|e| e.query_buf.as_ptr()).unwrap_or(ptr::null())
+}
 
-    fn adjacency_debug_ptr(&self) -> *const f32 {
-        self.adjacency_debug_buf.as_ptr()
-    }
+#[no_mangle]
+pub extern "C" fn editor_query_len() -> u32 {
+    editor_ref().map(|e| e.query_buf.len() as u32).unwrap_or(0)
+}
 
-    fn adjacency_debug_len(&self) -> u32 {
-        self.adjacency_debug_buf.len() as u32
-    }
+// Spatial queries over the line grid. Nearest returns the line id (0 = none);
+// k-nearest and radius write the spatial buffer and return the number found.
+#[no_mangle]
+pub extern "C" fn editor_nearest_line(x: f32, y: f32, max_dist: f32) -> u32 {
+    editor_ref()
+        .and_then(|e| e.nearest_line_by(x, y, max_dist, line_segment_dist_sq).map(|(idx, _)| e.lines[idx].id))
+        .unwrap_or(0)
+}
 
-    fn node_outgoing_ptr(&self) -> *const f32 {
-        self.node_outgoing_buf.as_ptr()
-    }
+#[no_mangle]
+pub extern "C" fn editor_k_nearest_lines(x: f32, y: f32, k: u32, max_dist: f32) -> u32 {
+    editor_mut().map(|e| e.k_nearest_lines(x, y, k as usize, max_dist)).unwrap_or(0)
+}
 
-    fn node_outgoing_len(&self) -> u32 {
-        self.node_outgoing_buf.len() as u32
-    }
+#[no_mangle]
+pub extern "C" fn editor_lines_in_radius(x: f32, y: f32, radius: f32) -> u32 {
+    editor_mut().map(|e| e.lines_in_radius(x, y, radius)).unwrap_or(0)
+}
 
-    fn node_audit_ptr(&self) -> *const f32 {
-        self.node_audit_buf.as_ptr()
-    }
+// Spatial buffer layout (u32): [count, line_id * count], closest first
+#[no_mangle]
+pub extern "C" fn editor_spatial_ptr() -> *const u32 {
+    editor_ref().map(|e| e.spatial_buf.as_ptr()).unwrap_or(ptr::null())
+}
 
-    fn node_audit_len(&self) -> u32 {
-        self.node_audit_buf.len() as u32
-    }
+#[no_mangle]
+pub extern "C" fn editor_spatial_len() -> u32 {
+    editor_ref().map(|e| e.spatial_buf.len() as u32).unwrap_or(0)
+}
 
-    fn graph_debug_ptr(&self) -> *const f32 {
-        self.graph_debug_buf.as_ptr()
-    }
+// Snap (x, y) for drawing. Flags: 1 endpoints, 2 intersections, 4 midpoints,
+// 8 grid, 16 angle steps from the anchor (pass NaN when there is none). Returns
+// the snap kind (one of the flags, 0 = none); the point is in the snap buffer.
+#[no_mangle]
+pub extern "C" fn editor_snap(x: f32, y: f32, anchor_x: f32, anchor_y: f32, flags: u32) -> u32 {
+    editor_mut().map(|e| e.snap(x, y, anchor_x, anchor_y, flags)).unwrap_or(0)
+}
 
-    fn graph_debug_len(&self) -> u32 {
-        self.graph_debug_buf.len() as u32
-    }
+// Snap buffer layout: [kind, x, y]
+#[no_mangle]
+pub extern "C" fn editor_snap_ptr_f32() -> *const f32 {
+    editor_ref().map(|e| e.snap_buf.as_ptr()).unwrap_or(ptr::null())
+}
 
-    fn fill_walk_debug_ptr(&self) -> *const f32 {
-        self.fill_walk_debug_buf.as_ptr()
-    }
+#[no_mangle]
+pub extern "C" fn editor_snap_len_f32() -> u32 {
+    editor_ref().map(|e| e.snap_buf.len() as u32).unwrap_or(0)
+}
 
-    fn fill_walk_debug_len(&self) -> u32 {
-        self.fill_walk_debug_buf.len() as u32
+// Grid spacing for grid snapping; returns 0 (unchanged) unless positive and finite
+#[no_mangle]
+pub extern "C" fn editor_set_snap_grid(size: f32) -> u32 {
+    match editor_mut() {
+        Some(editor) if size > 0.0 && size.is_finite() => {
+            editor.snap_grid = size;
+            1
+        }
+        _ => 0,
     }
 }
 
-// LOCK POLICY:
-// This global is accessed only from single-threaded WASM.
-// JavaScript calls all editor_* functions from the main thread only.
-// SAFETY: UnsafeCell is wrapped in Sync ONLY because we guarantee single-threaded access.
-// If Web Workers or WASM threads are used, this MUST be changed to Mutex<Option<Editor>>.
-struct EditorCell {
-    inner: UnsafeCell<Option<Editor>>,
+// Select the last query result; returns the selection size
+#[no_mangle]
+pub extern "C" fn editor_select_query(add: u32) -> u32 {
+    editor_mut().map(|e| e.select_query(add != 0)).unwrap_or(0)
 }
 
-unsafe impl Sync for EditorCell {}  // Only safe for single-threaded WASM
-
-static EDITOR: EditorCell = EditorCell {
-    inner: UnsafeCell::new(None),
-};
+// Copy lines and fills into the clipboard blob (editor_clipboard_ptr/len).
+// Each returns the blob length in bytes.
+#[no_mangle]
+pub extern "C" fn editor_copy_rect(x1: f32, y1: f32, x2: f32, y2: f32) -> u32 {
+    editor_mut().map(|e| e.copy_rect(x1, y1, x2, y2)).unwrap_or(0)
+}
 
-fn editor_mut() -> Option<&'static mut Editor> {
-    unsafe { (&mut *EDITOR.inner.get()).as_mut() }
+// `lines` / `fills` list indices; either may be null with count 0
+// Safety: non-null `lines` / `fills` must address `line_count` / `fill_count` readable u32s
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_copy_indices(lines: *const u32, line_count: usize, fills: *const u32, fill_count: usize) -> u32 {
+    let read = |ptr: *const u32, count: usize| -> Vec<usize> {
+        if ptr.is_null() || count == 0 {
+            return Vec::new();
+        }
+        let raw = unsafe { core::slice::from_raw_parts(ptr, count) };
+        raw.iter().map(|&i| i as usize).collect()
+    };
+    let (line_idx, fill_idx) = (read(lines, line_count), read(fills, fill_count));
+    editor_mut().map(|e| e.copy_items(&line_idx, &fill_idx)).unwrap_or(0)
 }
 
-fn editor_ref() -> Option<&'static Editor> {
-    unsafe { (&*EDITOR.inner.get()).as_ref() }
+#[no_mangle]
+pub extern "C" fn editor_copy_selection() -> u32 {
+    editor_mut().map(|e| e.copy_selection()).unwrap_or(0)
 }
 
 #[no_mangle]
-pub extern "C" fn editor_init() {
-    unsafe {
-        *EDITOR.inner.get() = Some(Editor::new());
-    }
+pub extern "C" fn editor_clipboard_ptr() -> *const u8 {
+    editor_ref().map(|e| e.clipboard_buf.as_ptr()).unwrap_or(ptr::null())
 }
 
 #[no_mangle]
-pub extern "C" fn editor_add_line(x1: f32, y1: f32, x2: f32, y2: f32) {
-    if let Some(editor) = editor_mut() {
-        editor.add_line(Line { x1, y1, x2, y2 });
-    }
+pub extern "C" fn editor_clipboard_len() -> u32 {
+    editor_ref().map(|e| e.clipboard_buf.len() as u32).unwrap_or(0)
 }
 
+// Make room for an incoming blob of `len` bytes in the clipboard buffer and
+// return where to write it (for pasting a blob copied from another document)
 #[no_mangle]
-pub extern "C" fn editor_add_frame(x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32, x4: f32, y4: f32) {
-    if let Some(editor) = editor_mut() {
-        editor.add_frame(x1, y1, x2, y2, x3, y3, x4, y4);
+pub extern "C" fn editor_clipboard_reserve(len: usize) -> *mut u8 {
+    match editor_mut() {
+        Some(editor) => {
+            editor.clipboard_buf.resize(len, 0);
+            editor.clipboard_buf.as_mut_ptr()
+        }
+        None => ptr::null_mut(),
     }
 }
 
+// Insert a clipboard blob offset by (dx, dy) as one undo step; the pasted items
+// become the selection. Returns the number of lines + fills, 0 for a bad blob.
+// Safety: a non-null `blob` must address `len` readable bytes
 #[no_mangle]
-pub extern "C" fn editor_undo() {
-    if let Some(editor) = editor_mut() {
-        editor.undo();
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_paste(blob: *const u8, len: usize, dx: f32, dy: f32) -> u32 {
+    if blob.is_null() || len == 0 {
+        return 0;
     }
+    let bytes = unsafe { core::slice::from_raw_parts(blob, len) }.to_vec();
+    editor_mut().and_then(|e| e.paste(&bytes, dx, dy).ok()).unwrap_or(0)
 }
 
+// Why the last paste was rejected: 0 = no error, 1 = bad magic, 2 = unsupported
+// version, 3 = truncated, 4 = bad data (unknown paint, non-finite coordinate)
 #[no_mangle]
-pub extern "C" fn editor_clear() {
-    if let Some(editor) = editor_mut() {
-        editor.clear();
-    }
+pub extern "C" fn editor_paste_error() -> u32 {
+    editor_ref()
+        .and_then(|e| e.paste_error)
+        .map(|err| err.code())
+        .unwrap_or(0)
 }
 
+// Delete stroke parts strictly inside fills. `indices` lists the fills to trim
+// against; count 0 means all fills. Returns the number of segments removed.
+// Safety: `indices` must address `count` readable u32s (unread when count is 0)
 #[no_mangle]
-pub extern "C" fn editor_cleanup_overhangs() {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_trim_inside_fills(indices: *const u32, count: usize) -> u32 {
     if let Some(editor) = editor_mut() {
-        editor.cleanup_overhangs();
+        if count == 0 {
+            return editor.trim_inside_fills(None);
+        }
+        let raw = unsafe { core::slice::from_raw_parts(indices, count) };
+        let selected: Vec<usize> = raw.iter().map(|&i| i as usize).collect();
+        return editor.trim_inside_fills(Some(&selected));
     }
+    0
 }
 
+// Pattern drawn in the fill color: kind 0 = hatch, 1 = crosshatch, 2 = dots.
+// angle in degrees; spacing is clamped to MIN_PATTERN_SPACING.
 #[no_mangle]
-pub extern "C" fn editor_line_count() -> u32 {
-    editor_ref().map(|e| e.line_count()).unwrap_or(0)
+pub extern "C" fn editor_set_fill_pattern(idx: u32, kind: u32, angle: f32, spacing: f32) -> u32 {
+    let Some(kind) = PatternKind::from_code(kind) else {
+        return 0;
+    };
+    let spacing = spacing.max(pattern::MIN_PATTERN_SPACING);
+    editor_mut()
+        .map(|e| e.set_fill_paint(idx as usize, Paint::Pattern { kind, angle, spacing }) as u32)
+        .unwrap_or(0)
 }
 
+// Format: [count, (fill_idx, x1, y1, x2, y2) * count]; dots have x1 == x2, y1 == y2
 #[no_mangle]
-pub extern "C" fn editor_export_ptr_f32() -> *const f32 {
+pub extern "C" fn editor_pattern_lines_ptr_f32() -> *const f32 {
     editor_ref()
-        .map(|e| e.export_ptr())
+        .map(|e| e.pattern_lines_ptr())
         .unwrap_or(ptr::null())
 }
 
 #[no_mangle]
-pub extern "C" fn editor_export_len_f32() -> u32 {
-    editor_ref().map(|e| e.export_len()).unwrap_or(0)
+pub extern "C" fn editor_pattern_lines_len_f32() -> u32 {
+    editor_ref().map(|e| e.pattern_lines_len()).unwrap_or(0)
 }
 
+// Back to the fill's solid color (or swatch)
 #[no_mangle]
-pub extern "C" fn editor_export_fills_ptr() -> *const f32 {
-    if let Some(editor) = editor_mut() {
-        editor.refresh_fills_export_buf();
-        return editor.fills_export_buf.as_ptr();
-    }
-    ptr::null()
+pub extern "C" fn editor_set_fill_solid(idx: u32) -> u32 {
+    editor_mut()
+        .map(|e| e.set_fill_paint(idx as usize, Paint::Solid) as u32)
+        .unwrap_or(0)
 }
 
+// handle 0 = linear start / radial center, 1 = linear end / radial rim.
+// Each call is one undo step, so call it when a drag ends.
 #[no_mangle]
-pub extern "C" fn editor_export_fills_len() -> u32 {
-    if let Some(editor) = editor_mut() {
-        editor.refresh_fills_export_buf();
-        return editor.fills_export_buf.len() as u32;
-    }
-    0
+pub extern "C" fn editor_move_gradient_handle(idx: u32, handle: u32, x: f32, y: f32) -> u32 {
+    editor_mut()
+        .map(|e| e.move_gradient_handle(idx as usize, handle, x, y) as u32)
+        .unwrap_or(0)
 }
 
+// Index of the topmost fill under (x, y), or -1 when there is none
 #[no_mangle]
-pub extern "C" fn editor_set_debug(enabled: u32) {
-    if let Some(editor) = editor_mut() {
-        editor.debug_enabled = enabled != 0;
-    }
+pub extern "C" fn editor_fill_hit_test(x: f32, y: f32) -> i32 {
+    editor_ref()
+        .and_then(|e| e.fill_hit_test(x, y))
+        .map(|idx| idx as i32)
+        .unwrap_or(-1)
 }
 
+// Returns 1 when the fill exists and the color parsed. Drops any swatch reference.
+// Safety: `color_ptr` must address `color_len` readable bytes
 #[no_mangle]
-pub extern "C" fn editor_nearest(px: f32, py: f32) {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_recolor_fill(idx: u32, color_ptr: *const u8, color_len: usize) -> u32 {
     if let Some(editor) = editor_mut() {
-        if editor.debug_enabled {
-            editor.compute_nearest_debug(px, py);
-        } else {
-            editor.debug_buf.clear();
-            editor.debug_buf.push(0.0);
+        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
+        if let Some(color) = editor.read_color(color_slice) {
+            return editor.recolor_fill(idx as usize, color, None) as u32;
         }
     }
+    0
 }
 
+// Returns 1 when a fill was removed
 #[no_mangle]
-pub extern "C" fn editor_debug_ptr_f32() -> *const f32 {
-    editor_ref()
-        .map(|e| e.debug_ptr())
-        .unwrap_or(ptr::null())
+pub extern "C" fn editor_delete_fill(idx: u32) -> u32 {
+    editor_mut().map(|e| e.delete_fill(idx as usize) as u32).unwrap_or(0)
 }
 
+// direction > 0 moves the fill up (drawn later), otherwise down.
+// Returns the new index, or -1 when the fill cannot move that way.
 #[no_mangle]
-pub extern "C" fn editor_debug_len_f32() -> u32 {
-    editor_ref().map(|e| e.debug_len()).unwrap_or(0)
+pub extern "C" fn editor_move_fill(idx: u32, direction: i32) -> i32 {
+    editor_mut()
+        .and_then(|e| e.move_fill(idx as usize, direction > 0))
+        .map(|new_idx| new_idx as i32)
+        .unwrap_or(-1)
 }
 
 #[no_mangle]
-pub extern "C" fn editor_intersections_ptr_f32() -> *const f32 {
-    editor_ref()
-        .map(|e| e.intersections_ptr())
-        .unwrap_or(ptr::null())
+pub extern "C" fn editor_fills_count() -> u32 {
+    editor_ref().map(|e| e.fills.len() as u32).unwrap_or(0)
 }
 
+// Accepts #rgb, #rgba, #rrggbb, #rrggbbaa, rgb()/rgba() and hsl()/hsla().
+// Returns 1 on success; on a parse error the previous color is kept and the
+// reason is available from editor_color_error. Clears the active fill swatch.
 #[no_mangle]
-pub extern "C" fn editor_intersections_len_f32() -> u32 {
-    editor_ref().map(|e| e.intersections_len()).unwrap_or(0)
+pub extern "C" fn editor_set_fill_color(color_ptr: *const u8, color_len: usize) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
+        if let Some(color) = editor.read_color(color_slice) {
+            editor.fill_color = color;
+            editor.fill_swatch = None;
+            return 1;
+        }
+    }
+    0
 }
 
+// Color the eyedropper reads when no fill covers the point
+// Safety: `color_ptr` must address `color_len` readable bytes
 #[no_mangle]
-pub extern "C" fn editor_fill(x: f32, y: f32, color_ptr: *const u8, color_len: usize) {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_set_background_color(color_ptr: *const u8, color_len: usize) -> u32 {
     if let Some(editor) = editor_mut() {
         let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
-        let color = parse_hex_color(color_slice);
+        if let Some(color) = editor.read_color(color_slice) {
+            editor.background_color = color;
+            return 1;
+        }
+    }
+    0
+}
 
-        if let Some(nearest_idx) = find_nearest_line(&editor.lines, x, y, f32::INFINITY) {
-            let connected = find_connected_lines(&editor.lines, nearest_idx);
-            let polygon = trace_polygon(&editor.lines, &connected);
+// Why the last color string was rejected: 0 = no error, 1 = empty, 2 = not UTF-8,
+// 3 = bad hex, 4 = bad function syntax, 5 = component out of range, 6 = unknown format
+#[no_mangle]
+pub extern "C" fn editor_color_error() -> u32 {
+    editor_ref()
+        .and_then(|e| e.color_error)
+        .map(|err| err.code())
+        .unwrap_or(0)
+}
 
-            if polygon.is_closed() {
-                editor.fills.push(polygon.with_color(color));
-                editor.history.push(Command::AddFill);
-                editor.refresh_export_fills();
-            }
+// Add a named swatch. Returns its id, or 0 when the name is not UTF-8 or the color
+// does not parse.
+// Safety: `name_ptr` / `color_ptr` must address `name_len` / `color_len` readable bytes
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_add_swatch(name_ptr: *const u8, name_len: usize, color_ptr: *const u8, color_len: usize) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let name_slice = unsafe { core::slice::from_raw_parts(name_ptr, name_len) };
+        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
+        let Ok(name) = core::str::from_utf8(name_slice) else {
+            editor.color_error = Some(ColorError::NotUtf8);
+            return 0;
+        };
+        if let Some(color) = editor.read_color(color_slice) {
+            return editor.add_swatch(name, color);
+        }
+    }
+    0
+}
+
+// Id of the swatch with this name, 0 when there is none
+// Safety: `name_ptr` must address `name_len` readable bytes
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_swatch_id(name_ptr: *const u8, name_len: usize) -> u32 {
+    if let Some(editor) = editor_ref() {
+        let name_slice = unsafe { core::slice::from_raw_parts(name_ptr, name_len) };
+        if let Ok(name) = core::str::from_utf8(name_slice) {
+            return editor.palette.find_by_name(name).unwrap_or(0);
         }
     }
+    0
 }
 
+// Recolors every fill that references the swatch. Returns 1 on success.
+// Safety: `color_ptr` must address `color_len` readable bytes
 #[no_mangle]
-pub extern "C" fn editor_fill_debug_at(ox: f32, oy: f32) {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn editor_set_swatch_color(id: u32, color_ptr: *const u8, color_len: usize) -> u32 {
     if let Some(editor) = editor_mut() {
-        editor.fill_debug_at(ox, oy);
+        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
+        if let Some(color) = editor.read_color(color_slice) {
+            return editor.set_swatch_color(id, color) as u32;
+        }
     }
+    0
 }
 
 #[no_mangle]
-pub extern "C" fn editor_fills_count() -> u32 {
-    editor_ref().map(|e| e.fills.len() as u32).unwrap_or(0)
+pub extern "C" fn editor_remove_swatch(id: u32) -> u32 {
+    editor_mut().map(|e| e.remove_swatch(id) as u32).unwrap_or(0)
 }
 
+// New fills reference this swatch; 0 goes back to the plain fill color.
+// Returns 1 when the swatch exists (or id is 0).
 #[no_mangle]
-pub extern "C" fn editor_set_fill_color(color_ptr: *const u8, color_len: usize) {
+pub extern "C" fn editor_set_fill_swatch(id: u32) -> u32 {
     if let Some(editor) = editor_mut() {
-        let color_slice = unsafe { core::slice::from_raw_parts(color_ptr, color_len) };
-        editor.fill_color = parse_hex_color(color_slice);
+        if id == 0 {
+            editor.fill_swatch = None;
+            return 1;
+        }
+        if editor.palette.get(id).is_some() {
+            editor.fill_swatch = Some(id);
+            return 1;
+        }
     }
+    0
+}
+
+// Format: [count, (id, r, g, b, a) * count], channels 0-1
+#[no_mangle]
+pub extern "C" fn editor_swatches_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.swatches_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_swatches_len_f32() -> u32 {
+    editor_ref().map(|e| e.swatches_len()).unwrap_or(0)
+}
+
+// RGBA (0xRRGGBBAA) of the topmost fill containing (x, y), or the background
+#[no_mangle]
+pub extern "C" fn editor_color_at(x: f32, y: f32) -> u32 {
+    editor_ref().map(|e| e.color_at(x, y)).unwrap_or(0)
 }
 
 #[no_mangle]
@@ -2634,8 +5937,46 @@ pub extern "C" fn editor_fill_walk_debug_len_f32() -> u32 {
     editor_ref().map(|e| e.fill_walk_debug_len()).unwrap_or(0)
 }
 
+// Leak detector: returns the number of endpoints with a gap target within radius.
+// radius <= 0 uses LEAK_RADIUS. Format: [count, (ex, ey, tx, ty, gap, kind) * count]
+#[no_mangle]
+pub extern "C" fn editor_analyze_leaks(radius: f32) -> u32 {
+    if let Some(editor) = editor_mut() {
+        let r = if radius > 0.0 { radius } else { LEAK_RADIUS };
+        return editor.analyze_leaks(r);
+    }
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn editor_leaks_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.leak_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_leaks_len_f32() -> u32 {
+    editor_ref().map(|e| e.leak_len()).unwrap_or(0)
+}
+
+// Structured result of the last fill call
+// Format: [status, steps, unique_states, side, area, fill_idx, has_fail_node, fail_x, fail_y]
+// steps and unique_states are 0 (not tracked) for the legacy strategy
+#[no_mangle]
+pub extern "C" fn editor_fill_result_ptr_f32() -> *const f32 {
+    editor_ref()
+        .map(|e| e.fill_result_ptr())
+        .unwrap_or(ptr::null())
+}
+
+#[no_mangle]
+pub extern "C" fn editor_fill_result_len_f32() -> u32 {
+    editor_ref().map(|e| e.fill_result_len()).unwrap_or(0)
+}
+
 // GUARDRAILS: Fill stats exports
-// Format: [ok, steps, unique_states, cand_max, abort_code]
+// Format: [ok, steps, unique_states, cand_max, abort_code] + (step, node, incoming, chosen, cand_count, score) per trace entry
 #[no_mangle]
 pub extern "C" fn editor_fill_stats_ptr_f32() -> *const f32 {
     editor_ref()
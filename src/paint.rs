@@ -0,0 +1,190 @@
+//! Fill paints: solid color, linear / radial gradients or line patterns.
+//! Gradient geometry is in canvas coordinates (SVG userSpaceOnUse), stop colors
+//! are packed 0xRRGGBBAA like everywhere else.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::color::{parse_color, ColorError};
+use crate::pattern::PatternKind;
+use crate::transform::Affine;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32, // 0-1 along the gradient
+    pub color: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    /// Polygon color (or its swatch)
+    Solid,
+    Linear {
+        start: (f32, f32),
+        end: (f32, f32),
+        stops: Vec<GradientStop>,
+    },
+    Radial {
+        center: (f32, f32),
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+    /// Drawn with the polygon color, expanded to lines by crate::pattern
+    Pattern {
+        kind: PatternKind,
+        angle: f32, // degrees
+        spacing: f32,
+    },
+}
+
+impl Paint {
+    /// Export kind: 0 solid, 1 linear, 2 radial, 3 pattern
+    pub fn kind(&self) -> u32 {
+        match self {
+            Paint::Solid => 0,
+            Paint::Linear { .. } => 1,
+            Paint::Radial { .. } => 2,
+            Paint::Pattern { .. } => 3,
+        }
+    }
+
+    /// Kind-specific export params:
+    /// linear `[x1, y1, x2, y2, stop_count, (offset, r, g, b, a) * stop_count]`,
+    /// radial `[cx, cy, r, stop_count, (offset, r, g, b, a) * stop_count]`,
+    /// pattern `[pattern_kind, angle, spacing]`, solid none
+    pub fn write_params(&self, buf: &mut Vec<f32>) {
+        match self {
+            Paint::Solid => {}
+            Paint::Pattern { kind, angle, spacing } => {
+                buf.extend_from_slice(&[kind.code() as f32, *angle, *spacing]);
+            }
+            Paint::Linear { start, end, stops } => {
+                buf.extend_from_slice(&[start.0, start.1, end.0, end.1]);
+                write_stops(buf, stops);
+            }
+            Paint::Radial { center, radius, stops } => {
+                buf.extend_from_slice(&[center.0, center.1, *radius]);
+                write_stops(buf, stops);
+            }
+        }
+    }
+
+    /// Move a drag handle: 0 is the linear start or radial center, 1 the linear end
+    /// or any point on the radial rim. Moving the radial center keeps the radius.
+    pub fn set_handle(&mut self, handle: u32, x: f32, y: f32) -> bool {
+        match (self, handle) {
+            (Paint::Linear { start, .. }, 0) => *start = (x, y),
+            (Paint::Linear { end, .. }, 1) => *end = (x, y),
+            (Paint::Radial { center, .. }, 0) => *center = (x, y),
+            (Paint::Radial { center, radius, .. }, 1) => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                *radius = crate::sqrt_precise(dx * dx + dy * dy);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Carry the paint along with its fill: gradient geometry is mapped through `m`
+    /// (radial radius by the uniform length factor). Patterns stay on the global
+    /// grid; their angle follows mirroring plus `turn` degrees of rotation.
+    pub fn transform(&mut self, m: &Affine, turn: f32) {
+        match self {
+            Paint::Solid => {}
+            Paint::Linear { start, end, .. } => {
+                *start = m.apply(start.0, start.1);
+                *end = m.apply(end.0, end.1);
+            }
+            Paint::Radial { center, radius, .. } => {
+                *center = m.apply(center.0, center.1);
+                *radius *= m.length_scale();
+            }
+            Paint::Pattern { angle, .. } => {
+                if m.mirrors() {
+                    *angle = -*angle;
+                }
+                *angle = (*angle + turn) % 180.0;
+            }
+        }
+    }
+
+    /// Color at a point, `solid` for solid and pattern paints. Outside the gradient range the
+    /// end stops extend (SVG spreadMethod="pad").
+    pub fn sample(&self, x: f32, y: f32, solid: u32) -> u32 {
+        match self {
+            Paint::Solid | Paint::Pattern { .. } => solid,
+            Paint::Linear { start, end, stops } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let len2 = dx * dx + dy * dy;
+                let t = if len2 > 0.0 { ((x - start.0) * dx + (y - start.1) * dy) / len2 } else { 0.0 };
+                sample_stops(stops, t, solid)
+            }
+            Paint::Radial { center, radius, stops } => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                let t = if *radius > 0.0 { crate::sqrt_precise(dx * dx + dy * dy) / radius } else { 0.0 };
+                sample_stops(stops, t, solid)
+            }
+        }
+    }
+}
+
+fn write_stops(buf: &mut Vec<f32>, stops: &[GradientStop]) {
+    buf.push(stops.len() as f32);
+    for stop in stops.iter() {
+        buf.push(stop.offset);
+        crate::push_rgba(buf, stop.color);
+    }
+}
+
+fn sample_stops(stops: &[GradientStop], t: f32, fallback: u32) -> u32 {
+    let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+        return fallback;
+    };
+    if t <= first.offset {
+        return first.color;
+    }
+    for pair in stops.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if t <= b.offset {
+            let span = b.offset - a.offset;
+            let k = if span > 0.0 { (t - a.offset) / span } else { 1.0 };
+            return lerp_rgba(a.color, b.color, k);
+        }
+    }
+    last.color
+}
+
+fn lerp_rgba(a: u32, b: u32, k: f32) -> u32 {
+    let mut out = 0;
+    for shift in [24, 16, 8, 0] {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        out |= ((ca + (cb - ca) * k + 0.5) as u32 & 0xFF) << shift;
+    }
+    out
+}
+
+/// Parse a stop list like `0 #ff0000; 0.5 rgb(0 255 0); 1 #00f`.
+/// Offsets are 0-1 (or percentages) and must not decrease; at least two stops.
+pub fn parse_stops(input: &[u8]) -> Result<Vec<GradientStop>, ColorError> {
+    let text = core::str::from_utf8(input).map_err(|_| ColorError::NotUtf8)?;
+    let mut stops: Vec<GradientStop> = Vec::new();
+    for entry in text.split(';').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let (offset, color) = entry.split_once(char::is_whitespace).ok_or(ColorError::BadSyntax)?;
+        let offset = match offset.strip_suffix('%') {
+            Some(pct) => pct.parse::<f32>().map(|v| v / 100.0),
+            None => offset.parse::<f32>(),
+        }
+        .map_err(|_| ColorError::BadComponent)?;
+        let monotonic = stops.last().map(|s| offset >= s.offset).unwrap_or(true);
+        if !(0.0..=1.0).contains(&offset) || !monotonic {
+            return Err(ColorError::BadComponent);
+        }
+        let color = parse_color(color.as_bytes())?;
+        stops.push(GradientStop { offset, color });
+    }
+    if stops.len() < 2 {
+        return Err(ColorError::BadSyntax);
+    }
+    Ok(stops)
+}
@@ -0,0 +1,237 @@
+//! Pattern fills expanded into line geometry for plotter / print output.
+//! Hatch lines run along the pattern angle on a global grid (so neighbouring
+//! fills with the same pattern line up) and are clipped to the fill outline
+//! minus its holes with an even-odd scanline.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// Smallest spacing accepted, keeps the expansion bounded
+pub const MIN_PATTERN_SPACING: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternKind {
+    Hatch,
+    Crosshatch,
+    Dots,
+}
+
+impl PatternKind {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(PatternKind::Hatch),
+            1 => Some(PatternKind::Crosshatch),
+            2 => Some(PatternKind::Dots),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> u32 {
+        match self {
+            PatternKind::Hatch => 0,
+            PatternKind::Crosshatch => 1,
+            PatternKind::Dots => 2,
+        }
+    }
+}
+
+/// Most segments (or dots) one fill expands to. A fill large enough to exceed
+/// it at the requested spacing is expanded at a proportionally wider spacing.
+pub const MAX_PATTERN_PRIMITIVES: usize = 2048;
+
+/// Buffers reused by every expansion (the bump allocator never frees)
+#[derive(Default)]
+pub struct PatternScratch {
+    outer_uv: Vec<(f32, f32)>,
+    holes_uv: Vec<(f32, f32)>,
+    hole_ends: Vec<usize>,
+    spans: Vec<(f32, f32)>,
+    hole_spans: Vec<(f32, f32)>,
+    merged: Vec<(f32, f32)>,
+    crossings: Vec<f32>,
+}
+
+impl PatternScratch {
+    pub fn new() -> Self {
+        Self {
+            outer_uv: Vec::new(),
+            holes_uv: Vec::new(),
+            hole_ends: Vec::new(),
+            spans: Vec::new(),
+            hole_spans: Vec::new(),
+            merged: Vec::new(),
+            crossings: Vec::new(),
+        }
+    }
+}
+
+/// Expand a pattern over `outer` minus `holes`, appending `(x1, y1, x2, y2)` per
+/// segment through `emit`, at most MAX_PATTERN_PRIMITIVES of them. Dots are
+/// zero-length segments (a pen dot). `angle` is in degrees. The holes are packed
+/// back to back in `holes`, hole i ending at `hole_ends[i]`.
+#[allow(clippy::too_many_arguments)]
+pub fn expand_pattern(
+    kind: PatternKind,
+    angle: f32,
+    spacing: f32,
+    outer: &[(f32, f32)],
+    holes: &[(f32, f32)],
+    hole_ends: &[usize],
+    scratch: &mut PatternScratch,
+    emit: &mut dyn FnMut(f32, f32, f32, f32),
+) {
+    let (minx, miny, maxx, maxy) = crate::poly_bounds(outer);
+    let (w, h) = (maxx - minx, maxy - miny);
+    if !(w.is_finite() && h.is_finite()) {
+        return;
+    }
+    // Widen the spacing so the expected count stays at half the budget (dots
+    // cover the area, hatch lines the diagonal); the hard cap below only cuts
+    // odd shapes short
+    let budget = MAX_PATTERN_PRIMITIVES as f32;
+    let spacing = if spacing > MIN_PATTERN_SPACING { spacing } else { MIN_PATTERN_SPACING };
+    let spacing = match kind {
+        PatternKind::Dots => spacing.max(crate::sqrt_precise(w * h * 2.0 / budget)),
+        PatternKind::Hatch => spacing.max(crate::sqrt_precise(w * w + h * h) * 2.0 / budget),
+        PatternKind::Crosshatch => spacing.max(crate::sqrt_precise(w * w + h * h) * 4.0 / budget),
+    };
+
+    let rad = angle.to_radians();
+    let mut left = MAX_PATTERN_PRIMITIVES;
+    match kind {
+        PatternKind::Hatch => hatch(rad, spacing, outer, holes, hole_ends, false, scratch, &mut left, emit),
+        PatternKind::Crosshatch => {
+            hatch(rad, spacing, outer, holes, hole_ends, false, scratch, &mut left, emit);
+            let cross = rad + core::f32::consts::FRAC_PI_2;
+            hatch(cross, spacing, outer, holes, hole_ends, false, scratch, &mut left, emit);
+        }
+        PatternKind::Dots => hatch(rad, spacing, outer, holes, hole_ends, true, scratch, &mut left, emit),
+    }
+}
+
+// Scan lines v = k * spacing in the frame rotated by `rad`
+// (u runs along the hatch direction, v across it). Stops once `left` runs out.
+#[allow(clippy::too_many_arguments)]
+fn hatch(
+    rad: f32,
+    spacing: f32,
+    outer: &[(f32, f32)],
+    holes: &[(f32, f32)],
+    holes_end: &[usize],
+    dots: bool,
+    scratch: &mut PatternScratch,
+    left: &mut usize,
+    emit: &mut dyn FnMut(f32, f32, f32, f32),
+) {
+    let (sin, cos) = crate::sin_cos_approx(rad);
+    let to_frame = |&(x, y): &(f32, f32)| (x * cos + y * sin, -x * sin + y * cos);
+    let PatternScratch { outer_uv, holes_uv, hole_ends, spans, hole_spans, merged, crossings } = scratch;
+
+    outer_uv.clear();
+    outer_uv.extend(outer.iter().map(to_frame));
+    holes_uv.clear();
+    holes_uv.extend(holes.iter().map(to_frame));
+    hole_ends.clear();
+    hole_ends.extend(holes_end.iter().copied().filter(|&end| end <= holes.len()));
+
+    let (mut v_min, mut v_max) = (f32::MAX, f32::MIN);
+    for &(_, v) in outer_uv.iter() {
+        v_min = v_min.min(v);
+        v_max = v_max.max(v);
+    }
+    if v_min > v_max {
+        return;
+    }
+
+    let mut k = (v_min / spacing) as i32 - 1;
+    loop {
+        let v = k as f32 * spacing;
+        k += 1;
+        if v > v_max {
+            break;
+        }
+        if v < v_min {
+            continue;
+        }
+        scan_spans(outer_uv, v, spans, crossings);
+        let mut hole_start = 0;
+        for &hole_end in hole_ends.iter() {
+            scan_spans(&holes_uv[hole_start..hole_end], v, hole_spans, crossings);
+            subtract_spans(spans, hole_spans, merged);
+            hole_start = hole_end;
+        }
+        for &(u0, u1) in spans.iter() {
+            if dots {
+                let mut j = (u0 / spacing) as i32 - 1;
+                loop {
+                    let u = j as f32 * spacing;
+                    j += 1;
+                    if u > u1 {
+                        break;
+                    }
+                    if u >= u0 {
+                        if *left == 0 {
+                            return;
+                        }
+                        *left -= 1;
+                        let (x, y) = (u * cos - v * sin, u * sin + v * cos);
+                        emit(x, y, x, y);
+                    }
+                }
+            } else {
+                if *left == 0 {
+                    return;
+                }
+                *left -= 1;
+                emit(u0 * cos - v * sin, u0 * sin + v * cos, u1 * cos - v * sin, u1 * sin + v * cos);
+            }
+        }
+    }
+}
+
+// Inside intervals of the scan line at `v` (even-odd, half-open at vertices)
+fn scan_spans(poly: &[(f32, f32)], v: f32, out: &mut Vec<(f32, f32)>, crossings: &mut Vec<f32>) {
+    out.clear();
+    crossings.clear();
+    let n = poly.len();
+    for i in 0..n {
+        let (u0, v0) = poly[i];
+        let (u1, v1) = poly[(i + 1) % n];
+        if (v0 <= v) != (v1 <= v) {
+            crossings.push(u0 + (v - v0) / (v1 - v0) * (u1 - u0));
+        }
+    }
+    crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+    for pair in crossings.chunks_exact(2) {
+        if pair[1] > pair[0] {
+            out.push((pair[0], pair[1]));
+        }
+    }
+}
+
+// spans -= cut; both sorted and non-overlapping. `merged` is scratch space
+// that ends up holding the previous spans.
+fn subtract_spans(spans: &mut Vec<(f32, f32)>, cut: &[(f32, f32)], merged: &mut Vec<(f32, f32)>) {
+    if cut.is_empty() {
+        return;
+    }
+    merged.clear();
+    for &(mut a, b) in spans.iter() {
+        for &(c0, c1) in cut.iter() {
+            if c1 <= a || c0 >= b {
+                continue;
+            }
+            if c0 > a {
+                merged.push((a, c0));
+            }
+            a = a.max(c1);
+            if a >= b {
+                break;
+            }
+        }
+        if a < b {
+            merged.push((a, b));
+        }
+    }
+    core::mem::swap(spans, merged);
+}
@@ -0,0 +1,143 @@
+//! Polyline simplification for freehand strokes and the simplify command.
+//! Both methods keep the end points and only drop a point when it stays within
+//! `tolerance` of the simplified polyline, so the result never strays further.
+
+extern crate alloc;
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimplifyMethod {
+    /// Ramer–Douglas–Peucker: split each span at its farthest point until every
+    /// point is within tolerance
+    DouglasPeucker,
+    /// Visvalingam–Whyatt: repeatedly drop the point spanning the smallest
+    /// triangle, as long as the span it leaves covers its points within tolerance
+    Visvalingam,
+}
+
+impl SimplifyMethod {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(SimplifyMethod::DouglasPeucker),
+            1 => Some(SimplifyMethod::Visvalingam),
+            _ => None,
+        }
+    }
+}
+
+/// Simplified copy of `points`; unchanged for fewer than 3 points or a
+/// non-positive tolerance
+pub fn simplify_points(points: &[(f32, f32)], tolerance: f32, method: SimplifyMethod) -> Vec<(f32, f32)> {
+    if points.len() < 3 || tolerance.is_nan() || tolerance <= 0.0 {
+        return points.to_vec();
+    }
+    let keep = match method {
+        SimplifyMethod::DouglasPeucker => douglas_peucker(points, tolerance * tolerance),
+        SimplifyMethod::Visvalingam => visvalingam(points, tolerance * tolerance),
+    };
+    points.iter().zip(keep).filter_map(|(&p, k)| k.then_some(p)).collect()
+}
+
+fn douglas_peucker(points: &[(f32, f32)], tol2: f32) -> Vec<bool> {
+    let n = points.len();
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+    // Explicit stack: long strokes would recurse deeply
+    let mut spans: Vec<(usize, usize)> = vec![(0, n - 1)];
+    while let Some((a, b)) = spans.pop() {
+        let mut farthest: Option<(usize, f32)> = None;
+        for i in a + 1..b {
+            let d2 = segment_dist_sq(points[i], points[a], points[b]);
+            if d2 > tol2 && farthest.map(|(_, fd)| d2 > fd).unwrap_or(true) {
+                farthest = Some((i, d2));
+            }
+        }
+        if let Some((i, _)) = farthest {
+            keep[i] = true;
+            spans.push((a, i));
+            spans.push((i, b));
+        }
+    }
+    keep
+}
+
+// Points by smallest triangle first. Entries go stale instead of being
+// updated: a point's version bumps whenever a neighbour is dropped.
+struct Candidate {
+    area: f32,
+    point: usize,
+    version: u32,
+}
+
+impl Ord for Candidate {
+    // Reversed so the max-heap pops the smallest area (lowest index on ties)
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.area.total_cmp(&self.area).then(other.point.cmp(&self.point))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+fn visvalingam(points: &[(f32, f32)], tol2: f32) -> Vec<bool> {
+    let n = points.len();
+    let mut keep = vec![true; n];
+    // Doubly linked list over the points still kept
+    let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=n).collect();
+    let mut version = vec![0u32; n];
+    let mut heap: BinaryHeap<Candidate> = (1..n - 1)
+        .map(|i| Candidate { area: triangle_area(points[i - 1], points[i], points[i + 1]), point: i, version: 0 })
+        .collect();
+    while let Some(Candidate { point: i, version: v, .. }) = heap.pop() {
+        if !keep[i] || v != version[i] {
+            continue;
+        }
+        // A point that fails stays until a neighbour goes and it is pushed again
+        let (a, b) = (prev[i], next[i]);
+        if (a + 1..b).any(|k| segment_dist_sq(points[k], points[a], points[b]) > tol2) {
+            continue;
+        }
+        keep[i] = false;
+        next[a] = b;
+        prev[b] = a;
+        for j in [a, b] {
+            if j == 0 || j == n - 1 {
+                continue;
+            }
+            version[j] += 1;
+            let area = triangle_area(points[prev[j]], points[j], points[next[j]]);
+            heap.push(Candidate { area, point: j, version: version[j] });
+        }
+    }
+    keep
+}
+
+fn triangle_area(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if cross < 0.0 { -cross * 0.5 } else { cross * 0.5 }
+}
+
+// Squared distance from p to segment a-b (to a when the segment is degenerate)
+fn segment_dist_sq(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+    let (qx, qy) = (a.0 + t * dx - p.0, a.1 + t * dy - p.1);
+    qx * qx + qy * qy
+}
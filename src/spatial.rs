@@ -0,0 +1,266 @@
+//! Uniform grid over item bounding boxes for hit testing and nearest queries.
+//! Entries are `(cell, item)` pairs kept sorted by cell, so a rebuild reuses the
+//! same buffers (the bump allocator never frees) and a cell lookup is a binary
+//! search. Items spanning more than `MAX_ITEM_CELLS` cells go into a short
+//! list every query visits. Queries return candidates; exact distance tests
+//! stay with the caller.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// Cell edge in canvas units
+pub const GRID_CELL: f32 = 64.0;
+
+/// Larger items are kept out of the cells (huge or non-finite coordinates)
+const MAX_ITEM_CELLS: i64 = 1024;
+
+type Cell = (i32, i32);
+
+pub struct SpatialGrid {
+    entries: Vec<(Cell, u32)>,
+    /// Items too large for the cells
+    wide: Vec<u32>,
+    /// Occupied cell range, bounds the ring search
+    min_cell: Cell,
+    max_cell: Cell,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            wide: Vec::new(),
+            min_cell: (0, 0),
+            max_cell: (-1, -1),
+        }
+    }
+
+    fn cell_of(x: f32, y: f32) -> Cell {
+        (crate::floor_f32(x / GRID_CELL) as i32, crate::floor_f32(y / GRID_CELL) as i32)
+    }
+
+    // Cell range of a box, or None when it is too large to spread over cells
+    fn cell_range(minx: f32, miny: f32, maxx: f32, maxy: f32) -> Option<(Cell, Cell)> {
+        let (c0, c1) = (Self::cell_of(minx, miny), Self::cell_of(maxx, maxy));
+        let cells = (c1.0 as i64 - c0.0 as i64 + 1) * (c1.1 as i64 - c0.1 as i64 + 1);
+        if cells > MAX_ITEM_CELLS || !(minx.is_finite() && miny.is_finite() && maxx.is_finite() && maxy.is_finite()) {
+            None
+        } else {
+            Some((c0, c1))
+        }
+    }
+
+    /// Index segments `(x1, y1, x2, y2)`: each goes into the cells it passes through
+    pub fn rebuild_segments(&mut self, segments: impl Iterator<Item = (f32, f32, f32, f32)>) {
+        self.entries.clear();
+        self.wide.clear();
+        for (idx, (x1, y1, x2, y2)) in segments.enumerate() {
+            let Some((c0, c1)) = Self::cell_range(x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)) else {
+                self.wide.push(idx as u32);
+                continue;
+            };
+            for cx in c0.0..=c1.0 {
+                for cy in c0.1..=c1.1 {
+                    if segment_hits_cell((x1, y1, x2, y2), (cx, cy)) {
+                        self.entries.push(((cx, cy), idx as u32));
+                    }
+                }
+            }
+        }
+        self.finish();
+    }
+
+    /// Index bounding boxes `(min_x, min_y, max_x, max_y)`: each goes into every cell it overlaps
+    pub fn rebuild_boxes(&mut self, boxes: impl Iterator<Item = (f32, f32, f32, f32)>) {
+        self.entries.clear();
+        self.wide.clear();
+        for (idx, (minx, miny, maxx, maxy)) in boxes.enumerate() {
+            let Some((c0, c1)) = Self::cell_range(minx, miny, maxx, maxy) else {
+                self.wide.push(idx as u32);
+                continue;
+            };
+            for cx in c0.0..=c1.0 {
+                for cy in c0.1..=c1.1 {
+                    self.entries.push(((cx, cy), idx as u32));
+                }
+            }
+        }
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        self.entries.sort_unstable();
+        self.min_cell = (i32::MAX, i32::MAX);
+        self.max_cell = (i32::MIN, i32::MIN);
+        for &((cx, cy), _) in self.entries.iter() {
+            self.min_cell = (self.min_cell.0.min(cx), self.min_cell.1.min(cy));
+            self.max_cell = (self.max_cell.0.max(cx), self.max_cell.1.max(cy));
+        }
+    }
+
+    fn cell_items(&self, cell: Cell) -> impl Iterator<Item = u32> + '_ {
+        let start = self.entries.partition_point(|e| e.0 < cell);
+        self.entries[start..].iter().take_while(move |e| e.0 == cell).map(|e| e.1)
+    }
+
+    /// Items whose cells contain the point. Allocation free.
+    pub fn at_point(&self, x: f32, y: f32) -> impl Iterator<Item = u32> + '_ {
+        self.wide.iter().copied().chain(self.cell_items(Self::cell_of(x, y)))
+    }
+
+    /// Visit items whose cells overlap the rectangle; an item spanning several of
+    /// those cells is visited once per cell. Allocation free.
+    pub fn visit_rect(&self, minx: f32, miny: f32, maxx: f32, maxy: f32, mut visit: impl FnMut(u32)) {
+        self.wide.iter().copied().for_each(&mut visit);
+        if self.entries.is_empty() {
+            return;
+        }
+        // Clamp to the occupied range first so a huge rectangle stays cheap
+        let clamp = |v: f32, lo: i32, hi: i32| {
+            let c = crate::floor_f32(v / GRID_CELL);
+            if c < lo as f32 { lo } else if c > hi as f32 { hi } else { c as i32 }
+        };
+        let (lo, hi) = (self.min_cell, self.max_cell);
+        let (cx0, cx1) = (clamp(minx, lo.0, hi.0 + 1), clamp(maxx, lo.0 - 1, hi.0));
+        let (cy0, cy1) = (clamp(miny, lo.1, hi.1 + 1), clamp(maxy, lo.1 - 1, hi.1));
+        for cx in cx0..=cx1 {
+            for cy in cy0..=cy1 {
+                self.cell_items((cx, cy)).for_each(&mut visit);
+            }
+        }
+    }
+
+    /// Items whose cells overlap the rectangle, ascending and each reported once
+    pub fn query_rect(&self, minx: f32, miny: f32, maxx: f32, maxy: f32) -> Vec<u32> {
+        let mut out: Vec<u32> = Vec::new();
+        self.visit_rect(minx, miny, maxx, maxy, |item| out.push(item));
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    /// Nearest item to (x, y) within `max_dist` by the caller's squared distance,
+    /// searching rings of cells outwards. Allocation free (runs on pointer move).
+    pub fn nearest(&self, x: f32, y: f32, max_dist: f32, dist2: impl Fn(u32) -> f32) -> Option<(u32, f32)> {
+        let mut best: Option<(u32, f32)> = None;
+        self.rings(
+            x,
+            y,
+            max_dist,
+            &mut best,
+            // Keep going while this ring can still beat the best hit
+            |best, ring_min2| best.map(|(_, d)| ring_min2 <= d).unwrap_or(true),
+            |best, item| {
+                let d = dist2(item);
+                if d <= max_dist * max_dist && best.map(|(_, bd)| d < bd).unwrap_or(true) {
+                    *best = Some((item, d));
+                }
+            },
+        );
+        best
+    }
+
+    /// Up to `k` nearest items within `max_dist`, closest first
+    pub fn k_nearest(&self, x: f32, y: f32, k: usize, max_dist: f32, dist2: impl Fn(u32) -> f32) -> Vec<(u32, f32)> {
+        let mut found: Vec<(u32, f32)> = Vec::with_capacity(k + 1);
+        if k == 0 {
+            return found;
+        }
+        // Stop once a whole ring lies beyond the current k-th distance
+        let kth = |found: &Vec<(u32, f32)>| if found.len() == k { found[k - 1].1 } else { f32::INFINITY };
+        self.rings(
+            x,
+            y,
+            max_dist,
+            &mut found,
+            |found, ring_min2| ring_min2 <= kth(found),
+            |found, item| {
+                let d = dist2(item);
+                if d > max_dist * max_dist || d >= kth(found) || found.iter().any(|&(i, _)| i == item) {
+                    return;
+                }
+                let at = found.partition_point(|&(_, fd)| fd <= d);
+                found.insert(at, (item, d));
+                found.truncate(k);
+            },
+        );
+        found
+    }
+
+    // Visit items ring by ring (Chebyshev distance in cells from the query cell)
+    // while `more(state, min squared distance of the ring)` holds. Rings that
+    // cannot reach the occupied cells are skipped; only perimeter cells are visited.
+    fn rings<S>(
+        &self,
+        x: f32,
+        y: f32,
+        max_dist: f32,
+        state: &mut S,
+        more: impl Fn(&S, f32) -> bool,
+        mut visit: impl FnMut(&mut S, u32),
+    ) {
+        for &item in self.wide.iter() {
+            visit(state, item);
+        }
+        if self.entries.is_empty() || !(x.is_finite() && y.is_finite()) {
+            return;
+        }
+        // Ring arithmetic in i64 so far-away query points cannot overflow
+        let (qx, qy) = (crate::floor_f32(x / GRID_CELL) as i64, crate::floor_f32(y / GRID_CELL) as i64);
+        let (lo, hi) = ((self.min_cell.0 as i64, self.min_cell.1 as i64), (self.max_cell.0 as i64, self.max_cell.1 as i64));
+        let gap = |q: i64, lo: i64, hi: i64| if q < lo { lo - q } else if q > hi { q - hi } else { 0 };
+        let first = gap(qx, lo.0, hi.0).max(gap(qy, lo.1, hi.1));
+        let last = (qx - lo.0).max(hi.0 - qx).max(qy - lo.1).max(hi.1 - qy);
+        let last = if max_dist.is_finite() { last.min((max_dist / GRID_CELL) as i64 + 1) } else { last };
+
+        let mut visit_cell = |state: &mut S, cx: i64, cy: i64| {
+            if cx >= lo.0 && cx <= hi.0 && cy >= lo.1 && cy <= hi.1 {
+                self.cell_items((cx as i32, cy as i32)).for_each(|item| visit(state, item));
+            }
+        };
+        for r in first..=last {
+            // Anything in ring r is at least (r - 1) cells away from the query point
+            let ring_min = (r - 1).max(0) as f32 * GRID_CELL;
+            if !more(state, ring_min * ring_min) {
+                break;
+            }
+            if r == 0 {
+                visit_cell(state, qx, qy);
+                continue;
+            }
+            for cx in (qx - r).max(lo.0)..=(qx + r).min(hi.0) {
+                visit_cell(state, cx, qy - r);
+                visit_cell(state, cx, qy + r);
+            }
+            for cy in (qy - r + 1).max(lo.1)..=(qy + r - 1).min(hi.1) {
+                visit_cell(state, qx - r, cy);
+                visit_cell(state, qx + r, cy);
+            }
+        }
+    }
+}
+
+// Segment against the cell square (Liang-Barsky clip, padded so segments on a
+// cell border land in both cells)
+fn segment_hits_cell((x1, y1, x2, y2): (f32, f32, f32, f32), (cx, cy): Cell) -> bool {
+    const PAD: f32 = 0.5;
+    let (minx, miny) = (cx as f32 * GRID_CELL - PAD, cy as f32 * GRID_CELL - PAD);
+    let (maxx, maxy) = (minx + GRID_CELL + 2.0 * PAD, miny + GRID_CELL + 2.0 * PAD);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-dx, x1 - minx), (dx, maxx - x1), (-dy, y1 - miny), (dy, maxy - y1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    t0 <= t1
+}
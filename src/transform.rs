@@ -0,0 +1,65 @@
+//! 2D affine transforms applied to selected geometry.
+//! Stored as the matrix `[a c e; b d f]`, i.e. `x' = a*x + c*y + e`,
+//! `y' = b*x + d*y + f` (the SVG `matrix(a b c d e f)` layout).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Affine {
+    pub fn translate(dx: f32, dy: f32) -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: dx, f: dy }
+    }
+
+    /// Rotation by `angle` degrees (clockwise on screen, y points down) about `pivot`
+    pub fn rotate(angle: f32, pivot: (f32, f32)) -> Self {
+        let (sin, cos) = crate::sin_cos_approx(angle.to_radians());
+        Self { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }.about(pivot)
+    }
+
+    /// Scale by `(sx, sy)` about `pivot`; negative factors mirror
+    pub fn scale(sx: f32, sy: f32, pivot: (f32, f32)) -> Self {
+        Self { a: sx, b: 0.0, c: 0.0, d: sy, e: 0.0, f: 0.0 }.about(pivot)
+    }
+
+    /// Mirror across the vertical (`horizontal` flip) or horizontal axis through `pivot`
+    pub fn flip(horizontal: bool, pivot: (f32, f32)) -> Self {
+        if horizontal {
+            Self::scale(-1.0, 1.0, pivot)
+        } else {
+            Self::scale(1.0, -1.0, pivot)
+        }
+    }
+
+    // Same linear part, applied around `pivot` instead of the origin
+    fn about(mut self, (px, py): (f32, f32)) -> Self {
+        self.e = px - self.a * px - self.c * py;
+        self.f = py - self.b * px - self.d * py;
+        self
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    /// Uniform length factor (sqrt of |det|), used for radii
+    pub fn length_scale(&self) -> f32 {
+        let det = self.a * self.d - self.b * self.c;
+        crate::sqrt_precise(if det < 0.0 { -det } else { det })
+    }
+
+    /// True when the transform flips orientation (negative determinant)
+    pub fn mirrors(&self) -> bool {
+        self.a * self.d - self.b * self.c < 0.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.a, self.b, self.c, self.d, self.e, self.f].iter().all(|v| v.is_finite())
+    }
+}
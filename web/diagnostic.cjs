@@ -247,6 +247,35 @@ async function main() {
     // Should remove dangling line
   });
 
+  test('Leak detector reports a frame gap', () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(0, 0, 100, 0);
+    wasm.exports.editor_add_line(100, 0, 100, 100);
+    wasm.exports.editor_add_line(100, 100, 0, 100);
+    wasm.exports.editor_add_line(0, 100, 0, 10); // Stops 10 px short of the corner
+    const count = wasm.exports.editor_analyze_leaks(20);
+    if (count === 0) {
+      throw new Error('No leak reported');
+    }
+    const buf = new Float32Array(
+      wasm.exports.memory.buffer,
+      wasm.exports.editor_leaks_ptr_f32(),
+      wasm.exports.editor_leaks_len_f32()
+    );
+    const leaks = [];
+    for (let i = 0; i < buf[0]; i++) {
+      leaks.push(Array.from(buf.slice(1 + i * 6, 7 + i * 6)));
+    }
+    // One entry per gap, from either of its two dangling ends
+    const gap = leaks.find(([ex, ey, tx, ty]) => ex === 0 && tx === 0 && ey + ty === 10 && ey * ty === 0);
+    if (!gap) {
+      throw new Error(`Gap between (0, 0) and (0, 10) missing: ${JSON.stringify(leaks)}`);
+    }
+    if (Math.abs(gap[4] - 10) > 1e-3 || gap[5] !== 1) {
+      throw new Error(`Expected a 10 px gap to an end node, got ${gap}`);
+    }
+  });
+
   test('Debug mode toggle', () => {
     wasm.exports.editor_set_debug(true);
     wasm.exports.editor_set_debug(false);
@@ -263,6 +292,455 @@ async function main() {
   });
   console.log();
 
+  // Test 6: Color parsing
+  console.log('🎨 Color Parsing:');
+  // Scratch space at the end of wasm memory for string arguments
+  const writeBytes = (bytes) => {
+    const ptr = wasm.exports.memory.buffer.byteLength - 256;
+    new Uint8Array(wasm.exports.memory.buffer, ptr, bytes.length).set(bytes);
+    return ptr;
+  };
+  const setBackground = (bytes) => wasm.exports.editor_set_background_color(writeBytes(bytes), bytes.length);
+
+  test('Color syntaxes parse', () => {
+    wasm.exports.editor_clear();
+    const cases = [
+      ['#f00', 0xFF0000FF],
+      ['#f008', 0xFF000088],
+      ['#00ff00', 0x00FF00FF],
+      ['#0000ff80', 0x0000FF80],
+      ['rgb(255, 128, 0)', 0xFF8000FF],
+      ['rgb(100% 0% 0% / 0)', 0xFF000000],
+      ['rgba(0, 0, 255, 1)', 0x0000FFFF],
+      ['hsl(120, 100%, 50%)', 0x00FF00FF],
+      ['hsla(240deg 100% 50% / 100%)', 0x0000FFFF],
+    ];
+    for (const [text, expected] of cases) {
+      if (setBackground(Buffer.from(text)) !== 1) {
+        throw new Error(`${text} rejected (error ${wasm.exports.editor_color_error()})`);
+      }
+      const color = wasm.exports.editor_color_at(-1000, -1000) >>> 0;
+      if (color !== expected) {
+        throw new Error(`${text}: got ${color.toString(16)}`);
+      }
+    }
+  });
+
+  test('Color errors report their code', () => {
+    const cases = [
+      [Buffer.alloc(0), 1],
+      [Buffer.from([0xff, 0xfe]), 2],
+      [Buffer.from('#12345'), 3],
+      [Buffer.from('rgb(1, 2)'), 4],
+      [Buffer.from('rgb(300, 0, 0)'), 5],
+      [Buffer.from('tomato'), 6],
+    ];
+    const before = wasm.exports.editor_color_at(-1000, -1000);
+    for (const [bytes, code] of cases) {
+      if (setBackground(bytes) !== 0) {
+        throw new Error(`case ${code} accepted`);
+      }
+      if (wasm.exports.editor_color_error() !== code) {
+        throw new Error(`Expected error ${code}, got ${wasm.exports.editor_color_error()}`);
+      }
+    }
+    if (wasm.exports.editor_color_at(-1000, -1000) !== before) {
+      throw new Error('Rejected color replaced the previous one');
+    }
+  });
+  console.log();
+
+  // Test 7: Clipboard blobs
+  console.log('📋 Clipboard:');
+  const drawSquare = () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(0, 0, 100, 0);
+    wasm.exports.editor_add_line(100, 0, 100, 100);
+    wasm.exports.editor_add_line(100, 100, 0, 100);
+    wasm.exports.editor_add_line(0, 100, 0, 0);
+    const color = Buffer.from('#336699');
+    wasm.exports.editor_fill(50, 50, writeBytes(color), color.length);
+  };
+  const copySquare = () => {
+    const len = wasm.exports.editor_copy_rect(-1, -1, 101, 101);
+    if (len === 0) {
+      throw new Error('Nothing copied');
+    }
+    return new Uint8Array(wasm.exports.memory.buffer, wasm.exports.editor_clipboard_ptr(), len).slice();
+  };
+  const paste = (blob, dx, dy) => {
+    const ptr = wasm.exports.editor_clipboard_reserve(blob.length);
+    new Uint8Array(wasm.exports.memory.buffer, ptr, blob.length).set(blob);
+    return wasm.exports.editor_paste(ptr, blob.length, dx, dy);
+  };
+
+  test('Clipboard round trip', () => {
+    drawSquare();
+    const blob = copySquare();
+    const items = 4 + wasm.exports.editor_fills_count();
+    if (paste(blob, 200, 0) !== items) {
+      throw new Error('Paste count mismatch');
+    }
+    if (wasm.exports.editor_line_count() !== 8) {
+      throw new Error(`Expected 8 lines, got ${wasm.exports.editor_line_count()}`);
+    }
+    wasm.exports.editor_undo();
+    if (wasm.exports.editor_line_count() !== 4) {
+      throw new Error('Paste not undone in one step');
+    }
+    // Pasting into an empty document and copying again gives the same blob
+    wasm.exports.editor_clear();
+    paste(blob, 0, 0);
+    const again = copySquare();
+    if (Buffer.compare(Buffer.from(again), Buffer.from(blob)) !== 0) {
+      throw new Error('Blob changed across paste and copy');
+    }
+    if (wasm.exports.editor_paste_error() !== 0) {
+      throw new Error('Error left after a good paste');
+    }
+  });
+
+  test('Truncated blob is rejected', () => {
+    drawSquare();
+    const blob = copySquare();
+    if (paste(blob.slice(0, blob.length - 3), 0, 0) !== 0) {
+      throw new Error('Truncated blob pasted');
+    }
+    if (wasm.exports.editor_paste_error() !== 3) {
+      throw new Error(`Expected Truncated (3), got ${wasm.exports.editor_paste_error()}`);
+    }
+  });
+
+  test('Bad magic is rejected', () => {
+    drawSquare();
+    const blob = copySquare();
+    blob[0] = 0x58;
+    if (paste(blob, 0, 0) !== 0) {
+      throw new Error('Blob with bad magic pasted');
+    }
+    if (wasm.exports.editor_paste_error() !== 1) {
+      throw new Error(`Expected BadMagic (1), got ${wasm.exports.editor_paste_error()}`);
+    }
+    if (wasm.exports.editor_line_count() !== 4) {
+      throw new Error('Rejected paste changed the document');
+    }
+  });
+  console.log();
+
+  // Test 8: Stroke geometry
+  console.log('✏️  Strokes:');
+  const writeFloats = (values) => {
+    const ptr = wasm.exports.memory.buffer.byteLength - 256 - values.length * 8;
+    new Float32Array(wasm.exports.memory.buffer, ptr, values.length).set(values);
+    return ptr;
+  };
+  // Paths of the strokes buffer: [{ stroke, points: [[x, y], ...] }]
+  const readStrokes = () => {
+    const buf = new Float32Array(
+      wasm.exports.memory.buffer,
+      wasm.exports.editor_strokes_ptr_f32(),
+      wasm.exports.editor_strokes_len_f32()
+    );
+    const paths = [];
+    for (let i = 1, k = 0; k < buf[0]; k++) {
+      const count = buf[i + 1];
+      const points = [];
+      for (let j = 0; j < count; j++) {
+        points.push([buf[i + 2 + j * 2], buf[i + 3 + j * 2]]);
+      }
+      paths.push({ stroke: buf[i], points });
+      i += 2 + count * 2;
+    }
+    return paths;
+  };
+  // Distance from p to the polyline through points
+  const polylineDistance = (p, points) => {
+    let best = Infinity;
+    for (let i = 0; i + 1 < points.length; i++) {
+      const [ax, ay] = points[i];
+      const [bx, by] = points[i + 1];
+      const dx = bx - ax;
+      const dy = by - ay;
+      const len2 = dx * dx + dy * dy;
+      const t = len2 > 0 ? Math.max(0, Math.min(1, ((p[0] - ax) * dx + (p[1] - ay) * dy) / len2)) : 0;
+      best = Math.min(best, Math.hypot(ax + t * dx - p[0], ay + t * dy - p[1]));
+    }
+    return best;
+  };
+
+  test('Stroke simplification stays within tolerance', () => {
+    const input = [];
+    for (let i = 0; i <= 200; i++) {
+      input.push([i * 3, 40 * Math.sin(i / 15) + (i % 3) * 0.8]);
+    }
+    const tolerance = 2;
+    for (const method of [0, 1]) {
+      wasm.exports.editor_clear();
+      if (wasm.exports.editor_set_stroke_simplify(method, tolerance) !== 1) {
+        throw new Error(`Method ${method} rejected`);
+      }
+      const flat = input.flat();
+      if (wasm.exports.editor_add_polyline(writeFloats(flat), input.length) === 0) {
+        throw new Error('Stroke not added');
+      }
+      const paths = readStrokes();
+      if (paths.length !== 1) {
+        throw new Error(`Expected 1 path, got ${paths.length}`);
+      }
+      const out = paths[0].points;
+      if (out.length >= input.length) {
+        throw new Error(`Method ${method} kept every point`);
+      }
+      const first = input[0];
+      const last = input[input.length - 1];
+      if (out[0][0] !== Math.fround(first[0]) || out[0][1] !== Math.fround(first[1])
+        || out[out.length - 1][0] !== Math.fround(last[0]) || out[out.length - 1][1] !== Math.fround(last[1])) {
+        throw new Error(`Method ${method} moved an end point`);
+      }
+      for (const p of input) {
+        const d = polylineDistance(p, out);
+        if (d > tolerance + 1e-3) {
+          throw new Error(`Method ${method}: point ${p} is ${d.toFixed(3)} away`);
+        }
+      }
+      console.log(`  Method ${method}: ${input.length} → ${out.length} points`);
+    }
+    wasm.exports.editor_set_stroke_simplify(0, 1);
+  });
+
+  // Cubic segments of the curves buffer as dense polylines: [{ stroke, points }]
+  const readCurves = (samples) => {
+    const buf = new Float32Array(
+      wasm.exports.memory.buffer,
+      wasm.exports.editor_curves_ptr_f32(),
+      wasm.exports.editor_curves_len_f32()
+    );
+    const curves = [];
+    for (let i = 1, k = 0; k < buf[0]; k++) {
+      const count = buf[i + 1];
+      let [x0, y0] = [buf[i + 2], buf[i + 3]];
+      const points = [[x0, y0]];
+      for (let j = 0; j < count; j++) {
+        const [c1x, c1y, c2x, c2y, x, y] = buf.slice(i + 4 + j * 6, i + 10 + j * 6);
+        for (let n = 1; n <= samples; n++) {
+          const t = n / samples;
+          const u = 1 - t;
+          const [a, b, c, d] = [u * u * u, 3 * u * u * t, 3 * u * t * t, t * t * t];
+          points.push([a * x0 + b * c1x + c * c2x + d * x, a * y0 + b * c1y + c * c2y + d * y]);
+        }
+        [x0, y0] = [x, y];
+      }
+      curves.push({ stroke: buf[i], points });
+      i += 4 + count * 6;
+    }
+    return curves;
+  };
+
+  test('Curve fit stays within its error bound', () => {
+    const input = [];
+    for (let i = 0; i <= 120; i++) {
+      input.push([i * 4, 60 * Math.sin(i / 12) + (i % 2) * 0.5]);
+    }
+    const tolerance = 2;
+    const flatness = 0.25;
+    wasm.exports.editor_clear();
+    const flat = input.flat();
+    if (wasm.exports.editor_add_curve(writeFloats(flat), input.length, 2, tolerance) === 0) {
+      throw new Error('Curve not added');
+    }
+    const curves = readCurves(64);
+    if (curves.length !== 1) {
+      throw new Error(`Expected 1 curve, got ${curves.length}`);
+    }
+    const curve = curves[0].points;
+    const [first, last] = [curve[0], curve[curve.length - 1]];
+    if (first[0] !== 0 || first[1] !== Math.fround(input[0][1])
+      || last[0] !== Math.fround(input[120][0]) || Math.abs(last[1] - input[120][1]) > 1e-3) {
+      throw new Error('Curve does not keep the stroke ends');
+    }
+    const path = readStrokes()[0].points;
+    for (const p of input) {
+      const d = polylineDistance(p, curve);
+      if (d > tolerance + 0.05) {
+        throw new Error(`Point ${p} is ${d.toFixed(3)} from the curve`);
+      }
+      const e = polylineDistance(p, path);
+      if (e > tolerance + flatness + 0.05) {
+        throw new Error(`Point ${p} is ${e.toFixed(3)} from the flattened stroke`);
+      }
+    }
+    console.log(`  ${input.length} points → ${(curve.length - 1) / 64} cubic segments`);
+  });
+
+  test('Erased or cleared curves come back on undo', () => {
+    const input = [];
+    for (let i = 0; i <= 40; i++) {
+      input.push([i * 5, 30 * Math.sin(i / 8)]);
+    }
+    wasm.exports.editor_clear();
+    const flat = input.flat();
+    const stroke = wasm.exports.editor_add_curve(writeFloats(flat), input.length, 2, 1);
+    if (stroke === 0) {
+      throw new Error('Curve not added');
+    }
+    const brush = [-10, 0, 210, 0];
+    if (wasm.exports.editor_erase(writeFloats(brush), 2, 60) === 0) {
+      throw new Error('Erase missed the curve');
+    }
+    if (wasm.exports.editor_export_len_f32() !== 0 || readCurves(1).length !== 0) {
+      throw new Error('Curve survived the erase');
+    }
+    wasm.exports.editor_undo();
+    let curves = readCurves(1);
+    if (curves.length !== 1 || curves[0].stroke !== stroke) {
+      throw new Error('Undo did not bring the curve back');
+    }
+    wasm.exports.editor_clear();
+    wasm.exports.editor_undo();
+    curves = readCurves(1);
+    if (curves.length !== 1 || curves[0].stroke !== stroke) {
+      throw new Error('Undoing clear did not bring the curve back');
+    }
+  });
+  console.log();
+
+  // Test 9: Editing tools
+  console.log('✂️  Editing Tools:');
+  const readExport = () => Array.from(new Float32Array(
+    wasm.exports.memory.buffer,
+    wasm.exports.editor_export_ptr_f32(),
+    wasm.exports.editor_export_len_f32()
+  ));
+  const readLineIds = () => Array.from(new Uint32Array(
+    wasm.exports.memory.buffer,
+    wasm.exports.editor_line_ids_ptr(),
+    wasm.exports.editor_line_ids_len()
+  ));
+
+  test('Extend moves a free end, never a joint', () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(0, 0, 100, 0);
+    wasm.exports.editor_add_line(100, 0, 100, 100);
+    wasm.exports.editor_add_line(150, -50, 150, 200); // Walls
+    wasm.exports.editor_add_line(-50, 150, 200, 150);
+    const before = readExport().join(',');
+    if (wasm.exports.editor_extend_at(100, 0, 0) !== 0) {
+      throw new Error('Extended at a corner');
+    }
+    if (readExport().join(',') !== before) {
+      throw new Error('Corner came apart');
+    }
+    if (wasm.exports.editor_extend_at(100, 100, 0) !== 1) {
+      throw new Error('Free end not extended');
+    }
+    const second = readExport().slice(4, 8).join(',');
+    if (second !== '100,0,100,150') {
+      throw new Error(`Expected 100,0,100,150, got ${second}`);
+    }
+  });
+  test('Lasso rejects a line leaving through its vertices', () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(10, 50, 90, 50); // Runs through the notch
+    wasm.exports.editor_add_line(10, 80, 90, 80);
+    const lasso = [0, 0, 30, 0, 40, 50, 50, 60, 60, 50, 70, 0, 100, 0, 100, 100, 0, 100];
+    const count = wasm.exports.editor_query_polygon(writeFloats(lasso), lasso.length / 2, 0);
+    if (count !== 1) {
+      throw new Error(`Expected 1 line inside, got ${count}`);
+    }
+    const query = new Uint32Array(wasm.exports.memory.buffer, wasm.exports.editor_query_ptr(), 2);
+    if (query[0] !== 1 || query[1] !== readLineIds()[1]) {
+      throw new Error(`Expected the lower line, got ${Array.from(query)}`);
+    }
+    if (wasm.exports.editor_query_polygon(writeFloats(lasso), lasso.length / 2, 1) !== 2) {
+      throw new Error('Partial query should match both lines');
+    }
+  });
+  test('Weld onto a segment splits it and undo restores it', () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(0, 0, 100, 0);
+    const before = readExport().join(',');
+    const [id] = readLineIds();
+    wasm.exports.editor_add_line(50, 40, 50, 2); // End lands 2 px off the middle
+    const lines = readExport();
+    const ids = readLineIds();
+    if (ids.length !== 3) {
+      throw new Error(`Expected 2 pieces and the new line, got ${ids.length} lines`);
+    }
+    if (ids[0] !== id || new Set(ids).size !== 3) {
+      throw new Error(`First piece should keep id ${id}, others be fresh: ${ids}`);
+    }
+    if (lines.join(',') !== '0,0,50,0,50,0,100,0,50,40,50,0') {
+      throw new Error(`Unexpected weld result ${lines.join(',')}`);
+    }
+    wasm.exports.editor_undo();
+    if (readExport().join(',') !== before || readLineIds().join(',') !== String(id)) {
+      throw new Error(`Undo left ${readExport().join(',')}`);
+    }
+  });
+  test('Erase across a line leaves two pieces, undo one line', () => {
+    wasm.exports.editor_clear();
+    wasm.exports.editor_add_line(0, 0, 100, 0);
+    const before = readExport().join(',');
+    const brush = [50, -20, 50, 20];
+    if (wasm.exports.editor_erase(writeFloats(brush), 2, 5) !== 1) {
+      throw new Error('Erase did not touch the line');
+    }
+    const lines = readExport();
+    if (lines.length !== 8) {
+      throw new Error(`Expected 2 pieces, got ${lines.length / 4}`);
+    }
+    const [left, right] = [lines.slice(0, 4), lines.slice(4, 8)];
+    if (left[0] !== 0 || right[2] !== 100 || Math.abs(left[2] - 45) > 0.01 || Math.abs(right[0] - 55) > 0.01) {
+      throw new Error(`Unexpected pieces ${lines.join(',')}`);
+    }
+    wasm.exports.editor_undo();
+    if (readExport().join(',') !== before) {
+      throw new Error(`Undo left ${readExport().join(',')}`);
+    }
+  });
+  test('Transform moves lines, fills and curves together', () => {
+    drawSquare();
+    const input = [];
+    for (let i = 0; i <= 20; i++) {
+      input.push([i * 5, 150 + 20 * Math.sin(i / 4)]);
+    }
+    const flat = input.flat();
+    if (wasm.exports.editor_add_curve(writeFloats(flat), input.length, 2, 1) === 0) {
+      throw new Error('Curve not added');
+    }
+    if (wasm.exports.editor_fills_count() !== 1 || wasm.exports.editor_fill_hit_test(50, 50) !== 0) {
+      throw new Error('Square not filled');
+    }
+    const lines = readExport();
+    const curve = readCurves(1)[0].points;
+    wasm.exports.editor_select_rect(-10, -10, 110, 200, 0);
+    if (wasm.exports.editor_translate_selection(200, 0) !== lines.length / 4 + 1) {
+      throw new Error('Not every line and the fill were transformed');
+    }
+    const moved = readExport();
+    if (moved.some((v, i) => Math.abs(v - lines[i] - (i % 2 === 0 ? 200 : 0)) > 1e-3)) {
+      throw new Error('Lines did not move by (200, 0)');
+    }
+    if (wasm.exports.editor_fill_hit_test(250, 50) !== 0 || wasm.exports.editor_fill_hit_test(50, 50) !== -1) {
+      throw new Error('Fill did not move with its lines');
+    }
+    const movedCurve = readCurves(1)[0].points;
+    if (movedCurve.some(([x, y], i) => Math.abs(x - curve[i][0] - 200) > 1e-3 || y !== curve[i][1])) {
+      throw new Error('Curve did not move with its stroke');
+    }
+    wasm.exports.editor_undo();
+    if (readExport().join(',') !== lines.join(',')) {
+      throw new Error('Undo did not restore the lines');
+    }
+    if (wasm.exports.editor_fill_hit_test(50, 50) !== 0 || wasm.exports.editor_fill_hit_test(250, 50) !== -1) {
+      throw new Error('Undo did not restore the fill');
+    }
+    if (readCurves(1)[0].points.join(',') !== curve.join(',')) {
+      throw new Error('Undo did not restore the curve');
+    }
+  });
+  console.log();
+
   // Summary
   console.log('━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━');
   const total = passCount + failCount;
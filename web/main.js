@@ -18,11 +18,24 @@ async function main() {
     const linesGroup = document.getElementById('lines');
     const fillsGroup = document.getElementById('fills');
     const preview = document.getElementById('preview');
+    const polylinePreview = document.getElementById('polylinePreview');
+    const modeBtn = document.getElementById('modeBtn');
     const undoBtn = document.getElementById('undoBtn');
     const clearBtn = document.getElementById('clearBtn');
     const cleanBtn = document.getElementById('cleanBtn');
+    const trimBtn = document.getElementById('trimBtn');
+    const scissorsBtn = document.getElementById('scissorsBtn');
+    const extendBtn = document.getElementById('extendBtn');
+    const eraserBtn = document.getElementById('eraserBtn');
+    const selectBtn = document.getElementById('selectBtn');
+    const selectionRect = document.getElementById('selectionRect');
+    const snapGridBtn = document.getElementById('snapGridBtn');
+    const snapIndicator = document.getElementById('snapIndicator');
+    const weldLayer = document.getElementById('weldLayer');
     const fillBtn = document.getElementById('fillBtn');
     const fillColor = document.getElementById('fillColor');
+    const addSwatchBtn = document.getElementById('addSwatchBtn');
+    const swatchesGroup = document.getElementById('swatches');
     const addFrameBtn = document.getElementById('addFrameBtn');
     const exportPngBtn = document.getElementById('exportPngBtn');
     const debugBtn = document.getElementById('debugBtn');
@@ -39,11 +52,39 @@ async function main() {
     const fillTraceLayer = document.getElementById('fillTraceLayer');
     const graphDebugLayer = document.getElementById('graphDebugLayer');
     const fillDebugLayer = document.getElementById('fillDebugLayer');
+    const leakLayer = document.getElementById('leakLayer');
+    const overhangPreviewLayer = document.getElementById('overhangPreviewLayer');
+    const fillPreviewLayer = document.getElementById('fillPreviewLayer');
+    const gradientHandleLayer = document.getElementById('gradientHandleLayer');
+    const eraserPath = document.getElementById('eraserPath');
     const viewBox = canvas.viewBox.baseVal;
 
     let wasm = null;
     let dragging = false;
     let fillMode = false;
+    let toolMode = 'freehand'; // 'freehand' draws one line per drag, 'polyline' one stroke of chained segments, 'curve' one smooth stroke
+    let polylinePoints = null; // Anchors of the polyline stroke in progress
+    const POLYLINE_MIN_SEG = 8; // Pointer travel (px) before a new anchor is committed
+    const POLYLINE_COLLINEAR_COS = 0.993; // cos(7°): a straighter turn moves the last anchor instead
+    const CURVE_METHOD = 2; // editor_add_curve: 0 = Chaikin, 1 = Catmull-Rom, 2 = Bézier fit
+    const CURVE_TOLERANCE = 2; // Max distance (px) of the curve from the drawn points
+    let strokeLineIds = new Map(); // Stroke id -> ids of its segments, from the last render
+    let lineTool = null; // 'cut' deletes the line piece between crossings, 'extend' lengthens a line end, 'erase' brushes geometry away, 'select' picks geometry to transform
+    let selectStart = null; // Corner of the selection rectangle being dragged
+    let erasePoints = null; // Brush path of the eraser stroke in progress
+    const ERASER_RADIUS = 8;
+    const CLIPBOARD_KEY = 'rustroke.clipboard'; // Base64 clipboard blob shared between documents
+    const PASTE_OFFSET = 10;
+    const SIMPLIFY_TOLERANCE = 2; // Max drift (px) of the s / S simplify keys
+    const SNAP_POINTS = 1 | 2 | 4; // Endpoints, intersections, midpoints (editor_snap flags)
+    const SNAP_GRID = 8;
+    const SNAP_ANGLE = 16;
+    const SNAP_KIND_NAMES = {1: 'endpoint', 2: 'intersection', 4: 'midpoint', 8: 'grid', 16: 'angle'};
+    let snapToGrid = false;
+    let hoverPoint = null; // Last pointer position over the canvas (fill shortcuts)
+    let activeSwatch = 0; // Palette swatch new fills reference (0 = plain fill color)
+    let fillPaints = []; // Per fill {kind, hex, bounds, handles} from the last render
+    let handleDrag = null; // {fill, handle} while a gradient handle is dragged
     let debugMode = false;
     let graphDebugMode = false;
     let showLines = true;
@@ -189,6 +230,17 @@ async function main() {
       eventRing.add(`ERR Promise: ${evt.reason?.message?.slice(0, 50) || 'unknown'}`);
     });
 
+    // Status codes returned by editor_fill / editor_fill_debug_at
+    const FILL_STATUS_TEXT = [
+      'filled',
+      'no geometry',
+      'no boundary near click',
+      'dead end',
+      'step limit',
+      'premature cycle',
+      'no closed region around click',
+    ];
+
     /**
      * Central action dispatcher
      * Routes all actions through one point for recording/playback
@@ -224,6 +276,7 @@ async function main() {
               animateLineStroke(x1, y1, x2, y2, () => {
                 wasm.editor_add_line(x1, y1, x2, y2);
                 renderFromWasm();
+                renderWelds();
                 resolve();
               });
             });
@@ -231,6 +284,7 @@ async function main() {
             // User action: add immediately
             wasm.editor_add_line(x1, y1, x2, y2);
             renderFromWasm();
+            renderWelds();
           }
           break;
         }
@@ -258,21 +312,22 @@ async function main() {
           // Force browser to paint so debug line shows "fill:start" BEFORE freeze
           await new Promise(resolve => requestAnimationFrame(resolve));
           
-          const colorBytes = new TextEncoder().encode(color);
-          const colorPtr = wasm.memory.buffer.byteLength - 256;
-          const colorView = new Uint8Array(wasm.memory.buffer, colorPtr, colorBytes.length);
-          colorView.set(colorBytes);
-          wasm.editor_set_fill_color(colorPtr, colorBytes.length);
-          
-          // Check fills count before
-          const fillsBefore = wasm.editor_fills_count();
+          // With an active swatch the fill references it instead of the plain color
+          if (activeSwatch === 0) {
+            const colorBytes = new TextEncoder().encode(color);
+            const colorPtr = wasm.memory.buffer.byteLength - 256;
+            const colorView = new Uint8Array(wasm.memory.buffer, colorPtr, colorBytes.length);
+            colorView.set(colorBytes);
+            wasm.editor_set_fill_color(colorPtr, colorBytes.length);
+          }
           
           const fillStartTime = performance.now();
           eventRing.add(`fill:start x=${x.toFixed(0)} y=${y.toFixed(0)}`);
+          let fillStatus = 0;
           
           try {
             // Use fill_debug_at which uses the fill graph system and creates the fill
-            wasm.editor_fill_debug_at(x, y);
+            fillStatus = wasm.editor_fill_debug_at(x, y);
             
             const fillDuration = performance.now() - fillStartTime;
             metrics.op = 'fill:end';
@@ -287,20 +342,25 @@ async function main() {
             throw err;
           }
           
-          // Check if fill was created
-          const fillsAfter = wasm.editor_fills_count();
-          const fillCreated = fillsAfter > fillsBefore;
+          // Refilling an already filled face recolors it, so the count may not change
+          const fillCreated = fillStatus === 0;
           
           // If no fill was created, turn off fill mode
           if (!fillCreated && fillMode) {
             fillMode = false;
             fillBtn.classList.remove('active');
             canvas.style.cursor = 'default';
-            eventRing.add('fill:abort no boundary');
+            clearFillOverlays();
+            eventRing.add(`fill:abort ${FILL_STATUS_TEXT[fillStatus] || 'no boundary'}`);
           }
           
           renderFromWasm();
           
+          // Show where the outline leaks so the user can close the gap
+          if (!fillCreated) {
+            renderLeakMarkers();
+          }
+          
           // Update debug visuals if debug mode is on
           if (debugMode) {
             renderFillTrace();
@@ -308,6 +368,59 @@ async function main() {
           }
           break;
         }
+        case "DeleteFill": {
+          const {x, y} = action.data;
+          if (typeof wasm.editor_fill_hit_test !== 'function') break;
+          const idx = wasm.editor_fill_hit_test(x, y);
+          if (idx >= 0) {
+            wasm.editor_delete_fill(idx);
+            eventRing.add(`fill:delete #${idx}`);
+            renderFromWasm();
+          }
+          break;
+        }
+        case "MoveFill": {
+          const {x, y, direction} = action.data;
+          if (typeof wasm.editor_fill_hit_test !== 'function') break;
+          const idx = wasm.editor_fill_hit_test(x, y);
+          if (idx >= 0) {
+            const moved = wasm.editor_move_fill(idx, direction);
+            eventRing.add(`fill:move #${idx} -> ${moved}`);
+            renderFromWasm();
+          }
+          break;
+        }
+        case "SetFillPaint": {
+          const {index, kind, x1, y1, x2, y2, stops} = action.data;
+          if (typeof wasm.editor_set_fill_linear !== 'function') break;
+          if (kind === 0) {
+            wasm.editor_set_fill_solid(index);
+          } else {
+            const stopsStr = encodeStringToWasm(stops);
+            const ok = kind === 1
+              ? wasm.editor_set_fill_linear(index, x1, y1, x2, y2, stopsStr.ptr, stopsStr.len)
+              : wasm.editor_set_fill_radial(index, x1, y1, Math.hypot(x2 - x1, y2 - y1), stopsStr.ptr, stopsStr.len);
+            if (!ok) eventRing.add(`fill:paint error=${wasm.editor_color_error()}`);
+          }
+          renderFromWasm();
+          break;
+        }
+        case "SetFillPattern": {
+          const {x, y, kind, angle, spacing} = action.data;
+          if (typeof wasm.editor_set_fill_pattern !== 'function') break;
+          const index = wasm.editor_fill_hit_test(x, y);
+          if (index >= 0) {
+            wasm.editor_set_fill_pattern(index, kind, angle, spacing);
+            renderFromWasm();
+          }
+          break;
+        }
+        case "MoveGradientHandle": {
+          const {index, handle, x, y} = action.data;
+          wasm.editor_move_gradient_handle(index, handle, x, y);
+          renderFromWasm();
+          break;
+        }
         case "Undo": {
           wasm.editor_undo();
           renderFromWasm();
@@ -337,6 +450,132 @@ async function main() {
           renderFromWasm();
           break;
         }
+        case "ExtendAt": {
+          const {x, y} = action.data;
+          if (typeof wasm.editor_extend_at !== 'function') break;
+          // Max distance 0 uses the engine default
+          const extended = wasm.editor_extend_at(x, y, 0);
+          eventRing.add(`extend ${extended ? 'ok' : 'miss'} x=${x.toFixed(0)} y=${y.toFixed(0)}`);
+          renderFromWasm();
+          break;
+        }
+        case "AddPolyline": {
+          const {points} = action.data;
+          if (typeof wasm.editor_add_polyline !== 'function' || points.length < 2) break;
+          // Flat (x, y) f32 buffer below the string scratch area
+          const ptr = wasm.memory.buffer.byteLength - 256 - points.length * 8;
+          const view = new Float32Array(wasm.memory.buffer, ptr, points.length * 2);
+          points.forEach((p, i) => { view[i * 2] = p.x; view[i * 2 + 1] = p.y; });
+          const stroke = wasm.editor_add_polyline(ptr, points.length);
+          eventRing.add(`polyline points=${points.length} stroke=${stroke}`);
+          renderFromWasm();
+          break;
+        }
+        case "AddCurve": {
+          const {points, method, tolerance} = action.data;
+          if (typeof wasm.editor_add_curve !== 'function' || points.length < 2) break;
+          const ptr = wasm.memory.buffer.byteLength - 256 - points.length * 8;
+          const view = new Float32Array(wasm.memory.buffer, ptr, points.length * 2);
+          points.forEach((p, i) => { view[i * 2] = p.x; view[i * 2 + 1] = p.y; });
+          const stroke = wasm.editor_add_curve(ptr, points.length, method, tolerance);
+          eventRing.add(`curve points=${points.length} method=${method} stroke=${stroke}`);
+          renderFromWasm();
+          break;
+        }
+        case "Erase": {
+          const {points, radius} = action.data;
+          if (typeof wasm.editor_erase !== 'function' || points.length === 0) break;
+          // Path goes below the string scratch area as (x, y) f32 pairs
+          const ptr = wasm.memory.buffer.byteLength - 256 - points.length * 8;
+          const view = new Float32Array(wasm.memory.buffer, ptr, points.length * 2);
+          points.forEach((p, i) => { view[i * 2] = p.x; view[i * 2 + 1] = p.y; });
+          const touched = wasm.editor_erase(ptr, points.length, radius);
+          eventRing.add(`erase points=${points.length} lines=${touched}`);
+          renderFromWasm();
+          break;
+        }
+        case "Select": {
+          const {x, y, add} = action.data;
+          if (typeof wasm.editor_select_at !== 'function') break;
+          wasm.editor_select_at(x, y, add ? 1 : 0);
+          renderSelection();
+          break;
+        }
+        case "SelectRect": {
+          // partial: also take what the box only crosses or touches
+          const {x1, y1, x2, y2, add, partial} = action.data;
+          if (partial && typeof wasm.editor_query_rect === 'function') {
+            wasm.editor_query_rect(x1, y1, x2, y2, 1);
+            wasm.editor_select_query(add ? 1 : 0);
+          } else if (typeof wasm.editor_select_rect === 'function') {
+            wasm.editor_select_rect(x1, y1, x2, y2, add ? 1 : 0);
+          }
+          renderSelection();
+          break;
+        }
+        case "TransformSelection": {
+          // op: translate {dx, dy} | rotate {angle} | scale {factor} | flip {horizontal}; pivot is the selection center
+          const {op} = action.data;
+          if (typeof wasm.editor_translate_selection !== 'function') break;
+          const c = selectionCenter();
+          if (!c) break;
+          let moved = 0;
+          if (op === 'translate') moved = wasm.editor_translate_selection(action.data.dx, action.data.dy);
+          if (op === 'rotate') moved = wasm.editor_rotate_selection(action.data.angle, c.x, c.y);
+          if (op === 'scale') moved = wasm.editor_scale_selection(action.data.factor, action.data.factor, c.x, c.y);
+          if (op === 'flip') moved = wasm.editor_flip_selection(action.data.horizontal ? 1 : 0, c.x, c.y);
+          eventRing.add(`select:${op} moved=${moved}`);
+          renderFromWasm();
+          break;
+        }
+        case "SimplifySelection": {
+          // method: 0 = Ramer-Douglas-Peucker, 1 = Visvalingam
+          if (typeof wasm.editor_simplify_selection !== 'function') break;
+          const {tolerance, method} = action.data;
+          const removed = wasm.editor_simplify_selection(tolerance, method);
+          eventRing.add(`select:simplify tolerance=${tolerance} method=${method} removed=${removed}`);
+          renderFromWasm();
+          break;
+        }
+        case "Copy": {
+          if (typeof wasm.editor_copy_selection !== 'function') break;
+          const len = wasm.editor_copy_selection();
+          const bytes = new Uint8Array(wasm.memory.buffer, wasm.editor_clipboard_ptr(), len);
+          // localStorage makes the blob available to other documents / tabs
+          let binary = '';
+          for (const b of bytes) binary += String.fromCharCode(b);
+          localStorage.setItem(CLIPBOARD_KEY, btoa(binary));
+          eventRing.add(`copy bytes=${len}`);
+          break;
+        }
+        case "Paste": {
+          const {blob, dx, dy} = action.data;
+          if (typeof wasm.editor_paste !== 'function' || !blob) break;
+          const bytes = Uint8Array.from(atob(blob), c => c.charCodeAt(0));
+          const ptr = wasm.editor_clipboard_reserve(bytes.length);
+          new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes);
+          const pasted = wasm.editor_paste(ptr, bytes.length, dx, dy);
+          eventRing.add(`paste items=${pasted}`);
+          renderFromWasm();
+          break;
+        }
+        case "TrimAt": {
+          const {x, y} = action.data;
+          if (typeof wasm.editor_trim_at !== 'function') break;
+          const cut = wasm.editor_trim_at(x, y);
+          eventRing.add(`cut ${cut ? 'ok' : 'miss'} x=${x.toFixed(0)} y=${y.toFixed(0)}`);
+          renderFromWasm();
+          break;
+        }
+        case "Trim": {
+          if (typeof wasm.editor_trim_inside_fills !== 'function') break;
+          // Count 0 trims against every fill
+          const removed = wasm.editor_trim_inside_fills(0, 0);
+          console.log(`[Trim] removed=${removed}`);
+          eventRing.add(`trim removed=${removed}`);
+          renderFromWasm();
+          break;
+        }
         case "SetFillColor": {
           const {color} = action.data;
           fillColor.value = color;
@@ -345,6 +584,38 @@ async function main() {
           const colorView = new Uint8Array(wasm.memory.buffer, colorPtr, colorBytes.length);
           colorView.set(colorBytes);
           wasm.editor_set_fill_color(colorPtr, colorBytes.length);
+          // A plain color replaces the active swatch
+          activeSwatch = 0;
+          renderSwatches();
+          break;
+        }
+        case "AddSwatch": {
+          const {name, color} = action.data;
+          if (typeof wasm.editor_add_swatch !== 'function') break;
+          const nameStr = encodeStringToWasm(name);
+          const colorStr = encodeStringToWasm(color, 128);
+          const id = wasm.editor_add_swatch(nameStr.ptr, nameStr.len, colorStr.ptr, colorStr.len);
+          if (id > 0) {
+            activeSwatch = id;
+            wasm.editor_set_fill_swatch(id);
+          }
+          renderSwatches();
+          break;
+        }
+        case "SelectSwatch": {
+          const {id} = action.data;
+          if (typeof wasm.editor_set_fill_swatch !== 'function') break;
+          if (wasm.editor_set_fill_swatch(id)) {
+            activeSwatch = id;
+          }
+          renderSwatches();
+          break;
+        }
+        case "SetSwatchColor": {
+          const {id, color} = action.data;
+          const colorStr = encodeStringToWasm(color);
+          wasm.editor_set_swatch_color(id, colorStr.ptr, colorStr.len);
+          renderFromWasm();
           break;
         }
         case "ToggleShowLines": {
@@ -461,14 +732,32 @@ async function main() {
         const debugLayerClone = svgClone.querySelector('#debugLayer');
         const graphDebugLayerClone = svgClone.querySelector('#graphDebugLayer');
         const previewClone = svgClone.querySelector('#preview');
+        const polylinePreviewClone = svgClone.querySelector('#polylinePreview');
         const fillTraceLayerClone = svgClone.querySelector('#fillTraceLayer');
         const fillDebugLayerClone = svgClone.querySelector('#fillDebugLayer');
+        const leakLayerClone = svgClone.querySelector('#leakLayer');
+        const overhangPreviewLayerClone = svgClone.querySelector('#overhangPreviewLayer');
+        const fillPreviewLayerClone = svgClone.querySelector('#fillPreviewLayer');
+        const gradientHandleLayerClone = svgClone.querySelector('#gradientHandleLayer');
+        const eraserPathClone = svgClone.querySelector('#eraserPath');
+        const selectionRectClone = svgClone.querySelector('#selectionRect');
+        const snapIndicatorClone = svgClone.querySelector('#snapIndicator');
+        const weldLayerClone = svgClone.querySelector('#weldLayer');
         
         if (debugLayerClone) debugLayerClone.remove();
         if (graphDebugLayerClone) graphDebugLayerClone.remove();
         if (previewClone) previewClone.remove();
+        if (polylinePreviewClone) polylinePreviewClone.remove();
         if (fillTraceLayerClone) fillTraceLayerClone.remove();
         if (fillDebugLayerClone) fillDebugLayerClone.remove();
+        if (leakLayerClone) leakLayerClone.remove();
+        if (overhangPreviewLayerClone) overhangPreviewLayerClone.remove();
+        if (fillPreviewLayerClone) fillPreviewLayerClone.remove();
+        if (gradientHandleLayerClone) gradientHandleLayerClone.remove();
+        if (eraserPathClone) eraserPathClone.remove();
+        if (selectionRectClone) selectionRectClone.remove();
+        if (snapIndicatorClone) snapIndicatorClone.remove();
+        if (weldLayerClone) weldLayerClone.remove();
         
         // Remove lines if they are hidden
         if (!showLines) {
@@ -818,6 +1107,8 @@ async function main() {
      */
     function renderFromWasm() {
       if (!wasm) return;
+      // Leak markers describe the geometry at the time of a failed fill only
+      leakLayer.replaceChildren();
       const len = wasm.editor_export_len_f32();
       const ptr = wasm.editor_export_ptr_f32();
       
@@ -828,13 +1119,24 @@ async function main() {
           lineCounter.textContent = 'Lines: 0';
         } else {
           const arr = new Float32Array(wasm.memory.buffer, ptr, len);
-          const fragments = [];
+          const ids = readIds('editor_line_ids_ptr', 'editor_line_ids_len');
+          const strokes = readIds('editor_line_strokes_ptr', 'editor_line_strokes_len');
+          const fragments = renderStrokes();
+          strokeLineIds = new Map();
           for (let i = 0; i < arr.length; i += 4) {
+            // Polyline segments are drawn by their stroke path
+            const stroke = strokes ? strokes[i / 4] : 0;
+            if (stroke && ids) {
+              if (!strokeLineIds.has(stroke)) strokeLineIds.set(stroke, []);
+              strokeLineIds.get(stroke).push(ids[i / 4]);
+            }
+            if (stroke) continue;
             const line = document.createElementNS('http://www.w3.org/2000/svg', 'line');
             line.setAttribute('x1', arr[i + 0]);
             line.setAttribute('y1', arr[i + 1]);
             line.setAttribute('x2', arr[i + 2]);
             line.setAttribute('y2', arr[i + 3]);
+            if (ids) line.dataset.id = ids[i / 4];
             fragments.push(line);
           }
           linesGroup.replaceChildren(...fragments);
@@ -854,8 +1156,13 @@ async function main() {
       const fillsLen = wasm.editor_export_fills_len();
       const fillsPtr = wasm.editor_export_fills_ptr();
       if (fillsPtr && fillsLen > 0) {
+        // Record: [point_count, r, g, b, a, paint_kind, param_count, params..., (x, y) * point_count]
         const fillsArr = new Float32Array(wasm.memory.buffer, fillsPtr, fillsLen);
         const fillFragments = [];
+        const patternLines = readPatternLines();
+        const staleFills = readStaleFills();
+        const fillIds = readIds('editor_fill_ids_ptr', 'editor_fill_ids_len');
+        fillPaints = [];
         let i = 0;
         while (i < fillsArr.length) {
           const pointCount = fillsArr[i];
@@ -863,23 +1170,55 @@ async function main() {
           const g = Math.round(fillsArr[i + 2] * 255);
           const b = Math.round(fillsArr[i + 3] * 255);
           const a = fillsArr[i + 4];
-          i += 5;
+          const kind = fillsArr[i + 5];
+          const paramCount = fillsArr[i + 6];
+          const params = fillsArr.slice(i + 7, i + 7 + paramCount);
+          i += 7 + paramCount;
 
           const polygon = document.createElementNS('http://www.w3.org/2000/svg', 'polygon');
           let points = '';
+          const bounds = {minX: Infinity, minY: Infinity, maxX: -Infinity, maxY: -Infinity};
           for (let j = 0; j < pointCount && i < fillsArr.length; j++) {
             const x = fillsArr[i];
             const y = fillsArr[i + 1];
             points += `${x},${y} `;
+            bounds.minX = Math.min(bounds.minX, x);
+            bounds.minY = Math.min(bounds.minY, y);
+            bounds.maxX = Math.max(bounds.maxX, x);
+            bounds.maxY = Math.max(bounds.maxY, y);
             i += 2;
           }
           polygon.setAttribute('points', points);
-          polygon.setAttribute('fill', `rgba(${r},${g},${b},${a})`);
-          fillFragments.push(polygon);
+          const fillIndex = fillPaints.length;
+          // Outline lost its boundary (erased): keep it visible but marked
+          polygon.classList.toggle('stale', staleFills.has(fillIndex));
+          if (fillIds) polygon.dataset.id = fillIds[fillIndex];
+          if (kind === 3) {
+            // Pattern: outline stays empty, the engine-generated lines carry the color
+            polygon.setAttribute('fill', 'none');
+            fillFragments.push(polygon);
+            const stroke = `rgba(${r},${g},${b},${a})`;
+            for (const seg of patternLines.get(fillIndex) || []) {
+              seg.setAttribute('stroke', stroke);
+              fillFragments.push(seg);
+            }
+          } else if (kind > 0) {
+            const gradient = createGradient(`fillPaint${fillIndex}`, kind, params);
+            fillFragments.push(gradient);
+            polygon.setAttribute('fill', `url(#fillPaint${fillIndex})`);
+            fillFragments.push(polygon);
+          } else {
+            polygon.setAttribute('fill', `rgba(${r},${g},${b},${a})`);
+            fillFragments.push(polygon);
+          }
+
+          const hex = '#' + [r, g, b].map(c => c.toString(16).padStart(2, '0')).join('');
+          fillPaints.push({kind, hex, bounds, handles: gradientHandles(kind, params)});
         }
         fillsGroup.replaceChildren(...fillFragments);
       } else {
         fillsGroup.replaceChildren();
+        fillPaints = [];
       }
 
       // Update intersection dots if debug is on
@@ -892,6 +1231,308 @@ async function main() {
       } else {
         graphDebugLayer.replaceChildren();
       }
+
+      renderSwatches();
+      renderSelection();
+    }
+
+    /**
+     * Build an SVG gradient from fill paint params
+     * linear: [x1, y1, x2, y2, stop_count, (offset, r, g, b, a) * stop_count]
+     * radial: [cx, cy, r, stop_count, (offset, r, g, b, a) * stop_count]
+     */
+    function createGradient(id, kind, params) {
+      const svgNS = 'http://www.w3.org/2000/svg';
+      const linear = kind === 1;
+      const gradient = document.createElementNS(svgNS, linear ? 'linearGradient' : 'radialGradient');
+      gradient.setAttribute('id', id);
+      gradient.setAttribute('gradientUnits', 'userSpaceOnUse');
+      let k;
+      if (linear) {
+        gradient.setAttribute('x1', params[0]);
+        gradient.setAttribute('y1', params[1]);
+        gradient.setAttribute('x2', params[2]);
+        gradient.setAttribute('y2', params[3]);
+        k = 4;
+      } else {
+        gradient.setAttribute('cx', params[0]);
+        gradient.setAttribute('cy', params[1]);
+        gradient.setAttribute('r', params[2]);
+        k = 3;
+      }
+      const stopCount = params[k];
+      for (let s = 0; s < stopCount; s++) {
+        const base = k + 1 + s * 5;
+        const [offset, r, g, b, a] = params.slice(base, base + 5);
+        const stop = document.createElementNS(svgNS, 'stop');
+        stop.setAttribute('offset', offset);
+        stop.setAttribute('stop-color', `rgb(${Math.round(r * 255)},${Math.round(g * 255)},${Math.round(b * 255)})`);
+        stop.setAttribute('stop-opacity', a);
+        gradient.appendChild(stop);
+      }
+      return gradient;
+    }
+
+    /**
+     * Pattern fill geometry grouped by fill index
+     * Buffer format: [count, (fill_idx, x1, y1, x2, y2) * count]; dots have zero length
+     */
+    function readPatternLines() {
+      const byFill = new Map();
+      if (typeof wasm.editor_pattern_lines_ptr_f32 !== 'function') return byFill;
+      const len = wasm.editor_pattern_lines_len_f32();
+      const ptr = wasm.editor_pattern_lines_ptr_f32();
+      if (!ptr || len < 1) return byFill;
+      const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+      const count = Math.floor(arr[0]);
+      for (let i = 0; i < count; i++) {
+        const base = 1 + i * 5;
+        const seg = document.createElementNS('http://www.w3.org/2000/svg', 'line');
+        seg.setAttribute('x1', arr[base + 1]);
+        seg.setAttribute('y1', arr[base + 2]);
+        seg.setAttribute('x2', arr[base + 3]);
+        seg.setAttribute('y2', arr[base + 4]);
+        seg.setAttribute('stroke-width', 1.5);
+        seg.setAttribute('stroke-linecap', 'round');
+        const fill = arr[base];
+        if (!byFill.has(fill)) byFill.set(fill, []);
+        byFill.get(fill).push(seg);
+      }
+      return byFill;
+    }
+
+    // Stable ids exported next to lines / fills (same order), null with an older engine
+    function readIds(ptrFn, lenFn) {
+      if (typeof wasm[ptrFn] !== 'function') return null;
+      const ptr = wasm[ptrFn]();
+      const len = wasm[lenFn]();
+      return ptr && len > 0 ? new Uint32Array(wasm.memory.buffer, ptr, len) : null;
+    }
+
+    // Selected line / fill ids ([line_count, ids..., fill_count, ids...]), empty sets with an older engine
+    function readSelection() {
+      const selection = {lines: new Set(), fills: new Set()};
+      if (typeof wasm.editor_selection_ptr !== 'function') return selection;
+      const ptr = wasm.editor_selection_ptr();
+      const len = wasm.editor_selection_len();
+      if (!ptr || len < 2) return selection;
+      const arr = new Uint32Array(wasm.memory.buffer, ptr, len);
+      const lineCount = arr[0];
+      arr.subarray(1, 1 + lineCount).forEach(id => selection.lines.add(id));
+      arr.subarray(2 + lineCount, 2 + lineCount + arr[1 + lineCount]).forEach(id => selection.fills.add(id));
+      return selection;
+    }
+
+    function renderSelection() {
+      const {lines, fills} = readSelection();
+      for (const el of linesGroup.querySelectorAll('line')) {
+        el.classList.toggle('selected', lines.has(Number(el.dataset.id)));
+      }
+      for (const el of linesGroup.querySelectorAll('polyline, path')) {
+        const strokeIds = strokeLineIds.get(Number(el.dataset.stroke)) || [];
+        el.classList.toggle('selected', strokeIds.some(id => lines.has(id)));
+      }
+      for (const el of fillsGroup.querySelectorAll('polygon')) {
+        el.classList.toggle('selected', fills.has(Number(el.dataset.id)));
+      }
+    }
+
+    // Center of the selected elements' bounding box, the pivot for rotate / scale / flip
+    function selectionCenter() {
+      const selected = canvas.querySelectorAll('#lines line.selected, #lines polyline.selected, #lines path.selected, #fills polygon.selected');
+      if (selected.length === 0) return null;
+      let minX = Infinity, minY = Infinity, maxX = -Infinity, maxY = -Infinity;
+      for (const el of selected) {
+        const box = el.getBBox();
+        minX = Math.min(minX, box.x);
+        minY = Math.min(minY, box.y);
+        maxX = Math.max(maxX, box.x + box.width);
+        maxY = Math.max(maxY, box.y + box.height);
+      }
+      return {x: (minX + maxX) / 2, y: (minY + maxY) / 2};
+    }
+
+    /**
+     * One <polyline> per unbroken polyline stroke run, or a cubic <path> for an intact curve
+     * Buffer: [count, (stroke_id, point_count, (x, y) * point_count) * count]
+     * @returns {SVGElement[]} empty with an older engine
+     */
+    function renderStrokes() {
+      if (typeof wasm.editor_strokes_ptr_f32 !== 'function') return [];
+      const len = wasm.editor_strokes_len_f32();
+      if (len < 1) return [];
+      const buf = new Float32Array(wasm.memory.buffer, wasm.editor_strokes_ptr_f32(), len);
+      const curves = readCurves();
+      const paths = [];
+      let i = 1;
+      for (let k = 0; k < buf[0]; k++) {
+        const stroke = buf[i];
+        const pointCount = buf[i + 1];
+        i += 2;
+        const points = [];
+        for (let j = 0; j < pointCount; j++, i += 2) {
+          points.push(`${buf[i]},${buf[i + 1]}`);
+        }
+        if (curves.has(stroke)) {
+          const path = document.createElementNS('http://www.w3.org/2000/svg', 'path');
+          path.setAttribute('d', curves.get(stroke));
+          path.dataset.stroke = stroke;
+          paths.push(path);
+          continue;
+        }
+        const polyline = document.createElementNS('http://www.w3.org/2000/svg', 'polyline');
+        polyline.setAttribute('points', points.join(' '));
+        polyline.dataset.stroke = stroke;
+        paths.push(polyline);
+      }
+      return paths;
+    }
+
+    /**
+     * Path data of intact curve strokes by stroke id
+     * Buffer: [count, (stroke_id, segment_count, x0, y0, (c1x, c1y, c2x, c2y, x, y) * segment_count) * count]
+     * @returns {Map<number, string>}
+     */
+    function readCurves() {
+      const curves = new Map();
+      if (typeof wasm.editor_curves_ptr_f32 !== 'function') return curves;
+      const len = wasm.editor_curves_len_f32();
+      if (len < 1) return curves;
+      const buf = new Float32Array(wasm.memory.buffer, wasm.editor_curves_ptr_f32(), len);
+      let i = 1;
+      for (let k = 0; k < buf[0]; k++) {
+        const stroke = buf[i];
+        const segmentCount = buf[i + 1];
+        let d = `M ${buf[i + 2]} ${buf[i + 3]}`;
+        i += 4;
+        for (let j = 0; j < segmentCount; j++, i += 6) {
+          d += ` C ${buf[i]} ${buf[i + 1]} ${buf[i + 2]} ${buf[i + 3]} ${buf[i + 4]} ${buf[i + 5]}`;
+        }
+        curves.set(stroke, d);
+      }
+      return curves;
+    }
+
+    // Set of fill indices the engine flagged stale ([count, fill_idx * count])
+    function readStaleFills() {
+      const stale = new Set();
+      if (typeof wasm.editor_stale_fills_ptr_f32 !== 'function') return stale;
+      const len = wasm.editor_stale_fills_len_f32();
+      const ptr = wasm.editor_stale_fills_ptr_f32();
+      if (!ptr || len < 1) return stale;
+      const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+      for (let i = 0; i < arr[0]; i++) stale.add(arr[1 + i]);
+      return stale;
+    }
+
+    /**
+     * Drag handles for a gradient: linear start/end, radial center and rim
+     */
+    function gradientHandles(kind, params) {
+      if (kind === 1) return [{x: params[0], y: params[1]}, {x: params[2], y: params[3]}];
+      if (kind === 2) return [{x: params[0], y: params[1]}, {x: params[0] + params[2], y: params[1]}];
+      return [];
+    }
+
+    function clearFillOverlays() {
+      fillPreviewLayer.replaceChildren();
+      gradientHandleLayer.replaceChildren();
+    }
+
+    /**
+     * Show the handles of the gradient fill under (x, y); `moved` overrides one handle while dragging
+     */
+    function renderGradientHandles(x, y, moved = null) {
+      const index = moved ? moved.fill : wasm.editor_fill_hit_test(x, y);
+      const paint = fillPaints[index];
+      if (!paint || paint.kind === 0) {
+        gradientHandleLayer.replaceChildren();
+        return null;
+      }
+      const handles = paint.handles.map((h, k) => (moved && moved.handle === k ? {x, y} : h));
+      const svgNS = 'http://www.w3.org/2000/svg';
+      const axis = document.createElementNS(svgNS, 'line');
+      axis.setAttribute('x1', handles[0].x);
+      axis.setAttribute('y1', handles[0].y);
+      axis.setAttribute('x2', handles[1].x);
+      axis.setAttribute('y2', handles[1].y);
+      axis.setAttribute('stroke', 'white');
+      axis.setAttribute('stroke-dasharray', '4 3');
+      const dots = handles.map(h => {
+        const dot = document.createElementNS(svgNS, 'circle');
+        dot.setAttribute('cx', h.x);
+        dot.setAttribute('cy', h.y);
+        dot.setAttribute('r', 6);
+        dot.setAttribute('fill', 'white');
+        dot.setAttribute('stroke', 'black');
+        return dot;
+      });
+      gradientHandleLayer.replaceChildren(axis, ...dots);
+      return index;
+    }
+
+    /**
+     * Apply a default gradient to the fill under (x, y): from its color to the picker color
+     * kind 1 = linear across the bounds, 2 = radial from the center, 0 = back to solid
+     */
+    function applyGradientAt(x, y, kind) {
+      if (typeof wasm.editor_fill_hit_test !== 'function') return;
+      const index = wasm.editor_fill_hit_test(x, y);
+      const paint = fillPaints[index];
+      if (!paint) return;
+      const {minX, minY, maxX, maxY} = paint.bounds;
+      const midY = (minY + maxY) / 2;
+      const midX = (minX + maxX) / 2;
+      const [x1, y1, x2, y2] = kind === 1
+        ? [minX, midY, maxX, midY]
+        : [midX, midY, midX + Math.max(maxX - minX, maxY - minY) / 2, midY];
+      dispatch({
+        type: "SetFillPaint",
+        data: {index, kind, x1, y1, x2, y2, stops: `0 ${paint.hex}; 1 ${fillColor.value}`}
+      }, {source: "user"});
+    }
+
+    /**
+     * Rebuild the swatch chips from the palette
+     * Buffer format: [count, (id, r, g, b, a) * count]
+     */
+    function renderSwatches() {
+      if (!wasm || typeof wasm.editor_swatches_ptr_f32 !== 'function') return;
+      const len = wasm.editor_swatches_len_f32();
+      const ptr = wasm.editor_swatches_ptr_f32();
+      const chips = [];
+      let found = false;
+      if (ptr && len > 0) {
+        const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+        const count = Math.floor(arr[0]);
+        for (let i = 0; i < count; i++) {
+          const base = 1 + i * 5;
+          const id = arr[base];
+          const hex = '#' + [arr[base + 1], arr[base + 2], arr[base + 3]]
+            .map(c => Math.round(c * 255).toString(16).padStart(2, '0')).join('');
+          const chip = document.createElement('button');
+          chip.className = 'swatch';
+          chip.style.background = hex;
+          chip.title = `Swatch ${id}`;
+          if (id === activeSwatch) {
+            chip.classList.add('active');
+            found = true;
+          }
+          chip.addEventListener('click', () => {
+            if (id === activeSwatch) {
+              // Deselect: keep painting with the swatch color as a plain color
+              dispatch({type: "SetFillColor", data: {color: hex}}, {source: "user"});
+            } else {
+              fillColor.value = hex;
+              dispatch({type: "SelectSwatch", data: {id}}, {source: "user"});
+            }
+          });
+          chips.push(chip);
+        }
+      }
+      // Undo may have removed the active swatch
+      if (!found) activeSwatch = 0;
+      swatchesGroup.replaceChildren(...chips);
     }
 
     /**
@@ -1274,6 +1915,112 @@ async function main() {
       }
     }
 
+    /**
+     * Mark dangling endpoints with red dots and draw the suggested closing segment
+     * Buffer format: [count, (ex, ey, tx, ty, gap, kind) * count]
+     */
+    function renderLeakMarkers() {
+      leakLayer.replaceChildren();
+      if (!wasm || typeof wasm.editor_analyze_leaks !== 'function') return;
+
+      const suggestions = wasm.editor_analyze_leaks(0);
+      const len = wasm.editor_leaks_len_f32();
+      const ptr = wasm.editor_leaks_ptr_f32();
+      if (!ptr || len < 1) return;
+
+      const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+      const count = Math.floor(arr[0]);
+      const fragments = [];
+      for (let i = 0; i < count; i++) {
+        const base = 1 + i * 6;
+        const [ex, ey, tx, ty, gap, kind] = arr.slice(base, base + 6);
+        if (kind > 0) {
+          const hint = document.createElementNS('http://www.w3.org/2000/svg', 'line');
+          hint.setAttribute('x1', ex);
+          hint.setAttribute('y1', ey);
+          hint.setAttribute('x2', tx);
+          hint.setAttribute('y2', ty);
+          hint.setAttribute('stroke', '#ef4444');
+          hint.setAttribute('stroke-dasharray', '3 3');
+          fragments.push(hint);
+        }
+        const marker = document.createElementNS('http://www.w3.org/2000/svg', 'circle');
+        marker.setAttribute('cx', ex);
+        marker.setAttribute('cy', ey);
+        marker.setAttribute('r', 5);
+        marker.setAttribute('fill', '#ef4444');
+        if (kind > 0) {
+          const title = document.createElementNS('http://www.w3.org/2000/svg', 'title');
+          title.textContent = `gap ${gap.toFixed(1)}px`;
+          marker.appendChild(title);
+        }
+        fragments.push(marker);
+      }
+      leakLayer.replaceChildren(...fragments);
+      eventRing.add(`fill:leaks endpoints=${count} suggestions=${suggestions}`);
+    }
+
+    /**
+     * Highlight the dangling segments Clean would remove
+     * Buffer format: [count, (x1, y1, x2, y2) * count]
+     */
+    function renderOverhangPreview() {
+      overhangPreviewLayer.replaceChildren();
+      if (!wasm || typeof wasm.editor_preview_overhangs !== 'function') return;
+
+      const count = wasm.editor_preview_overhangs();
+      const len = wasm.editor_overhang_preview_len_f32();
+      const ptr = wasm.editor_overhang_preview_ptr_f32();
+      if (!ptr || count === 0) return;
+
+      const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+      const fragments = [];
+      for (let i = 0; i < count; i++) {
+        const base = 1 + i * 4;
+        const seg = document.createElementNS('http://www.w3.org/2000/svg', 'line');
+        seg.setAttribute('x1', arr[base]);
+        seg.setAttribute('y1', arr[base + 1]);
+        seg.setAttribute('x2', arr[base + 2]);
+        seg.setAttribute('y2', arr[base + 3]);
+        seg.setAttribute('stroke', '#ef4444');
+        seg.setAttribute('stroke-width', 4);
+        seg.setAttribute('stroke-opacity', 0.7);
+        fragments.push(seg);
+      }
+      overhangPreviewLayer.replaceChildren(...fragments);
+    }
+
+    /**
+     * Draw the region a fill click at (x, y) would create, without committing it
+     * Buffer format: [status, count, (x, y) * count]
+     */
+    function renderFillPreview(x, y) {
+      if (!wasm || typeof wasm.editor_fill_preview !== 'function') return;
+
+      const status = wasm.editor_fill_preview(x, y);
+      const len = wasm.editor_fill_preview_len_f32();
+      const ptr = wasm.editor_fill_preview_ptr_f32();
+      if (status !== 0 || !ptr || len < 2) {
+        fillPreviewLayer.replaceChildren();
+        return;
+      }
+
+      const arr = new Float32Array(wasm.memory.buffer, ptr, len);
+      const count = Math.floor(arr[1]);
+      const points = [];
+      for (let i = 0; i < count && 3 + i * 2 < len; i++) {
+        points.push(`${arr[2 + i * 2]},${arr[3 + i * 2]}`);
+      }
+
+      const poly = document.createElementNS('http://www.w3.org/2000/svg', 'polygon');
+      poly.setAttribute('points', points.join(' '));
+      poly.setAttribute('fill', fillColor.value);
+      poly.setAttribute('fill-opacity', '0.35');
+      poly.setAttribute('stroke', fillColor.value);
+      poly.setAttribute('stroke-dasharray', '4 3');
+      fillPreviewLayer.replaceChildren(poly);
+    }
+
     function renderGraphDebug() {
       if (!wasm || !graphDebugMode) {
         graphDebugLayer.replaceChildren();
@@ -1374,11 +2121,12 @@ async function main() {
     /**
      * Allocates from the end of memory buffer
      * @param {string} str - string to encode
+     * @param {number} offset - byte offset into the 256-byte scratch area (for a second string)
      * @returns {{ptr: number, len: number}} memory pointer and length
      */
-    function encodeStringToWasm(str) {
+    function encodeStringToWasm(str, offset = 0) {
       const buffer = new TextEncoder().encode(str);
-      const ptr = wasm.memory.buffer.byteLength - 256;
+      const ptr = wasm.memory.buffer.byteLength - 256 + offset;
       const view = new Uint8Array(wasm.memory.buffer, ptr, buffer.length);
       view.set(buffer);
       return { ptr, len: buffer.length };
@@ -1396,7 +2144,7 @@ async function main() {
       metrics.lastPressure = evt.pressure >= 0 ? evt.pressure : -1;
       metrics.lastPointerType = evt.pointerType || '-';
       metrics.pointerState = 'down';
-      metrics.currentTool = fillMode ? 'fill' : 'draw';
+      metrics.currentTool = fillMode ? 'fill' : lineTool || 'draw';
       metrics.lastEvtType = 'pdown';
       metrics.lastEvtTs = performance.now();
       
@@ -1405,6 +2153,43 @@ async function main() {
       
       canvas.setPointerCapture(evt.pointerId);
 
+      // Alt+click in fill mode picks the color under the pointer
+      if (fillMode && evt.altKey && typeof wasm.editor_color_at === 'function') {
+        const rgba = wasm.editor_color_at(point.x, point.y) >>> 0;
+        const hex = '#' + (rgba >>> 8).toString(16).padStart(6, '0');
+        dispatch({type: "SetFillColor", data: {color: hex}}, {source: "user"});
+        eventRing.add(`fill:pick ${hex}`);
+        return;
+      }
+
+      if (lineTool === 'select') {
+        selectStart = {x: point.x, y: point.y, add: evt.shiftKey};
+        return;
+      }
+
+      if (lineTool === 'erase') {
+        erasePoints = [point];
+        renderEraserPath();
+        return;
+      }
+
+      if (lineTool) {
+        const type = lineTool === 'cut' ? "TrimAt" : "ExtendAt";
+        dispatch({type, data: {x: point.x, y: point.y}}, {source: "user"});
+        return;
+      }
+
+      // Grab a gradient handle of the hovered fill instead of filling
+      if (fillMode && typeof wasm.editor_fill_hit_test === 'function') {
+        const index = wasm.editor_fill_hit_test(point.x, point.y);
+        const handles = fillPaints[index]?.handles || [];
+        const handle = handles.findIndex(h => Math.hypot(h.x - point.x, h.y - point.y) <= 8);
+        if (handle >= 0) {
+          handleDrag = {fill: index, handle};
+          return;
+        }
+      }
+
       if (fillMode) {
         // Stay in fill mode after filling
         
@@ -1414,8 +2199,12 @@ async function main() {
           data: {x: point.x, y: point.y, color: fillColor.value}
         }, {source: "user"});
       } else {
-        startPoint = point;
+        startPoint = snapPoint(point, null, evt);
         dragging = true;
+        if (toolMode === 'polyline' || toolMode === 'curve') {
+          polylinePoints = [startPoint];
+          renderPolylinePreview();
+        }
         if (showLines) {
           preview.classList.add('active');
         }
@@ -1441,17 +2230,68 @@ async function main() {
       metrics.lastEvtTs = performance.now();
       
       const pos = toSvgPoint(evt);
+      hoverPoint = pos;
       
       // Update debug overlay anytime if enabled (not just during drawing)
       if (debugMode && wasm) {
         wasm.editor_nearest(pos.x, pos.y);
         updateDebugOverlay(pos.x, pos.y);
       }
+
+      // Selection rectangle follows the pointer until release
+      if (selectStart) {
+        selectionRect.setAttribute('x', Math.min(selectStart.x, pos.x));
+        selectionRect.setAttribute('y', Math.min(selectStart.y, pos.y));
+        selectionRect.setAttribute('width', Math.abs(pos.x - selectStart.x));
+        selectionRect.setAttribute('height', Math.abs(pos.y - selectStart.y));
+        selectionRect.classList.add('active');
+        return;
+      }
+
+      // Eraser stroke: collect the brush path, erased on release as one undo step
+      if (erasePoints) {
+        const last = erasePoints[erasePoints.length - 1];
+        if (Math.hypot(pos.x - last.x, pos.y - last.y) >= ERASER_RADIUS / 2) {
+          erasePoints.push(pos);
+          renderEraserPath();
+        }
+        return;
+      }
+
+      // Dragging a gradient handle: only move the handle overlay until release
+      if (handleDrag) {
+        renderGradientHandles(pos.x, pos.y, handleDrag);
+        return;
+      }
+
+      // Show the region a click would fill
+      if (fillMode && !dragging) {
+        renderFillPreview(pos.x, pos.y);
+        if (typeof wasm.editor_fill_hit_test === 'function') {
+          renderGradientHandles(pos.x, pos.y);
+        }
+      }
       
+      // Snap while drawing; hovering shows where a new line would start
+      let end = pos;
+      if (!fillMode && !lineTool) {
+        const anchor = polylinePoints ? polylinePoints[polylinePoints.length - 1] : startPoint;
+        end = snapPoint(pos, dragging ? anchor : null, evt);
+      } else {
+        snapIndicator.classList.remove('active');
+      }
+
       // Only update preview if actively drawing
       if (!dragging) return;
-      preview.setAttribute('x2', pos.x);
-      preview.setAttribute('y2', pos.y);
+      if (polylinePoints) {
+        if (toolMode === 'curve') addCurvePoint(pos);
+        else commitPolylinePoint(end);
+        const anchor = polylinePoints[polylinePoints.length - 1];
+        preview.setAttribute('x1', anchor.x);
+        preview.setAttribute('y1', anchor.y);
+      }
+      preview.setAttribute('x2', end.x);
+      preview.setAttribute('y2', end.y);
     });
 
     /**
@@ -1516,12 +2356,24 @@ async function main() {
       metrics.lastPointerId = evt.pointerId;
       eventRing.add(`EVT pcancel id=${evt.pointerId}`);
       dragging = false;
+      handleDrag = null;
+      erasePoints = null;
+      eraserPath.classList.remove('active');
+      polylinePoints = null;
+      polylinePreview.classList.remove('active');
+      selectStart = null;
+      selectionRect.classList.remove('active');
       preview.classList.remove('active');
     });
     
     canvas.addEventListener('pointerleave', () => {
       dragging = false;
+      hoverPoint = null;
       preview.classList.remove('active');
+      snapIndicator.classList.remove('active');
+      polylinePoints = null;
+      polylinePreview.classList.remove('active');
+      if (!handleDrag) clearFillOverlays();
       if (debugMode) {
         debugNearestLine.classList.remove('active');
         debugNearestPoint.classList.remove('active');
@@ -1540,10 +2392,61 @@ async function main() {
       metrics.lastEvtTs = performance.now();
       metrics.lastPointerId = evt.pointerId;
       
+      if (selectStart && wasm) {
+        const start = selectStart;
+        const end = toSvgPoint(evt);
+        selectStart = null;
+        selectionRect.classList.remove('active');
+        // A click (no real drag) picks the item under the pointer
+        if (Math.hypot(end.x - start.x, end.y - start.y) < 3) {
+          dispatch({type: "Select", data: {x: end.x, y: end.y, add: start.add}}, {source: "user"});
+        } else {
+          // Dragging right-to-left also selects what the box crosses
+          const partial = end.x < start.x;
+          dispatch({type: "SelectRect", data: {x1: start.x, y1: start.y, x2: end.x, y2: end.y, add: start.add, partial}}, {source: "user"});
+        }
+        return;
+      }
+
+      if (erasePoints && wasm) {
+        const points = erasePoints;
+        erasePoints = null;
+        eraserPath.classList.remove('active');
+        dispatch({type: "Erase", data: {points, radius: ERASER_RADIUS}}, {source: "user"});
+        return;
+      }
+
+      if (handleDrag && wasm) {
+        const end = toSvgPoint(evt);
+        const {fill, handle} = handleDrag;
+        handleDrag = null;
+        dispatch({
+          type: "MoveGradientHandle",
+          data: {index: fill, handle, x: end.x, y: end.y}
+        }, {source: "user"});
+        return;
+      }
+
       if (!dragging || !wasm) return;
       dragging = false;
       preview.classList.remove('active');
-      const end = toSvgPoint(evt);
+      if (polylinePoints) {
+        const points = polylinePoints;
+        const last = points[points.length - 1];
+        const end = snapPoint(toSvgPoint(evt), last, evt);
+        if (Math.hypot(end.x - last.x, end.y - last.y) >= 1) points.push(end);
+        polylinePoints = null;
+        polylinePreview.classList.remove('active');
+        snapIndicator.classList.remove('active');
+        if (toolMode === 'curve') {
+          dispatch({type: "AddCurve", data: {points, method: CURVE_METHOD, tolerance: CURVE_TOLERANCE}}, {source: "user"});
+        } else {
+          dispatch({type: "AddPolyline", data: {points}}, {source: "user"});
+        }
+        return;
+      }
+      const end = snapPoint(toSvgPoint(evt), startPoint, evt);
+      snapIndicator.classList.remove('active');
       console.log(`Line added: (${startPoint.x.toFixed(1)},${startPoint.y.toFixed(1)}) -> (${end.x.toFixed(1)},${end.y.toFixed(1)})`);
       
       // Use dispatcher for recording support
@@ -1555,15 +2458,166 @@ async function main() {
       console.log('Total lines now:', wasm.editor_line_count());
     }
 
+    /**
+     * Snap a drawing point to nearby topology, the grid or (Shift, from the anchor) 15° steps
+     * @param {{x: number, y: number}} pos
+     * @param {{x: number, y: number} | null} anchor - start of the line being drawn
+     * @param {PointerEvent} evt
+     * @returns {{x: number, y: number}}
+     */
+    function snapPoint(pos, anchor, evt) {
+      if (typeof wasm.editor_snap !== 'function') return pos;
+      let flags = SNAP_POINTS;
+      if (snapToGrid) flags |= SNAP_GRID;
+      if (anchor && evt.shiftKey) flags |= SNAP_ANGLE;
+      const kind = wasm.editor_snap(pos.x, pos.y, anchor ? anchor.x : NaN, anchor ? anchor.y : NaN, flags);
+      if (!kind) {
+        snapIndicator.classList.remove('active');
+        return pos;
+      }
+      const buf = new Float32Array(wasm.memory.buffer, wasm.editor_snap_ptr_f32(), wasm.editor_snap_len_f32());
+      snapIndicator.setAttribute('cx', buf[1]);
+      snapIndicator.setAttribute('cy', buf[2]);
+      snapIndicator.setAttribute('class', `active ${SNAP_KIND_NAMES[kind]}`);
+      return {x: buf[1], y: buf[2]};
+    }
+
+    /**
+     * Add the pointer position to the polyline in progress once it is POLYLINE_MIN_SEG
+     * from the last anchor; a nearly straight continuation moves the last anchor instead
+     * @param {{x: number, y: number}} pos
+     */
+    function commitPolylinePoint(pos) {
+      const last = polylinePoints[polylinePoints.length - 1];
+      const dx = pos.x - last.x;
+      const dy = pos.y - last.y;
+      const len = Math.hypot(dx, dy);
+      if (len < POLYLINE_MIN_SEG) return;
+      if (polylinePoints.length >= 2) {
+        const prev = polylinePoints[polylinePoints.length - 2];
+        const px = last.x - prev.x;
+        const py = last.y - prev.y;
+        const cos = (px * dx + py * dy) / (Math.hypot(px, py) * len);
+        if (cos > POLYLINE_COLLINEAR_COS) {
+          polylinePoints[polylinePoints.length - 1] = pos;
+          renderPolylinePreview();
+          return;
+        }
+      }
+      polylinePoints.push(pos);
+      renderPolylinePreview();
+    }
+
+    /**
+     * Record the raw pointer track of a curve stroke; the engine smooths it on release
+     * @param {{x: number, y: number}} pos
+     */
+    function addCurvePoint(pos) {
+      const last = polylinePoints[polylinePoints.length - 1];
+      if (Math.hypot(pos.x - last.x, pos.y - last.y) < 1) return;
+      polylinePoints.push(pos);
+      renderPolylinePreview();
+    }
+
+    function renderPolylinePreview() {
+      polylinePreview.setAttribute('points', polylinePoints.map(p => `${p.x},${p.y}`).join(' '));
+      polylinePreview.classList.toggle('active', showLines);
+    }
+
+    function setLineTool(tool) {
+      lineTool = tool;
+      snapIndicator.classList.remove('active');
+      scissorsBtn.classList.toggle('active', tool === 'cut');
+      extendBtn.classList.toggle('active', tool === 'extend');
+      eraserBtn.classList.toggle('active', tool === 'erase');
+      selectBtn.classList.toggle('active', tool === 'select');
+      canvas.style.cursor = tool ? 'crosshair' : 'default';
+    }
+
+    /**
+     * Flash the welds of the last added line: a jump from where the end was drawn
+     * to where it landed. Buffer: [count, (end, kind, fromX, fromY, toX, toY) * count]
+     */
+    function renderWelds() {
+      if (typeof wasm.editor_welds_ptr_f32 !== 'function') return;
+      const len = wasm.editor_welds_len_f32();
+      if (len < 1) return;
+      const buf = new Float32Array(wasm.memory.buffer, wasm.editor_welds_ptr_f32(), len);
+      const svgNS = 'http://www.w3.org/2000/svg';
+      for (let i = 0; i < buf[0]; i++) {
+        const [, kind, fromX, fromY, toX, toY] = buf.subarray(1 + i * 6, 7 + i * 6);
+        const jump = document.createElementNS(svgNS, 'line');
+        jump.setAttribute('x1', fromX);
+        jump.setAttribute('y1', fromY);
+        jump.setAttribute('x2', toX);
+        jump.setAttribute('y2', toY);
+        const dot = document.createElementNS(svgNS, 'circle');
+        dot.setAttribute('cx', toX);
+        dot.setAttribute('cy', toY);
+        dot.setAttribute('r', kind === 2 ? 4 : 3);
+        weldLayer.append(jump, dot);
+        setTimeout(() => { jump.remove(); dot.remove(); }, 600);
+        eventRing.add(`weld ${kind === 2 ? 'segment' : 'endpoint'} x=${toX.toFixed(0)} y=${toY.toFixed(0)}`);
+      }
+    }
+
+    function renderEraserPath() {
+      eraserPath.setAttribute('points', erasePoints.map(p => `${p.x},${p.y}`).join(' '));
+      eraserPath.setAttribute('stroke-width', ERASER_RADIUS * 2);
+      eraserPath.classList.add('active');
+    }
+
+    scissorsBtn.addEventListener('click', () => {
+      if (fillMode) fillBtn.click();
+      setLineTool(lineTool === 'cut' ? null : 'cut');
+    });
+
+    extendBtn.addEventListener('click', () => {
+      if (fillMode) fillBtn.click();
+      setLineTool(lineTool === 'extend' ? null : 'extend');
+    });
+
+    eraserBtn.addEventListener('click', () => {
+      if (fillMode) fillBtn.click();
+      setLineTool(lineTool === 'erase' ? null : 'erase');
+    });
+
+    selectBtn.addEventListener('click', () => {
+      if (fillMode) fillBtn.click();
+      setLineTool(lineTool === 'select' ? null : 'select');
+    });
+
+    modeBtn.addEventListener('click', () => {
+      const modes = {freehand: ['polyline', 'Polyline'], polyline: ['curve', 'Curve'], curve: ['freehand', 'Freehand']};
+      [toolMode, modeBtn.textContent] = modes[toolMode];
+      modeBtn.classList.toggle('active', toolMode !== 'freehand');
+    });
+
+    snapGridBtn.addEventListener('click', () => {
+      snapToGrid = !snapToGrid;
+      snapGridBtn.classList.toggle('active', snapToGrid);
+    });
+
     fillBtn.addEventListener('click', () => {
+      if (lineTool) setLineTool(null);
       fillMode = !fillMode;
       fillBtn.classList.toggle('active');
-      if (!fillMode) canvas.style.cursor = 'default';
-      else canvas.style.cursor = 'crosshair';
+      if (!fillMode) {
+        canvas.style.cursor = 'default';
+        clearFillOverlays();
+      } else {
+        canvas.style.cursor = 'crosshair';
+      }
     });
 
     fillColor.addEventListener('change', () => {
-      if (wasm) {
+      if (wasm && activeSwatch > 0) {
+        // Editing the active swatch recolors every fill that uses it
+        dispatch({
+          type: "SetSwatchColor",
+          data: {id: activeSwatch, color: fillColor.value}
+        }, {source: "user"});
+      } else if (wasm) {
         dispatch({
           type: "SetFillColor",
           data: {color: fillColor.value}
@@ -1571,12 +2625,21 @@ async function main() {
       }
       // Auto-enable fill mode when color is changed
       if (!fillMode) {
+        if (lineTool) setLineTool(null);
         fillMode = true;
         fillBtn.classList.add('active');
         canvas.style.cursor = 'crosshair';
       }
     });
 
+    addSwatchBtn.addEventListener('click', () => {
+      if (!wasm) return;
+      dispatch({
+        type: "AddSwatch",
+        data: {name: `swatch-${swatchesGroup.children.length + 1}`, color: fillColor.value}
+      }, {source: "user"});
+    });
+
     debugBtn.addEventListener('click', () => {
       dispatch({
         type: "ToggleDebug",
@@ -1622,7 +2685,18 @@ async function main() {
       }
     });
 
+    trimBtn.addEventListener('click', () => {
+      if (wasm) {
+        dispatch({type: "Trim", data: {}}, {source: "user"});
+      }
+    });
+
+    // Hovering Clean highlights the segments it would remove
+    cleanBtn.addEventListener('pointerenter', () => renderOverhangPreview());
+    cleanBtn.addEventListener('pointerleave', () => overhangPreviewLayer.replaceChildren());
+
     cleanBtn.addEventListener('click', () => {
+      overhangPreviewLayer.replaceChildren();
       if (wasm) {
         dispatch({type: "Clean", data: {}}, {source: "user"});
       }
@@ -1661,12 +2735,77 @@ async function main() {
 
     // Escape key handler - turn off fill, debug, and graph modes
     document.addEventListener('keydown', (evt) => {
+      // Fill mode: Delete removes the fill under the cursor, [ and ] change its z-order
+      if (fillMode && hoverPoint && wasm) {
+        const {x, y} = hoverPoint;
+        if (evt.key === 'Delete' || evt.key === 'Backspace') {
+          dispatch({type: "DeleteFill", data: {x, y}}, {source: "user"});
+          return;
+        }
+        if (evt.key === ']' || evt.key === '[') {
+          dispatch({type: "MoveFill", data: {x, y, direction: evt.key === ']' ? 1 : -1}}, {source: "user"});
+          return;
+        }
+        // g = linear gradient, r = radial gradient, s = back to solid
+        const gradientKind = {g: 1, r: 2, s: 0}[evt.key];
+        if (gradientKind !== undefined) {
+          applyGradientAt(x, y, gradientKind);
+          return;
+        }
+        // h = hatch, x = crosshatch, d = dots
+        const patternKind = {h: 0, x: 1, d: 2}[evt.key];
+        if (patternKind !== undefined) {
+          dispatch({type: "SetFillPattern", data: {x, y, kind: patternKind, angle: 45, spacing: 8}}, {source: "user"});
+          return;
+        }
+      }
+
+      // Select tool: arrows move (Shift = 10px), r / R rotate 15 degrees, + / - scale, f / v flip,
+      // s / S simplify (RDP / Visvalingam), Ctrl+C / Ctrl+V copy and paste, Ctrl+D duplicates
+      if (lineTool === 'select' && wasm) {
+        if (evt.ctrlKey || evt.metaKey) {
+          const key = evt.key.toLowerCase();
+          if (key === 'c' || key === 'd') dispatch({type: "Copy", data: {}}, {source: "user"});
+          if (key === 'v' || key === 'd') {
+            const blob = localStorage.getItem(CLIPBOARD_KEY);
+            dispatch({type: "Paste", data: {blob, dx: PASTE_OFFSET, dy: PASTE_OFFSET}}, {source: "user"});
+          }
+          if (key === 'c' || key === 'v' || key === 'd') evt.preventDefault();
+          return;
+        }
+        const step = evt.shiftKey ? 10 : 1;
+        const arrows = {ArrowLeft: [-step, 0], ArrowRight: [step, 0], ArrowUp: [0, -step], ArrowDown: [0, step]};
+        let data = null;
+        if (arrows[evt.key]) data = {op: 'translate', dx: arrows[evt.key][0], dy: arrows[evt.key][1]};
+        if (evt.key === 'r' || evt.key === 'R') data = {op: 'rotate', angle: evt.key === 'r' ? 15 : -15};
+        if (evt.key === '+' || evt.key === '=') data = {op: 'scale', factor: 1.1};
+        if (evt.key === '-') data = {op: 'scale', factor: 1 / 1.1};
+        if (evt.key === 'f' || evt.key === 'v') data = {op: 'flip', horizontal: evt.key === 'f'};
+        if (evt.key === 's' || evt.key === 'S') {
+          evt.preventDefault();
+          dispatch({type: "SimplifySelection", data: {tolerance: SIMPLIFY_TOLERANCE, method: evt.key === 's' ? 0 : 1}}, {source: "user"});
+          return;
+        }
+        if (data) {
+          evt.preventDefault();
+          dispatch({type: "TransformSelection", data}, {source: "user"});
+          return;
+        }
+      }
+
       if (evt.key === 'Escape') {
+        if (lineTool === 'select' && typeof wasm?.editor_select_clear === 'function') {
+          wasm.editor_select_clear();
+          renderSelection();
+        }
+        if (lineTool) setLineTool(null);
+
         // Turn off fill mode
         if (fillMode) {
           fillMode = false;
           fillBtn.classList.remove('active');
           canvas.style.cursor = 'default';
+          clearFillOverlays();
         }
         
         // Turn off debug mode
@@ -1715,6 +2854,14 @@ async function main() {
   const colorView = new Uint8Array(wasm.memory.buffer, colorPtr, colorBytes.length);
   colorView.set(colorBytes);
   wasm.editor_set_fill_color(colorPtr, colorBytes.length);
+
+  // Eyedropper falls back to the canvas background
+  if (typeof wasm.editor_set_background_color === 'function') {
+    const bgHex = getComputedStyle(document.documentElement).getPropertyValue('--canvas-bg').trim() || '#ABABAB';
+    const bgBytes = new TextEncoder().encode(bgHex);
+    new Uint8Array(wasm.memory.buffer, colorPtr, bgBytes.length).set(bgBytes);
+    wasm.editor_set_background_color(colorPtr, bgBytes.length);
+  }
   
   // Update viewBox to match viewport size for accurate coordinate mapping
   function updateViewBox() {